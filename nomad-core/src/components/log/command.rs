@@ -0,0 +1,293 @@
+//! LogService Commands
+//!
+//! Commands that ground can send to reconfigure the LogService in flight, and the
+//! housekeeping the LogService reports back.
+
+use crate::{LogLevel, component::ComponentId};
+
+/// Maximum number of per-component level overrides the LogService can hold
+pub const MAX_COMPONENT_LEVELS: usize = 16;
+
+/// Maximum number of sinks that can be individually enabled or disabled
+pub const MAX_SINKS: usize = 32;
+
+/// Commands accepted by the LogService
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LogCommand {
+    /// Sets the global minimum level, records below it are discarded
+    SetLevel(LogLevel),
+    /// Overrides the minimum level of a single component
+    SetComponentLevel(ComponentId, LogLevel),
+    /// Removes the level override of a component, the global level applies again
+    ClearComponentLevel(ComponentId),
+    /// Enables the sink at the given index of the sinks drained into
+    EnableSink(u8),
+    /// Disables the sink at the given index of the sinks drained into
+    DisableSink(u8),
+    /// Discards every pending record
+    ClearBuffer,
+    /// Requests the pending records to be drained into the sinks. Only sets a flag, the
+    /// owner of the sinks drains the service once it sees `drain_requested`.
+    Drain,
+}
+
+/// Reasons a LogCommand can be rejected
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LogCommandError {
+    /// No room left for another component level override
+    TooManyComponentLevels,
+    /// Sink index is out of range
+    InvalidSink(u8),
+}
+
+/// LogService housekeeping, current settings and statistics
+///
+/// * `min_level`: Global minimum level
+/// * `component_levels`: Number of active per-component level overrides
+/// * `sinks_enabled`: Bitmask of enabled sinks, bit N is the sink at index N
+/// * `pending`: Records waiting in the buffer
+/// * `logged`: Records accepted into the buffer
+/// * `filtered`: Records discarded for being below the minimum level
+/// * `overwritten`: Records lost because the buffer was full
+/// * `drained`: Records drained into sinks
+/// * `discarded`: Records drained while every sink was disabled, written nowhere
+/// * `commands_accepted`: Commands that were executed
+/// * `commands_rejected`: Commands that were rejected
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LogHousekeeping {
+    pub min_level: LogLevel,
+    pub component_levels: u8,
+    pub sinks_enabled: u32,
+    pub pending: u32,
+    pub logged: u32,
+    pub filtered: u32,
+    pub overwritten: u32,
+    pub drained: u32,
+    pub discarded: u32,
+    pub commands_accepted: u32,
+    pub commands_rejected: u32,
+}
+
+/// Configuration of the LogService that can be changed by command
+///
+/// * `min_level`: Global minimum level
+/// * `component_levels`: Per-component minimum level overrides
+/// * `sinks_enabled`: Bitmask of enabled sinks
+/// * `drain_requested`: Set by a Drain command until the next drain
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct LogConfig {
+    pub min_level: LogLevel,
+    pub component_levels: [Option<(ComponentId, LogLevel)>; MAX_COMPONENT_LEVELS],
+    pub sinks_enabled: u32,
+    pub drain_requested: bool,
+}
+
+impl LogConfig {
+    pub fn new() -> Self {
+        Self {
+            min_level: LogLevel::Debug,
+            component_levels: [None; MAX_COMPONENT_LEVELS],
+            sinks_enabled: u32::MAX,
+            drain_requested: false,
+        }
+    }
+
+    /// Minimum level that applies to a component
+    pub fn level_for(&self, component: ComponentId) -> LogLevel {
+        self.component_levels
+            .iter()
+            .flatten()
+            .find(|(id, _)| *id == component)
+            .map_or(self.min_level, |(_, level)| *level)
+    }
+
+    /// Whether the sink at `index` is enabled
+    pub fn sink_enabled(&self, index: usize) -> bool {
+        index < MAX_SINKS && self.sinks_enabled & (1 << index) != 0
+    }
+
+    /// Number of active component level overrides
+    pub fn component_level_count(&self) -> u8 {
+        self.component_levels.iter().flatten().count() as u8
+    }
+
+    /// Applies a configuration command. ClearBuffer is left to the LogService as it
+    /// does not touch the configuration.
+    pub fn apply(&mut self, cmd: LogCommand) -> Result<(), LogCommandError> {
+        match cmd {
+            LogCommand::SetLevel(level) => self.min_level = level,
+            LogCommand::SetComponentLevel(component, level) => {
+                let slot = self
+                    .component_levels
+                    .iter()
+                    .position(|e| matches!(e, Some((id, _)) if *id == component))
+                    .or_else(|| self.component_levels.iter().position(Option::is_none))
+                    .ok_or(LogCommandError::TooManyComponentLevels)?;
+                self.component_levels[slot] = Some((component, level));
+            }
+            LogCommand::ClearComponentLevel(component) => {
+                for entry in self.component_levels.iter_mut() {
+                    if matches!(entry, Some((id, _)) if *id == component) {
+                        *entry = None;
+                    }
+                }
+            }
+            LogCommand::EnableSink(index) | LogCommand::DisableSink(index) => {
+                if usize::from(index) >= MAX_SINKS {
+                    return Err(LogCommandError::InvalidSink(index));
+                }
+                if matches!(cmd, LogCommand::EnableSink(_)) {
+                    self.sinks_enabled |= 1 << index;
+                } else {
+                    self.sinks_enabled &= !(1 << index);
+                }
+            }
+            LogCommand::Drain => self.drain_requested = true,
+            LogCommand::ClearBuffer => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LogHandle, LogRecord, LogSink, components::log::LogService};
+
+    /// Sink counting the records written into it
+    #[derive(Default)]
+    struct CountingSink(usize);
+
+    impl LogSink for CountingSink {
+        fn write(&mut self, _record: &LogRecord) {
+            self.0 += 1;
+        }
+    }
+
+    #[test]
+    /// Global and per-component levels filter records
+    fn level_filtering() {
+        let mut logging = LogService::<8>::new();
+        logging
+            .handle_command(LogCommand::SetLevel(LogLevel::Warn))
+            .unwrap();
+        logging
+            .handle_command(LogCommand::SetComponentLevel(
+                ComponentId(2),
+                LogLevel::Debug,
+            ))
+            .unwrap();
+
+        logging.log_message(ComponentId(1), LogLevel::Info, "filtered");
+        logging.log_message(ComponentId(1), LogLevel::Error, "kept");
+        logging.log_message(ComponentId(2), LogLevel::Debug, "kept, raised verbosity");
+
+        let hk = logging.housekeeping();
+        assert_eq!(hk.min_level, LogLevel::Warn);
+        assert_eq!(hk.component_levels, 1);
+        assert_eq!(hk.logged, 2);
+        assert_eq!(hk.filtered, 1);
+        assert_eq!(hk.pending, 2);
+
+        // Once the override is cleared the global level applies again
+        logging
+            .handle_command(LogCommand::ClearComponentLevel(ComponentId(2)))
+            .unwrap();
+        logging.log_message(ComponentId(2), LogLevel::Debug, "filtered");
+        assert_eq!(logging.housekeeping().filtered, 2);
+        assert_eq!(logging.housekeeping().component_levels, 0);
+    }
+
+    #[test]
+    /// The override table is bounded
+    fn too_many_component_levels() {
+        let mut logging = LogService::<4>::new();
+        for id in 0..MAX_COMPONENT_LEVELS as u16 {
+            logging
+                .handle_command(LogCommand::SetComponentLevel(
+                    ComponentId(id),
+                    LogLevel::Info,
+                ))
+                .unwrap();
+        }
+        // Updating an existing override needs no new slot
+        assert_eq!(
+            logging.handle_command(LogCommand::SetComponentLevel(
+                ComponentId(0),
+                LogLevel::Error
+            )),
+            Ok(())
+        );
+        assert_eq!(
+            logging.handle_command(LogCommand::SetComponentLevel(
+                ComponentId(MAX_COMPONENT_LEVELS as u16),
+                LogLevel::Info
+            )),
+            Err(LogCommandError::TooManyComponentLevels)
+        );
+        assert_eq!(logging.housekeeping().commands_rejected, 1);
+    }
+
+    #[test]
+    /// Disabled sinks are skipped when draining
+    fn sink_enable() {
+        let mut logging = LogService::<4>::new();
+        let mut sinks = [CountingSink::default(), CountingSink::default()];
+
+        logging.handle_command(LogCommand::DisableSink(1)).unwrap();
+        logging.log_message(ComponentId(0), LogLevel::Info, "one");
+        logging.drain_multi(&mut sinks);
+        assert_eq!((sinks[0].0, sinks[1].0), (1, 0));
+
+        logging.handle_command(LogCommand::EnableSink(1)).unwrap();
+        logging.log_message(ComponentId(0), LogLevel::Info, "two");
+        logging.drain_multi(&mut sinks);
+        assert_eq!((sinks[0].0, sinks[1].0), (2, 1));
+
+        assert_eq!(
+            logging.handle_command(LogCommand::DisableSink(MAX_SINKS as u8)),
+            Err(LogCommandError::InvalidSink(MAX_SINKS as u8))
+        );
+        assert_eq!(logging.housekeeping().drained, 2);
+
+        // Records drained while every sink is disabled reach nothing and are discarded
+        logging.handle_command(LogCommand::DisableSink(0)).unwrap();
+        logging.log_message(ComponentId(0), LogLevel::Info, "three");
+        let mut sink = CountingSink::default();
+        logging.drain(&mut sink);
+        assert_eq!(sink.0, 0);
+        let hk = logging.housekeeping();
+        assert_eq!((hk.drained, hk.discarded, hk.pending), (2, 1, 0));
+    }
+
+    #[test]
+    /// Clearing the buffer and requesting a drain
+    fn clear_and_drain() {
+        let mut logging = LogService::<4>::new();
+        logging.log_message(ComponentId(0), LogLevel::Info, "one");
+        logging.handle_command(LogCommand::ClearBuffer).unwrap();
+        assert!(!logging.has_pending());
+
+        logging.log_message(ComponentId(0), LogLevel::Info, "two");
+        logging.handle_command(LogCommand::Drain).unwrap();
+        assert!(logging.drain_requested());
+
+        let mut sink = CountingSink::default();
+        logging.drain(&mut sink);
+        assert_eq!(sink.0, 1);
+        assert!(!logging.drain_requested());
+    }
+
+    #[test]
+    /// Overwritten records are counted
+    fn overwrite_statistics() {
+        let mut logging = LogService::<2>::new();
+        for _ in 0..5 {
+            logging.log_message(ComponentId(0), LogLevel::Info, "spam");
+        }
+        let hk = logging.housekeeping();
+        assert_eq!(hk.logged, 5);
+        assert_eq!(hk.overwritten, 3);
+        assert_eq!(hk.pending, 2);
+    }
+}
@@ -0,0 +1,354 @@
+//! LogService Downlink
+//!
+//! Packages the contents of the LogService ring buffer as telemetry packets, so operators
+//! can retrieve recent logs over the radio after an anomaly.
+//!
+//! Every packet starts with a header followed by as many binary encoded LogRecords
+//! (see `LogRecord::encode`) as fit:
+//! `[sequence: u16][flags: u8][record count: u8][records...]`
+
+use crate::{LogLevel, LogRecord, RecordDecodeError, logger::RECORD_HEADER_LEN};
+
+/// Size of the log dump packet header
+pub const LOG_DUMP_HEADER_LEN: usize = 4;
+
+/// Flag set on the final packet of a dump
+pub const LOG_DUMP_FLAG_LAST: u8 = 0x01;
+
+/// Command requesting a dump of the LogService buffer as telemetry.
+///
+/// * `last`: Only dump the newest N matching records, all records when None
+/// * `min_level`: Only dump records at or above this level, all levels when None
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct LogDumpCommand {
+    pub last: Option<usize>,
+    pub min_level: Option<LogLevel>,
+}
+
+impl LogDumpCommand {
+    /// Whether a record passes the level filter of the command
+    pub(crate) fn accepts(&self, record: &LogRecord) -> bool {
+        self.min_level.is_none_or(|min| record.level >= min)
+    }
+}
+
+/// Reasons a log dump fails
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DumpError {
+    /// The packet buffer cannot hold the header and a record without message
+    BufferTooSmall,
+}
+
+/// Header of a log dump packet
+///
+/// * `sequence`: Index of the packet within the dump, starting at 0
+/// * `last`: Whether this is the final packet of the dump
+/// * `count`: Number of records in the packet
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LogDumpHeader {
+    pub sequence: u16,
+    pub last: bool,
+    pub count: u8,
+}
+
+/// Packs records into packets of `packet.len()` bytes, calling `emit` for every complete
+/// packet. A dump always ends with a packet flagged as last, which is empty when there
+/// are no records. Returns the number of packets emitted.
+///
+/// Messages of records that do not fit an empty packet are truncated.
+pub(crate) fn packetize<'a, I, F>(
+    records: I,
+    packet: &mut [u8],
+    mut emit: F,
+) -> Result<usize, DumpError>
+where
+    I: Iterator<Item = &'a LogRecord>,
+    F: FnMut(&[u8]),
+{
+    // Need room for the header and at least one record without message
+    if packet.len() < LOG_DUMP_HEADER_LEN + RECORD_HEADER_LEN {
+        return Err(DumpError::BufferTooSmall);
+    }
+
+    // The sequence number wraps in long dumps, the packets are counted separately
+    let mut packets = 0;
+    let mut sequence: u16 = 0;
+    let mut len = LOG_DUMP_HEADER_LEN;
+    let mut count: u8 = 0;
+
+    for record in records {
+        let full = count == u8::MAX || len + record.encoded_len() > packet.len();
+        if full && count > 0 {
+            finish_packet(packet, sequence, false, count);
+            emit(&packet[..len]);
+            packets += 1;
+            sequence = sequence.wrapping_add(1);
+            len = LOG_DUMP_HEADER_LEN;
+            count = 0;
+        }
+
+        len += match record.encode(&mut packet[len..]) {
+            Some(n) => n,
+            None => truncated(record, packet.len() - len)
+                .encode(&mut packet[len..])
+                .unwrap_or(0),
+        };
+        count += 1;
+    }
+
+    finish_packet(packet, sequence, true, count);
+    emit(&packet[..len]);
+    Ok(packets + 1)
+}
+
+/// Copy of a record with its message shortened to fit `space` bytes when encoded
+fn truncated(record: &LogRecord, space: usize) -> LogRecord<&str> {
+    let mut end = space
+        .saturating_sub(RECORD_HEADER_LEN)
+        .min(record.message.len());
+    while !record.message.is_char_boundary(end) {
+        end -= 1;
+    }
+    LogRecord {
+        level: record.level,
+        component: record.component,
+        timestamp: record.timestamp,
+        message: &record.message[..end],
+    }
+}
+
+fn finish_packet(packet: &mut [u8], sequence: u16, last: bool, count: u8) {
+    packet[0..2].copy_from_slice(&sequence.to_le_bytes());
+    packet[2] = if last { LOG_DUMP_FLAG_LAST } else { 0 };
+    packet[3] = count;
+}
+
+/// Parses a log dump packet, returning its header and the records it contains.
+/// Intended for ground tools and tests.
+pub fn parse_log_dump_packet(
+    packet: &[u8],
+) -> Result<(LogDumpHeader, LogDumpRecords<'_>), RecordDecodeError> {
+    if packet.len() < LOG_DUMP_HEADER_LEN {
+        return Err(RecordDecodeError::Truncated);
+    }
+
+    let header = LogDumpHeader {
+        sequence: u16::from_le_bytes([packet[0], packet[1]]),
+        last: packet[2] & LOG_DUMP_FLAG_LAST != 0,
+        count: packet[3],
+    };
+    Ok((
+        header,
+        LogDumpRecords {
+            buf: &packet[LOG_DUMP_HEADER_LEN..],
+            remaining: header.count,
+        },
+    ))
+}
+
+/// Iterator over the records of a log dump packet
+pub struct LogDumpRecords<'a> {
+    buf: &'a [u8],
+    remaining: u8,
+}
+
+impl<'a> Iterator for LogDumpRecords<'a> {
+    type Item = Result<LogRecord<&'a str>, RecordDecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        match LogRecord::decode(self.buf) {
+            Ok((record, used)) => {
+                self.buf = &self.buf[used..];
+                Some(Ok(record))
+            }
+            Err(e) => {
+                self.remaining = 0;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LogHandle, component::ComponentId, components::log::LogService};
+
+    const MESSAGES: [(LogLevel, &str); 5] = [
+        (LogLevel::Info, "boot"),
+        (LogLevel::Warn, "imu default calibration"),
+        (LogLevel::Debug, "gnss sats=4"),
+        (LogLevel::Error, "nav diverged"),
+        (LogLevel::Info, "safe mode"),
+    ];
+
+    fn service() -> LogService<8> {
+        let mut logging = LogService::new();
+        for (level, message) in MESSAGES {
+            logging.log_message(ComponentId(1), level, message);
+        }
+        logging
+    }
+
+    /// Runs a dump and collects the messages of every packet
+    fn dump(
+        logging: &LogService<8>,
+        cmd: LogDumpCommand,
+        packet_len: usize,
+    ) -> ([&'static str; 8], usize, usize) {
+        let mut packet = [0u8; 256];
+        let mut messages = [""; 8];
+        let mut n = 0;
+        let mut expected_seq = 0;
+
+        let dumped = logging.dump_telemetry(&cmd, &mut packet[..packet_len], |bytes| {
+            let (header, records) = parse_log_dump_packet(bytes).unwrap();
+            assert!(bytes.len() <= packet_len);
+            assert_eq!(header.sequence, expected_seq);
+            expected_seq += 1;
+            for record in records {
+                // Decoded messages borrow the packet, map them back onto the static originals
+                let msg = record.unwrap().message;
+                messages[n] = MESSAGES
+                    .iter()
+                    .find(|(_, m)| *m == msg)
+                    .map_or("?", |(_, m)| m);
+                n += 1;
+            }
+        });
+        (messages, n, dumped.unwrap())
+    }
+
+    #[test]
+    /// Everything fits a single packet
+    fn dumps_all_records() {
+        let (messages, n, packets) = dump(&service(), LogDumpCommand::default(), 256);
+        assert_eq!(packets, 1);
+        assert_eq!(n, 5);
+        assert_eq!(messages[0], "boot");
+        assert_eq!(messages[4], "safe mode");
+    }
+
+    #[test]
+    /// Filtering by level and taking the newest records
+    fn filters_records() {
+        let cmd = LogDumpCommand {
+            last: Some(2),
+            min_level: Some(LogLevel::Info),
+        };
+        let (messages, n, _) = dump(&service(), cmd, 256);
+        assert_eq!(n, 2);
+        assert_eq!(&messages[..2], &["nav diverged", "safe mode"]);
+    }
+
+    #[test]
+    /// Records are split over several packets, only the final one is flagged as last
+    fn splits_packets() {
+        let logging = service();
+        let mut packet = [0u8; 40];
+        let mut flags = [false; 8];
+        let mut count = 0;
+
+        let packets = logging
+            .dump_telemetry(&LogDumpCommand::default(), &mut packet, |bytes| {
+                let (header, _) = parse_log_dump_packet(bytes).unwrap();
+                flags[count] = header.last;
+                count += 1;
+            })
+            .unwrap();
+
+        assert!(packets > 1);
+        assert_eq!(count, packets);
+        assert!(flags[..packets - 1].iter().all(|last| !last));
+        assert!(flags[packets - 1]);
+
+        // Nothing was lost in the split
+        let (_, n, _) = dump(&logging, LogDumpCommand::default(), 40);
+        assert_eq!(n, 5);
+    }
+
+    #[test]
+    /// An empty buffer still produces a single empty packet flagged as last
+    fn empty_dump() {
+        let logging = LogService::<4>::new();
+        let mut packet = [0u8; 64];
+        let mut headers = [None; 2];
+        let mut count = 0;
+
+        logging
+            .dump_telemetry(&LogDumpCommand::default(), &mut packet, |bytes| {
+                headers[count] = Some(parse_log_dump_packet(bytes).unwrap().0);
+                count += 1;
+            })
+            .unwrap();
+
+        assert_eq!(count, 1);
+        assert_eq!(
+            headers[0],
+            Some(LogDumpHeader {
+                sequence: 0,
+                last: true,
+                count: 0
+            })
+        );
+    }
+
+    #[test]
+    /// Records too long for a packet are truncated instead of dropped
+    fn truncates_long_messages() {
+        let mut logging = LogService::<2>::new();
+        logging.log_message(ComponentId(1), LogLevel::Info, "this message is too long");
+
+        let mut packet = [0u8; LOG_DUMP_HEADER_LEN + RECORD_HEADER_LEN + 4];
+        logging
+            .dump_telemetry(&LogDumpCommand::default(), &mut packet, |bytes| {
+                let (_, mut records) = parse_log_dump_packet(bytes).unwrap();
+                assert_eq!(records.next().unwrap().unwrap().message, "this");
+            })
+            .unwrap();
+
+        // A packet with room for a record without message holds the record, message cut
+        let mut exact = [0u8; LOG_DUMP_HEADER_LEN + RECORD_HEADER_LEN];
+        let mut records = 0;
+        logging
+            .dump_telemetry(&LogDumpCommand::default(), &mut exact, |bytes| {
+                let (_, mut parsed) = parse_log_dump_packet(bytes).unwrap();
+                assert_eq!(parsed.next().unwrap().unwrap().message, "");
+                records += 1;
+            })
+            .unwrap();
+        assert_eq!(records, 1);
+
+        // A packet without room for a record is rejected
+        let mut small = [0u8; LOG_DUMP_HEADER_LEN + RECORD_HEADER_LEN - 1];
+        let dumped = logging.dump_telemetry(&LogDumpCommand::default(), &mut small, |_| {
+            panic!("nothing is emitted")
+        });
+        assert_eq!(dumped, Err(DumpError::BufferTooSmall));
+    }
+
+    #[test]
+    /// Packets are counted past the wrap of the u16 sequence number
+    fn counts_packets_past_sequence_wrap() {
+        let record = LogRecord {
+            level: LogLevel::Info,
+            component: ComponentId(1),
+            timestamp: crate::time::MissionTime::ZERO,
+            message: "".into(),
+        };
+        let records = [record; 1];
+        let total = usize::from(u16::MAX) + 3;
+        let mut packet = [0u8; LOG_DUMP_HEADER_LEN + RECORD_HEADER_LEN + 1];
+        let mut emitted = 0;
+        let packets = packetize(records.iter().cycle().take(total), &mut packet, |_| {
+            emitted += 1
+        });
+        assert_eq!(packets, Ok(total));
+        assert_eq!(emitted, total);
+    }
+}
@@ -2,38 +2,130 @@
 //!
 //! A Logger service provided as part of the Nomad Standard Components Collection
 
+pub mod command;
+pub mod downlink;
+use command::{LogCommand, LogCommandError, LogConfig, LogHousekeeping};
+use downlink::{DumpError, LogDumpCommand};
+
+use core::fmt;
+
 use crate::{
     LogLevel,
     component::ComponentId,
-    logger::{LogBuffer, LogHandle, LogRecord, LogSink},
+    logger::{FormattedLogHandle, LogBuffer, LogHandle, LogMessage, LogRecord, LogSink},
+    time::{NoTimeSource, TimeSource},
 };
 
 /// Standard logging service provided by Nomad
-pub struct LogService<const CAP: usize> {
+///
+/// * `buf`: Ring buffer of pending records
+/// * `clock`: TimeSource records are stamped with, records are stamped with zero when none
+///   is provided
+/// * `config`: Levels and sink selection, changeable by LogCommand
+/// * `stats`: Statistics reported in housekeeping
+pub struct LogService<const CAP: usize, T: TimeSource = NoTimeSource> {
     // NOTE: NOT THREADSAFE
     // TODO: Make threadsafe once threading and syncronization is provided
     buf: LogBuffer<CAP>,
+    clock: T,
+    config: LogConfig,
+    stats: LogStats,
+}
+
+/// Counters reported in LogService housekeeping
+#[derive(Copy, Clone, Debug, Default)]
+struct LogStats {
+    logged: u32,
+    filtered: u32,
+    overwritten: u32,
+    drained: u32,
+    discarded: u32,
+    commands_accepted: u32,
+    commands_rejected: u32,
 }
 
 impl<const CAP: usize> LogService<CAP> {
     pub fn new() -> Self {
+        Self::with_time_source(NoTimeSource)
+    }
+}
+
+impl<const CAP: usize, T: TimeSource> LogService<CAP, T> {
+    /// Creates a LogService that stamps records with the mission time of `clock`
+    pub fn with_time_source(clock: T) -> Self {
         Self {
             buf: LogBuffer::new(),
+            clock,
+            config: LogConfig::new(),
+            stats: LogStats::default(),
         }
     }
 
-    /// Drain all pending records into a single sink.
+    /// Drain all pending records into a single sink. The sink counts as sink 0 when
+    /// sinks are enabled or disabled by command, records drained while it is disabled are
+    /// counted as discarded.
     pub fn drain<S: LogSink>(&mut self, sink: &mut S) {
-        self.buf.drain(|rec| sink.write(rec));
+        self.drain_multi(core::array::from_mut(sink));
     }
 
-    /// Drain into multiple sinks (console + file etc).
+    /// Drain into multiple sinks (console + file etc). Sinks disabled by command are skipped,
+    /// records that reach no sink at all are counted as discarded.
     pub fn drain_multi<S: LogSink, const N: usize>(&mut self, sinks: &mut [S; N]) {
+        let config = &self.config;
+        let (mut drained, mut discarded) = (0u32, 0u32);
         self.buf.drain(|rec| {
-            for s in sinks.iter_mut() {
-                s.write(rec);
+            let mut written = false;
+            for (i, s) in sinks.iter_mut().enumerate() {
+                if config.sink_enabled(i) {
+                    s.write(rec);
+                    written = true;
+                }
+            }
+            if written {
+                drained += 1;
+            } else {
+                discarded += 1;
             }
         });
+        self.stats.drained = self.stats.drained.wrapping_add(drained);
+        self.stats.discarded = self.stats.discarded.wrapping_add(discarded);
+        self.config.drain_requested = false;
+    }
+
+    /// Executes a command sent by ground or another component
+    pub fn handle_command(&mut self, cmd: LogCommand) -> Result<(), LogCommandError> {
+        let result = self.config.apply(cmd);
+        if result.is_ok() && cmd == LogCommand::ClearBuffer {
+            self.buf.clear();
+        }
+
+        match result {
+            Ok(()) => self.stats.commands_accepted = self.stats.commands_accepted.wrapping_add(1),
+            Err(_) => self.stats.commands_rejected = self.stats.commands_rejected.wrapping_add(1),
+        }
+        result
+    }
+
+    /// Whether a Drain command is waiting for the owner of the sinks to drain the service
+    pub fn drain_requested(&self) -> bool {
+        self.config.drain_requested
+    }
+
+    /// Current settings and statistics
+    pub fn housekeeping(&self) -> LogHousekeeping {
+        LogHousekeeping {
+            min_level: self.config.min_level,
+            component_levels: self.config.component_level_count(),
+            sinks_enabled: self.config.sinks_enabled,
+            pending: self.buf.len() as u32,
+            logged: self.stats.logged,
+            filtered: self.stats.filtered,
+            overwritten: self.stats.overwritten,
+            drained: self.stats.drained,
+            discarded: self.stats.discarded,
+            commands_accepted: self.stats.commands_accepted,
+            commands_rejected: self.stats.commands_rejected,
+        }
     }
 
     /// Check if there are logs pending.
@@ -42,21 +134,76 @@ impl<const CAP: usize> LogService<CAP> {
     }
 
     /// Expose read-only view if you want, e.g. for telemetry
-    pub fn snapshot<'a>(&'a self) -> impl Iterator<Item = &'a LogRecord> {
+    pub fn snapshot(&self) -> impl Iterator<Item = &LogRecord> {
         self.buf.iter()
     }
+
+    /// Handles a LogDumpCommand, packaging the buffered records as telemetry packets.
+    ///
+    /// Packets are built in `packet`, whose length sets the packet size, and handed to
+    /// `emit` one by one (e.g. to queue them for the radio). The buffer is left untouched.
+    /// Returns the number of packets emitted, or `DumpError::BufferTooSmall` when `packet`
+    /// cannot hold a single record.
+    pub fn dump_telemetry<F: FnMut(&[u8])>(
+        &self,
+        cmd: &LogDumpCommand,
+        packet: &mut [u8],
+        emit: F,
+    ) -> Result<usize, DumpError> {
+        let matching = self.buf.iter().filter(|r| cmd.accepts(r)).count();
+        let skip = cmd.last.map_or(0, |last| matching.saturating_sub(last));
+        let records = self.buf.iter().filter(|r| cmd.accepts(r)).skip(skip);
+
+        downlink::packetize(records, packet, emit)
+    }
 }
 
-// Implements the LogHandle trait to create a proper Logger
-impl<const CAP: usize> LogHandle for LogService<CAP> {
-    fn log_message(&mut self, component: ComponentId, level: LogLevel, message: &'static str) {
+impl<const CAP: usize> Default for LogService<CAP> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const CAP: usize, T: TimeSource> LogService<CAP, T> {
+    /// Pushes a record unless `level` is filtered for `component`. The message is only
+    /// built once the record passes the filter.
+    fn record(
+        &mut self,
+        component: ComponentId,
+        level: LogLevel,
+        message: impl FnOnce() -> LogMessage,
+    ) {
+        if level < self.config.level_for(component) {
+            self.stats.filtered = self.stats.filtered.wrapping_add(1);
+            return;
+        }
+
+        if self.buf.is_full() {
+            self.stats.overwritten = self.stats.overwritten.wrapping_add(1);
+        }
+        self.stats.logged = self.stats.logged.wrapping_add(1);
+
         // This simple logger will only publish the record to the ringbuffer and nothing more
         // TODO: When threading is implemented, implement another task/thread which drains the
         // buffer periodically
         self.buf.push(LogRecord {
             level,
             component,
-            message,
+            timestamp: self.clock.mission_time(),
+            message: message(),
         });
     }
 }
+
+// Implements the LogHandle trait to create a proper Logger
+impl<const CAP: usize, T: TimeSource> LogHandle for LogService<CAP, T> {
+    fn log_message(&mut self, component: ComponentId, level: LogLevel, message: &'static str) {
+        self.record(component, level, || message.into());
+    }
+}
+
+impl<const CAP: usize, T: TimeSource> FormattedLogHandle for LogService<CAP, T> {
+    fn log_formatted(&mut self, component: ComponentId, level: LogLevel, args: fmt::Arguments) {
+        self.record(component, level, || LogMessage::format(args));
+    }
+}
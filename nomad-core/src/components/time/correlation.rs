@@ -0,0 +1,423 @@
+//! TimeService Time Correlation
+//!
+//! Correlates local monotonic time with an external absolute time (GNSS, ground, another
+//! node). Samples pairing both times are fed through an alpha-beta filter estimating the
+//! offset and the drift of the local clock, from which corrected external time can be
+//! computed at any local time.
+
+use crate::time::MonotonicInstant;
+use core::time::Duration;
+
+const NANOS_PER_SEC: f64 = 1e9;
+
+/// A time correlation sample
+///
+/// * `external`: External absolute time, e.g. UTC since the Unix epoch from GNSS
+/// * `local`: Local monotonic time at which the external time was valid
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CorrelationSample {
+    pub external: Duration,
+    pub local: MonotonicInstant,
+}
+
+/// Tuning of the correlation filter
+///
+/// * `offset_gain`: Alpha gain, fraction of the residual applied to the offset
+/// * `drift_gain`: Beta gain, fraction of the residual rate applied to the drift
+/// * `jump_threshold`: Residuals larger than this are flagged as jumps and not applied
+/// * `max_drift`: Largest rate error of the local clock expected on top of the estimated
+///   drift, e.g. 100e-6 for 100 ppm. Widens the jump threshold by the error it can build
+///   up since the latest accepted sample.
+/// * `resync_after`: Consecutive jump samples consistent with each other after which the
+///   filter restarts from the latest one, 0 to only resync by command
+/// * `min_samples`: Samples needed before the correlation counts as synchronised
+/// * `holdover_timeout`: Time without samples after which the correlation is in holdover
+#[derive(Copy, Clone, Debug)]
+pub struct CorrelationConfig {
+    pub offset_gain: f64,
+    pub drift_gain: f64,
+    pub jump_threshold: Duration,
+    pub max_drift: f64,
+    pub resync_after: u32,
+    pub min_samples: u32,
+    pub holdover_timeout: Duration,
+}
+
+impl Default for CorrelationConfig {
+    fn default() -> Self {
+        Self {
+            offset_gain: 0.3,
+            drift_gain: 0.05,
+            jump_threshold: Duration::from_millis(100),
+            max_drift: 100e-6,
+            resync_after: 3,
+            min_samples: 4,
+            holdover_timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+/// What happened to a correlation sample
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CorrelationOutcome {
+    /// Sample applied, `residual_ns` is the difference between the sample and the
+    /// prediction before the update
+    Accepted { residual_ns: i64 },
+    /// Sample deviates from the prediction by more than the jump threshold and was not
+    /// applied. Use `TimeService::resync_time` to accept the new time.
+    JumpDetected { residual_ns: i64 },
+    /// Sample confirmed a jump seen by the previous ones, the filter restarted from it
+    Resynced { residual_ns: i64 },
+    /// Sample is older than the latest accepted sample and was ignored
+    Stale,
+}
+
+/// Synchronisation state of the correlation
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SyncState {
+    /// No samples yet, corrected time is unavailable
+    Unsynchronized,
+    /// Samples are being received, but too few to trust the drift estimate
+    Converging,
+    /// Offset and drift are tracked
+    Synchronized,
+    /// Was synchronised, but no sample arrived within the holdover timeout. Corrected time
+    /// is extrapolated from the last estimate.
+    Holdover,
+}
+
+/// Quality indicator of the corrected time
+///
+/// * `state`: Synchronisation state
+/// * `error_estimate`: Smoothed magnitude of recent residuals
+/// * `drift_ppb`: Estimated drift of the local clock, in parts per billion
+/// * `samples`: Samples accepted since the last resync
+/// * `jumps`: Samples flagged as jumps since the last resync
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CorrelationStatus {
+    pub state: SyncState,
+    pub error_estimate: Duration,
+    pub drift_ppb: f64,
+    pub samples: u32,
+    pub jumps: u32,
+}
+
+/// Alpha-beta filter tracking external time as a function of local monotonic time:
+/// `external(local) = ref_external + (local - ref_local) * (1 + drift)`
+///
+/// * `ref_external`: External time at `ref_local`, in nanoseconds
+/// * `ref_local`: Local time of the latest accepted sample
+/// * `drift`: Rate error of the local clock, external seconds per local second minus one
+/// * `error_ns`: Smoothed magnitude of the residuals
+/// * `pending_jump`: Latest of the consecutive jump samples, and how many agreed with it
+#[derive(Copy, Clone, Debug)]
+pub struct TimeCorrelation {
+    config: CorrelationConfig,
+    ref_external: i128,
+    ref_local: MonotonicInstant,
+    drift: f64,
+    error_ns: f64,
+    samples: u32,
+    jumps: u32,
+    pending_jump: Option<(CorrelationSample, u32)>,
+}
+
+impl TimeCorrelation {
+    pub fn new(config: CorrelationConfig) -> Self {
+        Self {
+            config,
+            ref_external: 0,
+            ref_local: MonotonicInstant::ZERO,
+            drift: 0.0,
+            error_ns: 0.0,
+            samples: 0,
+            jumps: 0,
+            pending_jump: None,
+        }
+    }
+
+    /// Filter tuning
+    pub fn config(&self) -> &CorrelationConfig {
+        &self.config
+    }
+
+    /// Discards the current estimate and restarts from `sample`
+    pub fn resync(&mut self, sample: CorrelationSample) {
+        *self = Self::new(self.config);
+        self.ref_external = sample.external.as_nanos() as i128;
+        self.ref_local = sample.local;
+        self.samples = 1;
+    }
+
+    /// Feeds a sample into the filter
+    pub fn add_sample(&mut self, sample: CorrelationSample) -> CorrelationOutcome {
+        if self.samples == 0 {
+            self.resync(sample);
+            return CorrelationOutcome::Accepted { residual_ns: 0 };
+        }
+        if sample.local <= self.ref_local {
+            return CorrelationOutcome::Stale;
+        }
+
+        let dt_ns = (sample.local - self.ref_local).as_nanos() as f64;
+        let predicted = self.predict_ns(sample.local);
+        let residual = sample.external.as_nanos() as i128 - predicted;
+        let residual_ns = residual.clamp(i64::MIN as i128, i64::MAX as i128) as i64;
+
+        if residual.unsigned_abs() > self.tolerance_ns(dt_ns) {
+            return self.jump(sample, residual_ns);
+        }
+        self.pending_jump = None;
+
+        let residual = residual as f64;
+        if self.samples == 1 {
+            // Two samples give a direct drift measurement, start the filter from it
+            self.drift += residual / dt_ns;
+            self.ref_external = sample.external.as_nanos() as i128;
+        } else {
+            self.ref_external = predicted + (self.config.offset_gain * residual) as i128;
+            self.drift += self.config.drift_gain * residual / dt_ns;
+        }
+        self.ref_local = sample.local;
+        self.error_ns += 0.25 * (residual.abs() - self.error_ns);
+        self.samples = self.samples.saturating_add(1);
+
+        CorrelationOutcome::Accepted { residual_ns }
+    }
+
+    /// Flags a jump sample. Once enough consecutive jump samples agree with each other the
+    /// external time really moved, e.g. a GNSS receiver corrected its time, and the filter
+    /// restarts from the latest one.
+    fn jump(&mut self, sample: CorrelationSample, residual_ns: i64) -> CorrelationOutcome {
+        self.jumps = self.jumps.saturating_add(1);
+        let agreeing = match self.pending_jump {
+            Some((pending, count)) if sample.local > pending.local => {
+                let dt_ns = (sample.local - pending.local).as_nanos() as f64;
+                let predicted = pending.external.as_nanos() as i128
+                    + dt_ns as i128
+                    + (dt_ns * self.drift) as i128;
+                let deviation = sample.external.as_nanos() as i128 - predicted;
+                if deviation.unsigned_abs() <= self.tolerance_ns(dt_ns) {
+                    count + 1
+                } else {
+                    1
+                }
+            }
+            _ => 1,
+        };
+
+        if self.config.resync_after > 0 && agreeing >= self.config.resync_after {
+            self.resync(sample);
+            return CorrelationOutcome::Resynced { residual_ns };
+        }
+        self.pending_jump = Some((sample, agreeing));
+        CorrelationOutcome::JumpDetected { residual_ns }
+    }
+
+    /// Largest residual accepted `dt_ns` after a reference, the jump threshold plus the
+    /// error the local clock can build up in that time
+    fn tolerance_ns(&self, dt_ns: f64) -> u128 {
+        self.config.jump_threshold.as_nanos() + (dt_ns * self.config.max_drift.abs()) as u128
+    }
+
+    /// Corrected external time at local monotonic time `local`, None before the first sample
+    pub fn external_at(&self, local: MonotonicInstant) -> Option<Duration> {
+        if self.samples == 0 {
+            return None;
+        }
+        let nanos = self.predict_ns(local).max(0);
+        Some(Duration::new(
+            (nanos / 1_000_000_000) as u64,
+            (nanos % 1_000_000_000) as u32,
+        ))
+    }
+
+    /// Quality indicator as seen at local monotonic time `local`
+    pub fn status(&self, local: MonotonicInstant) -> CorrelationStatus {
+        let state = if self.samples == 0 {
+            SyncState::Unsynchronized
+        } else if local - self.ref_local > self.config.holdover_timeout {
+            SyncState::Holdover
+        } else if self.samples < self.config.min_samples {
+            SyncState::Converging
+        } else {
+            SyncState::Synchronized
+        };
+
+        CorrelationStatus {
+            state,
+            error_estimate: Duration::from_secs_f64(self.error_ns / NANOS_PER_SEC),
+            drift_ppb: self.drift * NANOS_PER_SEC,
+            samples: self.samples,
+            jumps: self.jumps,
+        }
+    }
+
+    /// Predicted external time in nanoseconds at local time `local`
+    fn predict_ns(&self, local: MonotonicInstant) -> i128 {
+        let dt = local.since_start().as_nanos() as i128
+            - self.ref_local.since_start().as_nanos() as i128;
+        self.ref_external + dt + (dt as f64 * self.drift) as i128
+    }
+}
+
+impl Default for TimeCorrelation {
+    fn default() -> Self {
+        Self::new(CorrelationConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2025-01-01T00:00:00Z
+    const UTC_BASE: Duration = Duration::from_secs(1_735_689_600);
+
+    /// External time seen by a local clock running `drift_ppb` slow, offset from UTC_BASE
+    fn sample(local_secs: u64, drift_ppb: i64) -> CorrelationSample {
+        let local = Duration::from_secs(local_secs);
+        let extra = local.as_nanos() as i64 * drift_ppb / 1_000_000_000;
+        CorrelationSample {
+            external: UTC_BASE + local + Duration::from_nanos(extra as u64),
+            local: MonotonicInstant::from_start(local),
+        }
+    }
+
+    #[test]
+    /// Offset and drift are recovered from noiseless samples
+    fn tracks_offset_and_drift() {
+        let mut corr = TimeCorrelation::default();
+        assert_eq!(corr.external_at(MonotonicInstant::ZERO), None);
+        assert_eq!(
+            corr.status(MonotonicInstant::ZERO).state,
+            SyncState::Unsynchronized
+        );
+
+        for t in 0..10 {
+            assert!(matches!(
+                corr.add_sample(sample(t * 10, 50_000)),
+                CorrelationOutcome::Accepted { .. }
+            ));
+        }
+
+        let status = corr.status(MonotonicInstant::from_start(Duration::from_secs(90)));
+        assert_eq!(status.state, SyncState::Synchronized);
+        assert!((status.drift_ppb - 50_000.0).abs() < 1.0);
+
+        // Extrapolates between and beyond samples
+        let expected = sample(125, 50_000).external.as_nanos() as i128;
+        let corrected = corr
+            .external_at(MonotonicInstant::from_start(Duration::from_secs(125)))
+            .unwrap();
+        assert!((corrected.as_nanos() as i128 - expected).abs() < 1_000);
+    }
+
+    #[test]
+    /// Jumps are flagged and not applied, resync accepts them
+    fn flags_jumps() {
+        let mut corr = TimeCorrelation::default();
+        corr.add_sample(sample(0, 0));
+        corr.add_sample(sample(1, 0));
+
+        let mut jumped = sample(2, 0);
+        jumped.external += Duration::from_secs(1);
+        assert!(matches!(
+            corr.add_sample(jumped),
+            CorrelationOutcome::JumpDetected { residual_ns } if residual_ns == 1_000_000_000
+        ));
+        assert_eq!(corr.status(jumped.local).jumps, 1);
+        assert_eq!(corr.external_at(jumped.local), Some(sample(2, 0).external));
+
+        corr.resync(jumped);
+        assert_eq!(corr.external_at(jumped.local), Some(jumped.external));
+        assert_eq!(corr.status(jumped.local).state, SyncState::Converging);
+    }
+
+    #[test]
+    /// Consecutive jump samples that agree with each other resync the filter, scattered
+    /// ones do not
+    fn resyncs_after_consistent_jumps() {
+        let mut corr = TimeCorrelation::default();
+        corr.add_sample(sample(0, 0));
+        corr.add_sample(sample(1, 0));
+
+        let jumped = |t| {
+            let mut s = sample(t, 0);
+            s.external += Duration::from_secs(1);
+            s
+        };
+        let mut outlier = sample(3, 0);
+        outlier.external += Duration::from_secs(5);
+        assert!(matches!(
+            corr.add_sample(jumped(2)),
+            CorrelationOutcome::JumpDetected { .. }
+        ));
+        assert!(matches!(
+            corr.add_sample(outlier),
+            CorrelationOutcome::JumpDetected { .. }
+        ));
+        assert!(matches!(
+            corr.add_sample(jumped(4)),
+            CorrelationOutcome::JumpDetected { .. }
+        ));
+        assert!(matches!(
+            corr.add_sample(jumped(5)),
+            CorrelationOutcome::JumpDetected { .. }
+        ));
+        assert_eq!(
+            corr.external_at(jumped(5).local),
+            Some(sample(5, 0).external)
+        );
+
+        assert!(matches!(
+            corr.add_sample(jumped(6)),
+            CorrelationOutcome::Resynced { residual_ns } if residual_ns == 1_000_000_000
+        ));
+        assert_eq!(corr.external_at(jumped(6).local), Some(jumped(6).external));
+        assert_eq!(corr.status(jumped(6).local).jumps, 0);
+    }
+
+    #[test]
+    /// The jump threshold widens with the time since the latest accepted sample
+    fn threshold_scales_with_drift() {
+        let mut corr = TimeCorrelation::default();
+        corr.add_sample(sample(0, 0));
+        corr.add_sample(sample(1, 0));
+
+        // 150 ms off after 1000 s is within 100 ms + 100 ppm, but not after 10 s
+        let mut late = sample(1001, 0);
+        late.external += Duration::from_millis(150);
+        let mut soon = sample(11, 0);
+        soon.external += Duration::from_millis(150);
+        let mut copy = corr;
+        assert!(matches!(
+            copy.add_sample(soon),
+            CorrelationOutcome::JumpDetected { .. }
+        ));
+        assert!(matches!(
+            corr.add_sample(late),
+            CorrelationOutcome::Accepted { .. }
+        ));
+    }
+
+    #[test]
+    /// Old samples are ignored and missing samples lead to holdover
+    fn stale_and_holdover() {
+        let mut corr = TimeCorrelation::default();
+        for t in 0..5 {
+            corr.add_sample(sample(t, 0));
+        }
+        assert_eq!(corr.add_sample(sample(2, 0)), CorrelationOutcome::Stale);
+        assert_eq!(
+            corr.status(MonotonicInstant::from_start(Duration::from_secs(10)))
+                .state,
+            SyncState::Synchronized
+        );
+        assert_eq!(
+            corr.status(MonotonicInstant::from_start(Duration::from_secs(100)))
+                .state,
+            SyncState::Holdover
+        );
+    }
+}
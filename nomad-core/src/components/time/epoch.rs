@@ -0,0 +1,105 @@
+//! TimeService Mission Epoch
+//!
+//! The mission epoch (T0) is often only known at runtime, e.g. the moment of liftoff.
+//! These are the commands that set it in flight, the events the TimeService emits when it
+//! changes, and the store that keeps it across restarts.
+
+use crate::time::{Epoch, MissionTime};
+
+/// Commands accepted by the TimeService
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimeCommand {
+    /// Sets the mission epoch to the current time, e.g. on liftoff detection
+    SetEpochNow,
+    /// Sets the mission epoch to the given absolute time
+    SetEpoch(Epoch),
+}
+
+/// Reasons a TimeCommand can fail
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimeCommandError {
+    /// The epoch was applied, but could not be persisted. Either the store failed, or
+    /// the epoch was set while no absolute time was available.
+    NotPersisted,
+}
+
+/// Events emitted by the TimeService
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimeEvent {
+    /// The mission epoch changed
+    ///
+    /// * `epoch`: New epoch as absolute time, None if it was set while no absolute time was
+    ///   available
+    /// * `mission_time`: Mission time right before the change, against the old epoch
+    /// * `persisted`: Whether the new epoch was written to the epoch store
+    EpochChanged {
+        epoch: Option<Epoch>,
+        mission_time: MissionTime,
+        persisted: bool,
+    },
+}
+
+/// Persistent storage for the mission epoch, so it survives a restart
+pub trait EpochStore {
+    /// Reads the stored epoch, None when nothing was stored yet
+    fn load(&mut self) -> Option<Epoch>;
+
+    /// Stores the epoch, returning whether it was written
+    fn store(&mut self, epoch: Epoch) -> bool;
+}
+
+/// An EpochStore that stores nothing. Used where no persistent storage is wired up.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NoEpochStore;
+
+impl EpochStore for NoEpochStore {
+    fn load(&mut self) -> Option<Epoch> {
+        None
+    }
+
+    fn store(&mut self, _epoch: Epoch) -> bool {
+        false
+    }
+}
+
+/// Number of events the TimeService holds until they are polled
+pub const MAX_PENDING_TIME_EVENTS: usize = 8;
+
+/// Fixed capacity FIFO of events waiting to be polled. When full the oldest event is
+/// dropped.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct EventQueue {
+    events: [Option<TimeEvent>; MAX_PENDING_TIME_EVENTS],
+    head: usize,
+    len: usize,
+}
+
+impl EventQueue {
+    pub(crate) const fn new() -> Self {
+        Self {
+            events: [None; MAX_PENDING_TIME_EVENTS],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    pub(crate) fn push(&mut self, event: TimeEvent) {
+        let tail = (self.head + self.len) % MAX_PENDING_TIME_EVENTS;
+        self.events[tail] = Some(event);
+        if self.len == MAX_PENDING_TIME_EVENTS {
+            self.head = (self.head + 1) % MAX_PENDING_TIME_EVENTS;
+        } else {
+            self.len += 1;
+        }
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<TimeEvent> {
+        if self.len == 0 {
+            return None;
+        }
+        let event = self.events[self.head].take();
+        self.head = (self.head + 1) % MAX_PENDING_TIME_EVENTS;
+        self.len -= 1;
+        event
+    }
+}
@@ -2,33 +2,369 @@
 //!
 //! A Logger service provided as part of the Nomad Standard Components Collection
 
-use crate::time::TimeSource;
+pub mod correlation;
+pub mod epoch;
+pub mod timer;
+pub mod tone;
+use correlation::{
+    CorrelationOutcome, CorrelationSample, CorrelationStatus, SyncState, TimeCorrelation,
+};
+use epoch::{EpochStore, EventQueue, NoEpochStore, TimeCommand, TimeCommandError, TimeEvent};
+use timer::{TimerError, TimerExpiry, TimerId, TimerWheel};
+use tone::{TimeTone, ToneSchedule};
+
+use crate::time::{
+    Epoch, GpsTime, LeapSecondTable, MissionTime, MonotonicInstant, TaiTime, TimeConfig, TimeScale,
+    TimeSource, TimeSourceHealth, UtcTime,
+};
 use core::time::Duration;
 
+/// What mission time is measured against
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum EpochReference {
+    /// The TimeSource provides mission time, it was configured with the epoch
+    Source,
+    /// Epoch set at runtime at the given local monotonic time
+    Local(MonotonicInstant),
+    /// Epoch set at runtime as absolute time, mission time derived from absolute time
+    Absolute,
+}
+
 /// Standard TimeService FSW Components
 ///
 /// Wraps a TimeSource and provides a API to interact with it.
 /// At the moment allows other components to query time, in the future
 /// this could be upgraded to sync with GNSS/external time, distribute time events
 /// on the component bus, provide scheduling helpers.
-/// * `source`:
-pub struct TimeService<T: TimeSource> {
+/// * `source`: TimeSource providing monotonic and mission time
+/// * `correlation`: Correlation of monotonic time with external absolute time
+/// * `mission_epoch`: Mission epoch, if known
+/// * `epoch_reference`: What mission time is measured against
+/// * `leap_seconds`: Leap-second table used to convert between time scales
+/// * `epoch_store`: Persistent storage of the mission epoch
+/// * `events`: Events waiting to be polled
+/// * `timers`: Software timers
+/// * `tone`: Schedule of the published time-at-the-tone messages
+pub struct TimeService<T: TimeSource, S: EpochStore = NoEpochStore> {
     source: T,
+    correlation: TimeCorrelation,
+    mission_epoch: Option<Epoch>,
+    epoch_reference: EpochReference,
+    leap_seconds: LeapSecondTable,
+    epoch_store: S,
+    events: EventQueue,
+    timers: TimerWheel<MAX_TIMERS>,
+    tone: ToneSchedule,
 }
 
+/// Number of software timers the TimeService can run at once
+pub const MAX_TIMERS: usize = 32;
+
 impl<T: TimeSource> TimeService<T> {
     pub fn new(source: T) -> Self {
-        Self { source }
+        let leap_seconds = LeapSecondTable::builtin();
+        source.leap_seconds_changed(&leap_seconds);
+        Self {
+            source,
+            correlation: TimeCorrelation::default(),
+            mission_epoch: None,
+            epoch_reference: EpochReference::Source,
+            leap_seconds,
+            epoch_store: NoEpochStore,
+            events: EventQueue::new(),
+            timers: TimerWheel::default(),
+            tone: ToneSchedule::new(),
+        }
+    }
+
+    /// Creates a TimeService that also knows the mission epoch of `cfg`, so corrected
+    /// mission time can be derived from external time
+    pub fn with_config(source: T, cfg: &TimeConfig) -> Self {
+        Self {
+            mission_epoch: cfg.mission_epoch,
+            ..Self::new(source)
+        }
+    }
+}
+
+impl<T: TimeSource, S: EpochStore> TimeService<T, S> {
+    /// Persists the mission epoch in `store`. An epoch found in the store, set before a
+    /// restart, replaces the configured one.
+    pub fn with_epoch_store<S2: EpochStore>(self, mut store: S2) -> TimeService<T, S2> {
+        let stored = store.load();
+        let (mission_epoch, epoch_reference) = match stored {
+            Some(epoch) => (Some(epoch), EpochReference::Absolute),
+            None => (self.mission_epoch, self.epoch_reference),
+        };
+        TimeService {
+            source: self.source,
+            correlation: self.correlation,
+            mission_epoch,
+            epoch_reference,
+            leap_seconds: self.leap_seconds,
+            epoch_store: store,
+            events: self.events,
+            timers: self.timers,
+            tone: self.tone,
+        }
+    }
+
+    /// Replaces the correlation filter, e.g. to tune its gains and thresholds
+    pub fn set_correlation(&mut self, correlation: TimeCorrelation) {
+        self.correlation = correlation;
+    }
+
+    /// Feeds a time correlation sample, pairing external absolute time (UTC since the Unix
+    /// epoch) with local monotonic time. Samples that jump beyond the configured threshold
+    /// are flagged and not applied, until enough consecutive ones confirm the jump.
+    pub fn add_correlation_sample(&mut self, sample: CorrelationSample) -> CorrelationOutcome {
+        self.correlation.add_sample(sample)
+    }
+
+    /// Accepts a sample unconditionally, discarding the current estimate. Used to take a
+    /// time jump that was flagged by `add_correlation_sample`.
+    pub fn resync_time(&mut self, sample: CorrelationSample) {
+        self.correlation.resync(sample);
+    }
+
+    /// UTC (time since the Unix epoch) corrected for offset and drift of the local clock.
+    /// None until a correlation sample has been received.
+    pub fn corrected_utc(&self) -> Option<UtcTime> {
+        self.correlation
+            .external_at(self.source.monotonic())
+            .map(UtcTime)
+    }
+
+    /// Mission time derived from corrected UTC. None until a correlation sample has been
+    /// received, or without a mission epoch.
+    pub fn corrected_mission_time(&self) -> Option<MissionTime> {
+        let epoch = self.mission_epoch_utc()?;
+        Some(MissionTime::between(epoch.0, self.corrected_utc()?.0))
+    }
+
+    /// Mission epoch, if known
+    pub fn mission_epoch(&self) -> Option<Epoch> {
+        self.mission_epoch
+    }
+
+    /// Mission epoch as UTC, if known
+    pub fn mission_epoch_utc(&self) -> Option<UtcTime> {
+        self.mission_epoch
+            .map(|epoch| epoch.to_utc(&self.leap_seconds))
+    }
+
+    /// Leap-second table used for time scale conversions
+    pub fn leap_seconds(&self) -> &LeapSecondTable {
+        &self.leap_seconds
+    }
+
+    /// Updates the leap-second table, e.g. to add a newly announced leap second. The
+    /// TimeSource is handed the updated table, so both convert with the same one.
+    pub fn update_leap_seconds<R>(&mut self, update: impl FnOnce(&mut LeapSecondTable) -> R) -> R {
+        let result = update(&mut self.leap_seconds);
+        self.source.leap_seconds_changed(&self.leap_seconds);
+        result
+    }
+
+    /// Current UTC. Corrected UTC when correlated, otherwise UTC of the TimeSource, or
+    /// derived from the mission epoch and mission time. None when neither is available.
+    pub fn utc(&self) -> Option<UtcTime> {
+        self.absolute_utc().or_else(|| {
+            let epoch = self.mission_epoch_utc()?;
+            let met = self.mission_time();
+            let utc = if met.is_before_epoch() {
+                epoch.0.checked_sub(met.magnitude())?
+            } else {
+                epoch.0 + met.magnitude()
+            };
+            Some(UtcTime(utc))
+        })
+    }
+
+    /// UTC from correlation or from the TimeSource, independent of the mission epoch
+    fn absolute_utc(&self) -> Option<UtcTime> {
+        self.corrected_utc().or_else(|| self.source.utc())
+    }
+
+    /// Sets the mission epoch to the current time, e.g. on liftoff detection. The epoch is
+    /// persisted when absolute time is available, and an `EpochChanged` event is emitted.
+    pub fn set_epoch_now(&mut self) -> Result<(), TimeCommandError> {
+        let now = self.utc().map(Epoch::Utc);
+        let previous = self.mission_time();
+        self.mission_epoch = now;
+        self.epoch_reference = EpochReference::Local(self.source.monotonic());
+        self.epoch_changed(previous)
+    }
+
+    /// Sets the mission epoch to an absolute time. The epoch is persisted and an
+    /// `EpochChanged` event is emitted.
+    pub fn set_epoch(&mut self, epoch: Epoch) -> Result<(), TimeCommandError> {
+        let previous = self.mission_time();
+        self.mission_epoch = Some(epoch);
+        self.epoch_reference = EpochReference::Absolute;
+        self.epoch_changed(previous)
+    }
+
+    /// Handles a command sent to the TimeService
+    pub fn handle_command(&mut self, cmd: TimeCommand) -> Result<(), TimeCommandError> {
+        match cmd {
+            TimeCommand::SetEpochNow => self.set_epoch_now(),
+            TimeCommand::SetEpoch(epoch) => self.set_epoch(epoch),
+        }
+    }
+
+    /// Takes the oldest event not yet polled
+    pub fn poll_event(&mut self) -> Option<TimeEvent> {
+        self.events.pop()
+    }
+
+    /// Starts a one-shot timer expiring `delay` from now
+    pub fn start_timer(&mut self, delay: Duration) -> Result<TimerId, TimerError> {
+        self.timers.start(self.monotonic() + delay, None)
+    }
+
+    /// Starts a periodic timer, first expiring one `period` from now
+    pub fn start_periodic_timer(&mut self, period: Duration) -> Result<TimerId, TimerError> {
+        self.timers.start(self.monotonic() + period, Some(period))
+    }
+
+    /// Starts a timer expiring at `deadline`, repeating every `period` if given
+    pub fn start_timer_at(
+        &mut self,
+        deadline: MonotonicInstant,
+        period: Option<Duration>,
+    ) -> Result<TimerId, TimerError> {
+        self.timers.start(deadline, period)
+    }
+
+    /// Cancels a timer
+    pub fn cancel_timer(&mut self, id: TimerId) -> Result<(), TimerError> {
+        self.timers.cancel(id)
+    }
+
+    /// Moves a running timer to expire `delay` from now, keeping its period
+    pub fn reschedule_timer(&mut self, id: TimerId, delay: Duration) -> Result<(), TimerError> {
+        self.timers.reschedule(id, self.monotonic() + delay)
+    }
+
+    /// Takes the next expired timer, None when no timer is due. Call repeatedly, e.g. once
+    /// per cycle of the FSW loop, until None.
+    pub fn poll_timer(&mut self) -> Option<TimerExpiry> {
+        let now = self.monotonic();
+        self.timers.poll(now)
+    }
+
+    /// Publishes a tone every `period`, the first one a period from now. None stops
+    /// publishing.
+    pub fn set_tone_period(&mut self, period: Option<Duration>) -> Result<(), TimerError> {
+        if period == Some(Duration::ZERO) {
+            return Err(TimerError::ZeroPeriod);
+        }
+        self.tone.period = period;
+        self.tone.next = self.monotonic() + period.unwrap_or_default();
+        Ok(())
+    }
+
+    /// Takes the next tone when one is due, to be handed to a ToneDistributor or sent to
+    /// other nodes. Call once per cycle of the FSW loop.
+    pub fn poll_tone(&mut self) -> Option<TimeTone> {
+        let now = self.monotonic();
+        self.tone.due(now).then(|| self.time_tone())
+    }
+
+    /// Current time as a tone, published immediately rather than on schedule
+    pub fn time_tone(&mut self) -> TimeTone {
+        let health = self.health();
+        let utc = self.utc();
+        TimeTone {
+            sequence: self.tone.next_sequence(),
+            mission_time: self.mission_time(),
+            utc,
+            valid: health.valid,
+            synchronized: health.synchronized,
+            tai_minus_utc: utc.map_or_else(
+                || {
+                    self.leap_seconds
+                        .entries()
+                        .last()
+                        .map_or(0, |e| e.tai_minus_utc)
+                },
+                |utc| self.leap_seconds.tai_minus_utc(utc),
+            ),
+        }
+    }
+
+    /// Persists the new epoch and emits the event
+    fn epoch_changed(&mut self, mission_time: MissionTime) -> Result<(), TimeCommandError> {
+        let persisted = self
+            .mission_epoch
+            .is_some_and(|epoch| self.epoch_store.store(epoch));
+        self.events.push(TimeEvent::EpochChanged {
+            epoch: self.mission_epoch,
+            mission_time,
+            persisted,
+        });
+        if persisted {
+            Ok(())
+        } else {
+            Err(TimeCommandError::NotPersisted)
+        }
+    }
+
+    /// Current TAI, see `utc`
+    pub fn tai(&self) -> Option<TaiTime> {
+        self.utc().map(|utc| self.leap_seconds.utc_to_tai(utc))
+    }
+
+    /// Current GPS time, see `utc`
+    pub fn gps(&self) -> Option<GpsTime> {
+        self.utc().map(|utc| self.leap_seconds.utc_to_gps(utc))
+    }
+
+    /// Current time in the requested scale, as time since the epoch of that scale
+    pub fn time_in(&self, scale: TimeScale) -> Option<Duration> {
+        match scale {
+            TimeScale::Utc => self.utc().map(|t| t.0),
+            TimeScale::Tai => self.tai().map(|t| t.0),
+            TimeScale::Gps => self.gps().map(|t| t.0),
+        }
+    }
+
+    /// Quality indicator of corrected time
+    pub fn correlation_status(&self) -> CorrelationStatus {
+        self.correlation.status(self.source.monotonic())
+    }
+
+    /// Health of the TimeSource. While the correlation is synchronised the time counts as
+    /// synchronised, with the correlation error as bound.
+    pub fn health(&self) -> TimeSourceHealth {
+        let mut health = self.source.health();
+        let status = self.correlation_status();
+        if status.state == SyncState::Synchronized {
+            health.synchronized = true;
+            health.error_bound = Some(status.error_estimate);
+        }
+        health
     }
 
     /// Monotonic time since boot/start.
-    pub fn monotonic(&self) -> Duration {
+    pub fn monotonic(&self) -> MonotonicInstant {
         self.source.monotonic()
     }
 
-    /// Mission elapsed time (MET) since mission epoch.
-    pub fn mission_time(&self) -> Duration {
-        self.source.mission_time()
+    /// Mission elapsed time (MET) since mission epoch, negative (T-minus) before it.
+    pub fn mission_time(&self) -> MissionTime {
+        match self.epoch_reference {
+            EpochReference::Local(at) => MissionTime::ZERO + (self.source.monotonic() - at),
+            EpochReference::Absolute => {
+                match (self.mission_epoch_utc(), self.absolute_utc()) {
+                    (Some(epoch), Some(now)) => MissionTime::between(epoch.0, now.0),
+                    // Without absolute time, only the source can tell mission time
+                    _ => self.source.mission_time(),
+                }
+            }
+            EpochReference::Source => self.source.mission_time(),
+        }
     }
 
     /// Borrow the underlying source if you need additional behavior.
@@ -36,3 +372,293 @@ impl<T: TimeSource> TimeService<T> {
         &self.source
     }
 }
+
+/// The TimeService can be used wherever a TimeSource is expected, e.g. for stamping logs
+impl<T: TimeSource, S: EpochStore> TimeSource for TimeService<T, S> {
+    fn monotonic(&self) -> MonotonicInstant {
+        self.source.monotonic()
+    }
+
+    fn mission_time(&self) -> MissionTime {
+        TimeService::mission_time(self)
+    }
+
+    fn utc(&self) -> Option<UtcTime> {
+        TimeService::utc(self)
+    }
+
+    fn health(&self) -> TimeSourceHealth {
+        TimeService::health(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::{
+        FailoverTimeSource, NoTimeSource, SimulatedTimeSource, TimeMode, TimeSourceKind,
+    };
+    use epoch::{TimeCommand, TimeCommandError};
+    use tone::{SlewConfig, SlewingClock, TONE_MSG_LEN, ToneSubscriber};
+
+    #[test]
+    /// Corrected UTC and mission time follow the correlation samples
+    fn corrected_time() {
+        let cfg = TimeConfig {
+            mode: TimeMode::Simulated,
+            mission_epoch: Some(Epoch::Utc(UtcTime::from_unix_secs(1_000_000))),
+        };
+        let mut service = TimeService::with_config(SimulatedTimeSource::new(), &cfg);
+        assert_eq!(service.corrected_utc(), None);
+
+        for t in 0..5 {
+            service.inner().set(Duration::from_secs(t));
+            service.add_correlation_sample(CorrelationSample {
+                external: Duration::from_secs(1_000_100 + t),
+                local: service.monotonic(),
+            });
+        }
+        service.inner().advance(Duration::from_millis(500));
+
+        assert_eq!(
+            service.corrected_utc(),
+            Some(UtcTime(Duration::from_millis(1_000_104_500)))
+        );
+        assert_eq!(
+            service.corrected_mission_time(),
+            Some(MissionTime::after_epoch(Duration::from_millis(104_500)))
+        );
+        assert_eq!(service.correlation_status().state, SyncState::Synchronized);
+    }
+
+    #[test]
+    /// The TimeService runs on a failover source and reports its health
+    fn source_health_and_failover() {
+        let primary = SimulatedTimeSource::new();
+        let backup = NoTimeSource;
+        let service = TimeService::new(FailoverTimeSource::new([&backup, &primary]));
+
+        // The invalid source is skipped despite its priority
+        assert_eq!(service.inner().active(), 1);
+        let health = service.health();
+        assert!(health.valid);
+        assert_eq!(health.kind, TimeSourceKind::Simulated);
+        assert_eq!(health.error_bound, Some(Duration::ZERO));
+
+        let mut service = TimeService::new(PosixLike);
+        assert!(!service.health().synchronized);
+        for t in 0..4 {
+            service.add_correlation_sample(CorrelationSample {
+                external: Duration::from_secs(100 + t),
+                local: MonotonicInstant::from_start(Duration::from_secs(t)),
+            });
+        }
+        let health = service.health();
+        assert!(health.synchronized);
+        assert_eq!(health.kind, TimeSourceKind::OsClock);
+        assert_eq!(health.error_bound, Some(Duration::ZERO));
+    }
+
+    /// Free running source at a fixed time
+    struct PosixLike;
+
+    impl TimeSource for PosixLike {
+        fn monotonic(&self) -> MonotonicInstant {
+            MonotonicInstant::from_start(Duration::from_secs(3))
+        }
+
+        fn mission_time(&self) -> MissionTime {
+            MissionTime::ZERO
+        }
+
+        fn health(&self) -> TimeSourceHealth {
+            TimeSourceHealth::free_running(TimeSourceKind::OsClock)
+        }
+    }
+
+    #[test]
+    /// Time is reported in every scale, from the mission epoch until correlated
+    fn time_scales() {
+        // 2025-01-01T00:00:00Z given as GPS time
+        let leap_seconds = LeapSecondTable::builtin();
+        let epoch = UtcTime::from_unix_secs(1_735_689_600);
+        let cfg = TimeConfig {
+            mode: TimeMode::Simulated,
+            mission_epoch: Some(Epoch::Gps(leap_seconds.utc_to_gps(epoch))),
+        };
+        let service = TimeService::with_config(SimulatedTimeSource::new(), &cfg);
+        service.inner().set(Duration::from_secs(10));
+
+        assert_eq!(service.mission_epoch_utc(), Some(epoch));
+        assert_eq!(
+            service.utc(),
+            Some(UtcTime(epoch.0 + Duration::from_secs(10)))
+        );
+        assert_eq!(
+            service.time_in(TimeScale::Tai).unwrap() - service.time_in(TimeScale::Utc).unwrap(),
+            crate::time::scale::TAI_TO_UNIX_EPOCH + Duration::from_secs(37)
+        );
+        assert_eq!(
+            service.gps(),
+            Some(leap_seconds.utc_to_gps(service.utc().unwrap()))
+        );
+
+        // No epoch and no correlation, no absolute time
+        assert_eq!(TimeService::new(SimulatedTimeSource::new()).utc(), None);
+    }
+
+    /// EpochStore keeping the epoch in memory, standing in for persistent storage
+    #[derive(Default)]
+    struct MemoryEpochStore(Option<Epoch>);
+
+    impl EpochStore for &mut MemoryEpochStore {
+        fn load(&mut self) -> Option<Epoch> {
+            self.0
+        }
+
+        fn store(&mut self, epoch: Epoch) -> bool {
+            self.0 = Some(epoch);
+            true
+        }
+    }
+
+    #[test]
+    /// Setting the epoch at runtime moves mission time and emits an event
+    fn set_epoch_at_runtime() {
+        let mut service = TimeService::new(SimulatedTimeSource::new());
+        service.inner().set(Duration::from_secs(100));
+        assert_eq!(
+            service.mission_time(),
+            MissionTime::after_epoch(Duration::from_secs(100))
+        );
+
+        // Liftoff without absolute time, mission time restarts but nothing is persisted
+        assert_eq!(
+            service.handle_command(TimeCommand::SetEpochNow),
+            Err(TimeCommandError::NotPersisted)
+        );
+        service.inner().advance(Duration::from_secs(5));
+        assert_eq!(
+            service.mission_time(),
+            MissionTime::after_epoch(Duration::from_secs(5))
+        );
+        assert_eq!(
+            service.poll_event(),
+            Some(TimeEvent::EpochChanged {
+                epoch: None,
+                mission_time: MissionTime::after_epoch(Duration::from_secs(100)),
+                persisted: false,
+            })
+        );
+        assert_eq!(service.poll_event(), None);
+    }
+
+    #[test]
+    /// The epoch is persisted and restored after a restart, with mission time counting
+    /// from it against absolute time
+    fn epoch_survives_restart() {
+        let mut store = MemoryEpochStore::default();
+        let epoch = Epoch::Utc(UtcTime::from_unix_secs(1_000_000));
+
+        {
+            let mut service =
+                TimeService::new(SimulatedTimeSource::new()).with_epoch_store(&mut store);
+            service.set_epoch(epoch).unwrap();
+            assert!(matches!(
+                service.poll_event(),
+                Some(TimeEvent::EpochChanged {
+                    persisted: true,
+                    ..
+                })
+            ));
+        }
+        assert_eq!(store.0, Some(epoch));
+
+        // After the restart absolute time comes from correlation, 10s before the epoch
+        let mut service = TimeService::new(SimulatedTimeSource::new()).with_epoch_store(&mut store);
+        assert_eq!(service.mission_epoch(), Some(epoch));
+        service.resync_time(CorrelationSample {
+            external: Duration::from_secs(999_990),
+            local: MonotonicInstant::ZERO,
+        });
+        assert_eq!(
+            service.mission_time(),
+            MissionTime::before_epoch(Duration::from_secs(10))
+        );
+    }
+
+    #[test]
+    /// Timers follow the monotonic time of the source
+    fn timers() {
+        let mut service = TimeService::new(SimulatedTimeSource::new());
+        let once = service.start_timer(Duration::from_millis(50)).unwrap();
+        let tick = service
+            .start_periodic_timer(Duration::from_millis(20))
+            .unwrap();
+
+        service.inner().advance(Duration::from_millis(40));
+        assert_eq!(service.poll_timer().map(|e| e.id), Some(tick));
+        assert_eq!(service.poll_timer().map(|e| e.id), Some(tick));
+        assert_eq!(service.poll_timer(), None);
+
+        service
+            .reschedule_timer(once, Duration::from_millis(5))
+            .unwrap();
+        service.inner().advance(Duration::from_millis(5));
+        assert_eq!(service.poll_timer().map(|e| e.id), Some(once));
+
+        service.cancel_timer(tick).unwrap();
+        service.inner().advance(Duration::from_secs(1));
+        assert_eq!(service.poll_timer(), None);
+    }
+
+    #[test]
+    /// Timers beyond the end of the timeline are armed but never fire
+    fn far_timers() {
+        let mut service = TimeService::new(SimulatedTimeSource::new());
+        let never = service.start_timer(Duration::MAX).unwrap();
+        let periodic = service.start_periodic_timer(Duration::MAX).unwrap();
+        let soon = service.start_timer(Duration::from_millis(10)).unwrap();
+        service.reschedule_timer(soon, Duration::MAX).unwrap();
+
+        service.inner().advance(Duration::from_secs(3600));
+        assert_eq!(service.poll_timer(), None);
+        service.cancel_timer(never).unwrap();
+        service.cancel_timer(periodic).unwrap();
+        service.cancel_timer(soon).unwrap();
+    }
+
+    #[test]
+    /// Tones are published on schedule and followed by a clock on another node
+    fn time_at_the_tone() {
+        let service_clock = SimulatedTimeSource::new();
+        let mut service = TimeService::new(&service_clock);
+        assert_eq!(service.poll_tone(), None);
+        assert_eq!(
+            service.set_tone_period(Some(Duration::ZERO)),
+            Err(TimerError::ZeroPeriod)
+        );
+        service
+            .set_tone_period(Some(Duration::from_secs(1)))
+            .unwrap();
+
+        service_clock.set(Duration::from_secs(30));
+        let remote_clock = SimulatedTimeSource::new();
+        let remote = SlewingClock::new(&remote_clock, SlewConfig::default());
+
+        let tone = service.poll_tone().expect("tone due");
+        assert_eq!(service.poll_tone(), None);
+        assert_eq!(tone.sequence, 0);
+        assert!(tone.valid);
+        assert_eq!(tone.tai_minus_utc, 37);
+
+        let mut msg = [0u8; TONE_MSG_LEN];
+        tone.encode(&mut msg).unwrap();
+        remote.on_tone(&TimeTone::decode(&msg).unwrap());
+        assert_eq!(remote.mission_time(), service.mission_time());
+        assert!(remote.health().synchronized);
+
+        service_clock.advance(Duration::from_secs(1));
+        assert_eq!(service.poll_tone().map(|t| t.sequence), Some(1));
+    }
+}
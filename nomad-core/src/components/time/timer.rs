@@ -0,0 +1,411 @@
+//! TimeService Software Timers
+//!
+//! One-shot and periodic timers on the monotonic timeline, kept in a fixed capacity
+//! hashed timer wheel. Timers never call back into components, expiries are polled. As
+//! only monotonic time is used, timers behave the same under real and simulated time.
+
+use crate::time::MonotonicInstant;
+use core::time::Duration;
+
+/// Number of buckets of the timer wheel
+pub const WHEEL_SLOTS: usize = 64;
+
+/// Default tick of the timer wheel
+pub const DEFAULT_TIMER_RESOLUTION: Duration = Duration::from_millis(1);
+
+/// Handle of a started timer. Stays unique after the timer is cancelled or expired, so a
+/// stale handle cannot affect a newer timer reusing the same slot.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TimerId {
+    slot: u16,
+    generation: u16,
+}
+
+/// Reasons a timer operation can fail
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimerError {
+    /// All timers are in use
+    NoFreeTimers,
+    /// The timer expired (one-shot), was cancelled, or never existed
+    UnknownTimer,
+    /// Periodic timers need a non-zero period
+    ZeroPeriod,
+}
+
+/// A timer expiry
+///
+/// * `id`: Timer that expired
+/// * `deadline`: Monotonic time the timer was due at
+/// * `missed`: Periods of a periodic timer skipped because expiries were polled too late
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TimerExpiry {
+    pub id: TimerId,
+    pub deadline: MonotonicInstant,
+    pub missed: u32,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Timer {
+    generation: u16,
+    active: bool,
+    deadline: MonotonicInstant,
+    period: Option<Duration>,
+    /// Bucket the timer is linked into
+    bucket: usize,
+    /// Next timer in the same bucket
+    next: Option<u16>,
+}
+
+impl Timer {
+    const FREE: Self = Self {
+        generation: 0,
+        active: false,
+        deadline: MonotonicInstant::ZERO,
+        period: None,
+        bucket: 0,
+        next: None,
+    };
+}
+
+/// Hashed timer wheel holding up to N timers.
+///
+/// Timers are hashed into `WHEEL_SLOTS` buckets by their deadline tick. Polling walks the
+/// buckets from the last polled tick up to now, timers due in a later revolution of the
+/// wheel stay in their bucket.
+///
+/// * `timers`: Timer slots, bucket lists are linked through them
+/// * `buckets`: First timer of every bucket
+/// * `resolution`: Length of a tick
+/// * `tick`: Next tick to be polled
+pub struct TimerWheel<const N: usize> {
+    timers: [Timer; N],
+    buckets: [Option<u16>; WHEEL_SLOTS],
+    resolution: Duration,
+    tick: u64,
+}
+
+impl<const N: usize> TimerWheel<N> {
+    /// Creates an empty wheel with ticks of `resolution`. Deadlines are rounded to ticks
+    /// for bucketing only, timers never expire before their deadline.
+    pub fn new(resolution: Duration) -> Self {
+        assert!(N <= usize::from(u16::MAX), "too many timers");
+        assert!(!resolution.is_zero(), "timer resolution must not be zero");
+        Self {
+            timers: [Timer::FREE; N],
+            buckets: [None; WHEEL_SLOTS],
+            resolution,
+            tick: 0,
+        }
+    }
+
+    /// Starts a timer due at `deadline`, repeating every `period` if given
+    pub fn start(
+        &mut self,
+        deadline: MonotonicInstant,
+        period: Option<Duration>,
+    ) -> Result<TimerId, TimerError> {
+        if period.is_some_and(|p| p.is_zero()) {
+            return Err(TimerError::ZeroPeriod);
+        }
+        let slot = self
+            .timers
+            .iter()
+            .position(|t| !t.active)
+            .ok_or(TimerError::NoFreeTimers)?;
+
+        let timer = &mut self.timers[slot];
+        timer.generation = timer.generation.wrapping_add(1);
+        timer.active = true;
+        timer.deadline = deadline;
+        timer.period = period;
+        let id = TimerId {
+            slot: slot as u16,
+            generation: timer.generation,
+        };
+        self.link(slot as u16);
+        Ok(id)
+    }
+
+    /// Cancels a timer, no expiry is reported for it anymore
+    pub fn cancel(&mut self, id: TimerId) -> Result<(), TimerError> {
+        self.check(id)?;
+        self.unlink(id.slot);
+        self.timers[usize::from(id.slot)].active = false;
+        Ok(())
+    }
+
+    /// Moves a running timer to a new deadline, keeping its period
+    pub fn reschedule(
+        &mut self,
+        id: TimerId,
+        deadline: MonotonicInstant,
+    ) -> Result<(), TimerError> {
+        self.check(id)?;
+        self.unlink(id.slot);
+        self.timers[usize::from(id.slot)].deadline = deadline;
+        self.link(id.slot);
+        Ok(())
+    }
+
+    /// Deadline of a running timer
+    pub fn deadline(&self, id: TimerId) -> Option<MonotonicInstant> {
+        self.check(id).ok()?;
+        Some(self.timers[usize::from(id.slot)].deadline)
+    }
+
+    /// Number of running timers
+    pub fn active(&self) -> usize {
+        self.timers.iter().filter(|t| t.active).count()
+    }
+
+    /// Returns the next timer due at or before `now`, None when no timer is due.
+    /// Periodic timers are re-armed for their next period. Call repeatedly until None to
+    /// collect every expiry.
+    pub fn poll(&mut self, now: MonotonicInstant) -> Option<TimerExpiry> {
+        let now_tick = self.tick_of(now);
+        // Every bucket is visited within one revolution, skip revolutions with nothing new
+        if now_tick.saturating_sub(self.tick) >= WHEEL_SLOTS as u64 {
+            self.tick = now_tick - (WHEEL_SLOTS as u64 - 1);
+        }
+
+        loop {
+            if let Some(slot) = self.due_in_bucket(self.tick, now) {
+                return Some(self.expire(slot, now));
+            }
+            if self.tick >= now_tick {
+                return None;
+            }
+            self.tick += 1;
+        }
+    }
+
+    /// Earliest timer in the bucket of `tick` that is due at `now`
+    fn due_in_bucket(&self, tick: u64, now: MonotonicInstant) -> Option<u16> {
+        let mut due: Option<u16> = None;
+        let mut cursor = self.buckets[Self::bucket(tick)];
+        while let Some(slot) = cursor {
+            let timer = &self.timers[usize::from(slot)];
+            let earlier = due.is_none_or(|d| timer.deadline < self.timers[usize::from(d)].deadline);
+            if timer.deadline <= now && earlier {
+                due = Some(slot);
+            }
+            cursor = timer.next;
+        }
+        due
+    }
+
+    /// Reports the expiry of a due timer and re-arms or frees it
+    fn expire(&mut self, slot: u16, now: MonotonicInstant) -> TimerExpiry {
+        self.unlink(slot);
+        let timer = &mut self.timers[usize::from(slot)];
+        let mut expiry = TimerExpiry {
+            id: TimerId {
+                slot,
+                generation: timer.generation,
+            },
+            deadline: timer.deadline,
+            missed: 0,
+        };
+
+        match timer.period {
+            Some(period) => {
+                // Step whole periods so the timer does not drift, skipping periods that
+                // already passed. A period due exactly now still expires on the next poll.
+                let late = (now - timer.deadline).as_nanos();
+                let periods = late.div_ceil(period.as_nanos()).max(1);
+                expiry.missed = u32::try_from(periods - 1).unwrap_or(u32::MAX);
+                let step = u32::try_from(periods)
+                    .ok()
+                    .and_then(|n| period.checked_mul(n))
+                    .unwrap_or(Duration::MAX);
+                timer.deadline += step;
+                self.link(slot);
+            }
+            None => timer.active = false,
+        }
+        expiry
+    }
+
+    fn check(&self, id: TimerId) -> Result<(), TimerError> {
+        match self.timers.get(usize::from(id.slot)) {
+            Some(t) if t.active && t.generation == id.generation => Ok(()),
+            _ => Err(TimerError::UnknownTimer),
+        }
+    }
+
+    /// Tick of an instant, saturating for instants too far out to count in ticks
+    fn tick_of(&self, t: MonotonicInstant) -> u64 {
+        let tick = t.since_start().as_nanos() / self.resolution.as_nanos();
+        u64::try_from(tick).unwrap_or(u64::MAX)
+    }
+
+    fn bucket(tick: u64) -> usize {
+        (tick % WHEEL_SLOTS as u64) as usize
+    }
+
+    /// Inserts a timer into the bucket of its deadline. Timers already due go into the
+    /// bucket polled next.
+    fn link(&mut self, slot: u16) {
+        let tick = self
+            .tick_of(self.timers[usize::from(slot)].deadline)
+            .max(self.tick);
+        let bucket = Self::bucket(tick);
+        let timer = &mut self.timers[usize::from(slot)];
+        timer.bucket = bucket;
+        timer.next = self.buckets[bucket];
+        self.buckets[bucket] = Some(slot);
+    }
+
+    /// Removes a timer from the bucket it was linked into. The bucket is recorded when
+    /// linking, as the polled tick may have moved on since.
+    fn unlink(&mut self, slot: u16) {
+        let bucket = self.timers[usize::from(slot)].bucket;
+        let mut prev: Option<u16> = None;
+        let mut cursor = self.buckets[bucket];
+        while let Some(current) = cursor {
+            let next = self.timers[usize::from(current)].next;
+            if current == slot {
+                match prev {
+                    Some(p) => self.timers[usize::from(p)].next = next,
+                    None => self.buckets[bucket] = next,
+                }
+                self.timers[usize::from(slot)].next = None;
+                return;
+            }
+            prev = cursor;
+            cursor = next;
+        }
+    }
+}
+
+impl<const N: usize> Default for TimerWheel<N> {
+    fn default() -> Self {
+        Self::new(DEFAULT_TIMER_RESOLUTION)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(ms: u64) -> MonotonicInstant {
+        MonotonicInstant::from_start(Duration::from_millis(ms))
+    }
+
+    /// Polls every expiry due at `now`
+    fn expired(
+        wheel: &mut TimerWheel<8>,
+        now: MonotonicInstant,
+    ) -> ([Option<TimerExpiry>; 8], usize) {
+        let mut out = [None; 8];
+        let mut n = 0;
+        while let Some(expiry) = wheel.poll(now) {
+            out[n] = Some(expiry);
+            n += 1;
+        }
+        (out, n)
+    }
+
+    #[test]
+    /// One-shot timers expire once, at or after their deadline
+    fn one_shot() {
+        let mut wheel = TimerWheel::<8>::default();
+        let id = wheel.start(at(10), None).unwrap();
+
+        assert_eq!(wheel.poll(at(9)), None);
+        assert_eq!(
+            wheel.poll(at(12)),
+            Some(TimerExpiry {
+                id,
+                deadline: at(10),
+                missed: 0
+            })
+        );
+        assert_eq!(wheel.poll(at(100)), None);
+        assert_eq!(wheel.cancel(id), Err(TimerError::UnknownTimer));
+        assert_eq!(wheel.active(), 0);
+    }
+
+    #[test]
+    /// Periodic timers re-arm without drift and report missed periods
+    fn periodic() {
+        let mut wheel = TimerWheel::<8>::default();
+        let id = wheel
+            .start(at(10), Some(Duration::from_millis(10)))
+            .unwrap();
+
+        assert_eq!(wheel.poll(at(10)).map(|e| e.deadline), Some(at(10)));
+        assert_eq!(wheel.poll(at(15)), None);
+        assert_eq!(wheel.poll(at(21)).map(|e| e.deadline), Some(at(20)));
+
+        // Polled late, two periods were skipped
+        let expiry = wheel.poll(at(55)).unwrap();
+        assert_eq!((expiry.deadline, expiry.missed), (at(30), 2));
+        assert_eq!(wheel.deadline(id), Some(at(60)));
+    }
+
+    #[test]
+    /// Cancelled timers never expire, rescheduled ones expire at their new deadline
+    fn cancel_and_reschedule() {
+        let mut wheel = TimerWheel::<8>::default();
+        let a = wheel.start(at(10), None).unwrap();
+        let b = wheel.start(at(10), None).unwrap();
+
+        wheel.cancel(a).unwrap();
+        wheel.reschedule(b, at(30)).unwrap();
+        assert_eq!(wheel.poll(at(20)), None);
+        assert_eq!(wheel.poll(at(30)).map(|e| e.id), Some(b));
+
+        // The freed slot is reused, the stale handle does not reach the new timer
+        let c = wheel.start(at(40), None).unwrap();
+        assert_ne!(a, c);
+        assert_eq!(wheel.cancel(a), Err(TimerError::UnknownTimer));
+        assert_eq!(wheel.deadline(c), Some(at(40)));
+
+        // Timers already due and ones a revolution out share buckets with others, only
+        // the cancelled one is removed
+        let late = wheel.start(at(5), None).unwrap();
+        let far = wheel.start(at(30 + WHEEL_SLOTS as u64), None).unwrap();
+        wheel.cancel(late).unwrap();
+        wheel.cancel(c).unwrap();
+        assert_eq!(wheel.poll(at(40)), None);
+        assert_eq!(
+            wheel.poll(at(30 + WHEEL_SLOTS as u64)).map(|e| e.id),
+            Some(far)
+        );
+        assert_eq!(wheel.active(), 0);
+    }
+
+    #[test]
+    /// Timers beyond one revolution of the wheel and large time jumps are handled
+    fn long_deadlines_and_jumps() {
+        let mut wheel = TimerWheel::<8>::default();
+        let far = wheel.start(at(1_000), None).unwrap();
+        let near = wheel.start(at(5), None).unwrap();
+
+        // Same bucket as `far` one revolution earlier, far is not due yet
+        assert_eq!(
+            wheel.poll(at(1_000 - WHEEL_SLOTS as u64)).map(|e| e.id),
+            Some(near)
+        );
+        assert_eq!(wheel.poll(at(999)), None);
+
+        // Jump far ahead, as a simulated clock might
+        let (fired, n) = expired(&mut wheel, at(1_000_000));
+        assert_eq!(n, 1);
+        assert_eq!(fired[0].map(|e| e.id), Some(far));
+    }
+
+    #[test]
+    /// Capacity is fixed
+    fn capacity() {
+        let mut wheel = TimerWheel::<2>::default();
+        wheel.start(at(1), None).unwrap();
+        wheel.start(at(1), None).unwrap();
+        assert_eq!(wheel.start(at(1), None), Err(TimerError::NoFreeTimers));
+        assert_eq!(
+            wheel.start(at(1), Some(Duration::ZERO)),
+            Err(TimerError::ZeroPeriod)
+        );
+    }
+}
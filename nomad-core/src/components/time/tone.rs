@@ -0,0 +1,458 @@
+//! TimeService Time-at-the-Tone
+//!
+//! The TimeService periodically publishes the current time, so distributed subsystems and
+//! a second flight computer share one time reference. Subscribers register with a
+//! ToneDistributor, and the SlewingClock adjusts a local clock to the distributed time
+//! without stepping it.
+
+use crate::time::{
+    LeapSecondTable, MissionTime, MonotonicInstant, TimeSource, TimeSourceHealth, UtcTime,
+};
+use core::cell::Cell;
+use core::time::Duration;
+
+const TONE: u8 = b'T';
+const FLAG_VALID: u8 = 1 << 0;
+const FLAG_SYNCHRONIZED: u8 = 1 << 1;
+const FLAG_UTC: u8 = 1 << 2;
+
+/// Length of an encoded TimeTone
+pub const TONE_MSG_LEN: usize = 24;
+
+/// Time published by the TimeService, valid at the moment it was published
+///
+/// * `sequence`: Counter incremented with every tone
+/// * `mission_time`: Mission time at the tone
+/// * `utc`: UTC at the tone, if known
+/// * `valid`: Whether the publishing TimeSource was valid. Invalid tones must not be used
+///   to adjust clocks.
+/// * `synchronized`: Whether the publisher is synchronised to an external reference
+/// * `tai_minus_utc`: Leap seconds in effect, TAI - UTC in seconds
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TimeTone {
+    pub sequence: u32,
+    pub mission_time: MissionTime,
+    pub utc: Option<UtcTime>,
+    pub valid: bool,
+    pub synchronized: bool,
+    pub tai_minus_utc: i32,
+}
+
+/// Reasons a tone cannot be encoded, decoded or registered
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ToneError {
+    /// Buffer shorter than TONE_MSG_LEN
+    BufferTooSmall,
+    /// Message is not a tone
+    InvalidMessage,
+    /// Every subscriber slot is taken
+    NoFreeSlots,
+}
+
+impl TimeTone {
+    /// Encodes the tone for other nodes, little endian:
+    /// `['T'][sequence: u32][flags: u8][mission time ns: i64][utc ns: u64][tai - utc: i16]`.
+    /// Returns the number of bytes written.
+    pub fn encode(&self, buf: &mut [u8]) -> Result<usize, ToneError> {
+        let msg = buf
+            .get_mut(..TONE_MSG_LEN)
+            .ok_or(ToneError::BufferTooSmall)?;
+        let mut flags = 0;
+        if self.valid {
+            flags |= FLAG_VALID;
+        }
+        if self.synchronized {
+            flags |= FLAG_SYNCHRONIZED;
+        }
+        if self.utc.is_some() {
+            flags |= FLAG_UTC;
+        }
+        let utc = self
+            .utc
+            .map_or(0, |utc| u64::try_from(utc.0.as_nanos()).unwrap_or(u64::MAX));
+        let leap = i16::try_from(self.tai_minus_utc).unwrap_or(i16::MAX);
+
+        msg[0] = TONE;
+        msg[1..5].copy_from_slice(&self.sequence.to_le_bytes());
+        msg[5] = flags;
+        msg[6..14].copy_from_slice(&self.mission_time.as_nanos().to_le_bytes());
+        msg[14..22].copy_from_slice(&utc.to_le_bytes());
+        msg[22..24].copy_from_slice(&leap.to_le_bytes());
+        Ok(TONE_MSG_LEN)
+    }
+
+    /// Decodes a tone encoded by `encode`
+    pub fn decode(buf: &[u8]) -> Result<Self, ToneError> {
+        let msg = buf.get(..TONE_MSG_LEN).ok_or(ToneError::BufferTooSmall)?;
+        if msg[0] != TONE {
+            return Err(ToneError::InvalidMessage);
+        }
+        let field = |range: core::ops::Range<usize>| -> [u8; 8] {
+            msg[range].try_into().unwrap_or_default()
+        };
+        let flags = msg[5];
+        let utc = Duration::from_nanos(u64::from_le_bytes(field(14..22)));
+        Ok(Self {
+            sequence: u32::from_le_bytes([msg[1], msg[2], msg[3], msg[4]]),
+            mission_time: MissionTime::from_nanos(i64::from_le_bytes(field(6..14))),
+            utc: (flags & FLAG_UTC != 0).then_some(UtcTime(utc)),
+            valid: flags & FLAG_VALID != 0,
+            synchronized: flags & FLAG_SYNCHRONIZED != 0,
+            tai_minus_utc: i16::from_le_bytes([msg[22], msg[23]]).into(),
+        })
+    }
+}
+
+/// Schedule of the tones published by the TimeService
+///
+/// * `period`: Time between tones, None when not publishing
+/// * `next`: Local monotonic time of the next tone
+/// * `sequence`: Sequence number of the next tone
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct ToneSchedule {
+    pub(crate) period: Option<Duration>,
+    pub(crate) next: MonotonicInstant,
+    pub(crate) sequence: u32,
+}
+
+impl ToneSchedule {
+    pub(crate) const fn new() -> Self {
+        Self {
+            period: None,
+            next: MonotonicInstant::ZERO,
+            sequence: 0,
+        }
+    }
+
+    /// Whether a tone is due at `now`. Moves to the next tone, tones missed entirely are
+    /// skipped rather than published late in a burst.
+    pub(crate) fn due(&mut self, now: MonotonicInstant) -> bool {
+        let Some(period) = self.period else {
+            return false;
+        };
+        if now < self.next {
+            return false;
+        }
+        self.next += period;
+        if self.next <= now {
+            self.next = now + period;
+        }
+        true
+    }
+
+    /// Sequence number for the next tone
+    pub(crate) fn next_sequence(&mut self) -> u32 {
+        let sequence = self.sequence;
+        self.sequence = self.sequence.wrapping_add(1);
+        sequence
+    }
+}
+
+/// Consumer of distributed time
+pub trait ToneSubscriber {
+    /// Called with every tone, as soon as possible after it was published
+    fn on_tone(&self, tone: &TimeTone);
+}
+
+/// Subscribers registered to receive the tones of a TimeService
+///
+/// * `subscribers`: Registered subscribers, None for free slots
+pub struct ToneDistributor<'a, const N: usize> {
+    subscribers: [Option<&'a dyn ToneSubscriber>; N],
+}
+
+impl<'a, const N: usize> ToneDistributor<'a, N> {
+    pub const fn new() -> Self {
+        Self {
+            subscribers: [None; N],
+        }
+    }
+
+    /// Registers a subscriber, returning the slot to unregister it with
+    pub fn register(&mut self, subscriber: &'a dyn ToneSubscriber) -> Result<usize, ToneError> {
+        let slot = self
+            .subscribers
+            .iter()
+            .position(Option::is_none)
+            .ok_or(ToneError::NoFreeSlots)?;
+        self.subscribers[slot] = Some(subscriber);
+        Ok(slot)
+    }
+
+    /// Removes the subscriber in `slot`
+    pub fn unregister(&mut self, slot: usize) {
+        if let Some(entry) = self.subscribers.get_mut(slot) {
+            *entry = None;
+        }
+    }
+
+    /// Hands the tone to every registered subscriber
+    pub fn publish(&self, tone: &TimeTone) {
+        for subscriber in self.subscribers.iter().flatten() {
+            subscriber.on_tone(tone);
+        }
+    }
+}
+
+impl<const N: usize> Default for ToneDistributor<'_, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Tuning of the SlewingClock
+///
+/// * `max_slew_ppm`: Fastest rate at which the clock is adjusted, in parts per million
+/// * `step_threshold`: Differences larger than this are stepped instead of slewed
+/// * `latency`: Expected delay between publishing and receiving a tone, added to it
+#[derive(Copy, Clone, Debug)]
+pub struct SlewConfig {
+    pub max_slew_ppm: u32,
+    pub step_threshold: Duration,
+    pub latency: Duration,
+}
+
+impl Default for SlewConfig {
+    fn default() -> Self {
+        Self {
+            max_slew_ppm: 500,
+            step_threshold: Duration::from_secs(1),
+            latency: Duration::ZERO,
+        }
+    }
+}
+
+/// Local clock following the distributed time. Mission time of the local source is offset
+/// towards the time of the latest tone, changing the offset by at most `max_slew_ppm` so
+/// mission time neither jumps nor runs backwards. The first tone, and differences beyond
+/// the step threshold, are applied at once. Monotonic time is never adjusted.
+///
+/// * `source`: Local TimeSource
+/// * `config`: Slew rate and step threshold
+/// * `offset_ns`: Offset applied to local mission time at `since`
+/// * `target_ns`: Offset the clock slews towards
+/// * `since`: Local monotonic time `offset_ns` was valid at
+/// * `synchronized`: Whether a valid tone was received
+/// * `tai_minus_utc`: Leap seconds of the latest tone
+/// * `steps`: Number of times the offset was stepped
+pub struct SlewingClock<T: TimeSource> {
+    // NOTE: NOT THREADSAFE
+    source: T,
+    config: SlewConfig,
+    offset_ns: Cell<i64>,
+    target_ns: Cell<i64>,
+    since: Cell<MonotonicInstant>,
+    synchronized: Cell<bool>,
+    tai_minus_utc: Cell<Option<i32>>,
+    steps: Cell<u32>,
+}
+
+impl<T: TimeSource> SlewingClock<T> {
+    pub fn new(source: T, config: SlewConfig) -> Self {
+        Self {
+            source,
+            config,
+            offset_ns: Cell::new(0),
+            target_ns: Cell::new(0),
+            since: Cell::new(MonotonicInstant::ZERO),
+            synchronized: Cell::new(false),
+            tai_minus_utc: Cell::new(None),
+            steps: Cell::new(0),
+        }
+    }
+
+    /// Offset currently applied to local mission time, in nanoseconds
+    pub fn offset_nanos(&self) -> i64 {
+        self.offset_at(self.source.monotonic())
+    }
+
+    /// Correction still to be slewed, in nanoseconds
+    pub fn remaining_nanos(&self) -> i64 {
+        self.target_ns.get() - self.offset_nanos()
+    }
+
+    /// Leap seconds (TAI - UTC) distributed with the latest tone
+    pub fn tai_minus_utc(&self) -> Option<i32> {
+        self.tai_minus_utc.get()
+    }
+
+    /// Number of times the clock was stepped instead of slewed
+    pub fn steps(&self) -> u32 {
+        self.steps.get()
+    }
+
+    /// Borrow the local source
+    pub fn inner(&self) -> &T {
+        &self.source
+    }
+
+    /// Offset at local monotonic time `now`, slewed from `offset_ns` towards `target_ns`
+    fn offset_at(&self, now: MonotonicInstant) -> i64 {
+        let elapsed = (now - self.since.get()).as_nanos();
+        let max_change = elapsed.saturating_mul(self.config.max_slew_ppm.into()) / 1_000_000;
+        let max_change = i64::try_from(max_change).unwrap_or(i64::MAX);
+        let offset = self.offset_ns.get();
+        let remaining = self.target_ns.get().saturating_sub(offset);
+        offset + remaining.clamp(-max_change, max_change)
+    }
+}
+
+impl<T: TimeSource> ToneSubscriber for SlewingClock<T> {
+    fn on_tone(&self, tone: &TimeTone) {
+        if !tone.valid {
+            return;
+        }
+        let now = self.source.monotonic();
+        let latency = i64::try_from(self.config.latency.as_nanos()).unwrap_or(i64::MAX);
+        let target = tone
+            .mission_time
+            .as_nanos()
+            .saturating_add(latency)
+            .saturating_sub(self.source.mission_time().as_nanos());
+        let current = self.offset_at(now);
+        let step = self.config.step_threshold.as_nanos();
+
+        if !self.synchronized.get() || target.abs_diff(current) as u128 > step {
+            self.offset_ns.set(target);
+            if self.synchronized.get() {
+                self.steps.set(self.steps.get().saturating_add(1));
+            }
+        } else {
+            self.offset_ns.set(current);
+        }
+        self.target_ns.set(target);
+        self.since.set(now);
+        self.synchronized.set(true);
+        self.tai_minus_utc.set(Some(tone.tai_minus_utc));
+    }
+}
+
+impl<T: TimeSource> TimeSource for SlewingClock<T> {
+    fn monotonic(&self) -> MonotonicInstant {
+        self.source.monotonic()
+    }
+
+    fn mission_time(&self) -> MissionTime {
+        let offset = self.offset_nanos();
+        MissionTime::from_nanos(self.source.mission_time().as_nanos().saturating_add(offset))
+    }
+
+    /// Synchronised once a valid tone was received
+    fn health(&self) -> TimeSourceHealth {
+        let mut health = self.source.health();
+        if self.synchronized.get() {
+            health.synchronized = true;
+            health.error_bound = Some(Duration::from_nanos(self.remaining_nanos().unsigned_abs()));
+        }
+        health
+    }
+
+    fn leap_seconds_changed(&self, leap_seconds: &LeapSecondTable) {
+        self.source.leap_seconds_changed(leap_seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::SimulatedTimeSource;
+
+    fn tone(sequence: u32, mission_secs: u64) -> TimeTone {
+        TimeTone {
+            sequence,
+            mission_time: MissionTime::after_epoch(Duration::from_secs(mission_secs)),
+            utc: None,
+            valid: true,
+            synchronized: true,
+            tai_minus_utc: 37,
+        }
+    }
+
+    #[test]
+    /// Tones survive encoding and decoding
+    fn tone_roundtrip() {
+        let tone = TimeTone {
+            sequence: 42,
+            mission_time: MissionTime::before_epoch(Duration::from_millis(1_500)),
+            utc: Some(UtcTime::from_unix_secs(1_735_689_600)),
+            valid: true,
+            synchronized: false,
+            tai_minus_utc: 37,
+        };
+        let mut buf = [0u8; TONE_MSG_LEN];
+        assert_eq!(tone.encode(&mut buf), Ok(TONE_MSG_LEN));
+        assert_eq!(TimeTone::decode(&buf), Ok(tone));
+
+        assert_eq!(tone.encode(&mut [0u8; 8]), Err(ToneError::BufferTooSmall));
+        buf[0] = b'X';
+        assert_eq!(TimeTone::decode(&buf), Err(ToneError::InvalidMessage));
+    }
+
+    #[test]
+    /// The clock steps to the first tone, then slews at the configured rate
+    fn slewing_clock() {
+        let local = SimulatedTimeSource::new();
+        let clock = SlewingClock::new(&local, SlewConfig::default());
+        let mut tones = ToneDistributor::<'_, 2>::new();
+        tones.register(&clock).unwrap();
+
+        // First tone: stepped from 0 to 100s
+        tones.publish(&tone(0, 100));
+        assert_eq!(
+            clock.mission_time(),
+            MissionTime::after_epoch(Duration::from_secs(100))
+        );
+        assert_eq!(clock.tai_minus_utc(), Some(37));
+
+        // Local clock 10ms fast: slewed at 500ppm, 5us per 10ms
+        local.advance(Duration::from_secs(10));
+        tones.publish(&TimeTone {
+            mission_time: MissionTime::after_epoch(Duration::from_millis(109_990)),
+            ..tone(1, 0)
+        });
+        assert_eq!(clock.remaining_nanos(), -10_000_000);
+        local.advance(Duration::from_millis(10));
+        assert_eq!(clock.remaining_nanos(), -9_995_000);
+        assert_eq!(
+            clock.mission_time(),
+            MissionTime::after_epoch(Duration::from_nanos(110_009_995_000))
+        );
+
+        // Fully slewed after 20s
+        local.advance(Duration::from_secs(20));
+        assert_eq!(clock.remaining_nanos(), 0);
+        assert_eq!(clock.steps(), 0);
+
+        // Large differences are stepped, invalid tones ignored
+        tones.publish(&tone(2, 500));
+        assert_eq!(clock.steps(), 1);
+        assert_eq!(
+            clock.mission_time(),
+            MissionTime::after_epoch(Duration::from_secs(500))
+        );
+        tones.publish(&TimeTone {
+            valid: false,
+            ..tone(3, 0)
+        });
+        assert_eq!(
+            clock.mission_time(),
+            MissionTime::after_epoch(Duration::from_secs(500))
+        );
+    }
+
+    #[test]
+    /// Tones missed entirely are skipped
+    fn tone_schedule() {
+        let mut schedule = ToneSchedule::new();
+        let at = |ms| MonotonicInstant::from_start(Duration::from_millis(ms));
+        assert!(!schedule.due(at(0)));
+
+        schedule.period = Some(Duration::from_millis(100));
+        schedule.next = at(100);
+        assert!(!schedule.due(at(50)));
+        assert!(schedule.due(at(100)));
+        assert!(!schedule.due(at(150)));
+        assert!(schedule.due(at(450)));
+        assert_eq!(schedule.next, at(550));
+    }
+}
@@ -10,12 +10,18 @@ pub mod component;
 
 // Logging Subsystem
 pub mod logger;
-pub use logger::{LogBuffer, LogHandle, LogLevel, LogRecord, LogSink};
+pub use logger::{
+    FormattedLogHandle, LogBuffer, LogHandle, LogLevel, LogMessage, LogRecord, LogSink,
+    MESSAGE_CAPACITY, RecordDecodeError,
+};
 pub mod log_macros;
 pub use log_macros::*;
 
 // Time Subsystem
 pub mod time;
 
+// OSAL Interfaces
+pub mod osal;
+
 // ========== Nomad Standard Component Collection =========
 pub mod components;
@@ -3,6 +3,9 @@
 //! Provides Logging primities that can be used to implement Loggers
 
 use crate::component::ComponentId;
+use crate::time::MissionTime;
+use core::fmt;
+use core::ops::Deref;
 
 /// A LogSink is the final destination of log records.
 /// It consumes a LogRecord. It can be used to implement
@@ -19,8 +22,16 @@ pub trait LogHandle {
     fn log_message(&mut self, component: ComponentId, level: LogLevel, message: &'static str);
 }
 
-/// Severity levels for logging.
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// A Logger that also takes messages formatted at runtime, e.g. forwarded from the `log`
+/// facade. Loggers that only handle static messages do not need to implement it.
+pub trait FormattedLogHandle: LogHandle {
+    /// Logs a message formatted at runtime. The text is copied into the record, truncated
+    /// to `MESSAGE_CAPACITY` bytes.
+    fn log_formatted(&mut self, component: ComponentId, level: LogLevel, args: fmt::Arguments);
+}
+
+/// Severity levels for logging. Ordered from least to most severe.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum LogLevel {
     Debug,
     Info,
@@ -28,12 +39,275 @@ pub enum LogLevel {
     Error,
 }
 
+impl LogLevel {
+    /// Upper case name of the level, as used in text log formats
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+        }
+    }
+
+    /// Parses a level from its name, case insensitive
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            LogLevel::Debug,
+            LogLevel::Info,
+            LogLevel::Warn,
+            LogLevel::Error,
+        ]
+        .into_iter()
+        .find(|level| level.as_str().eq_ignore_ascii_case(name))
+    }
+
+    /// Numeric code of the level, as used in binary log formats
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    /// Converts a numeric code back into a level
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(LogLevel::Debug),
+            1 => Some(LogLevel::Info),
+            2 => Some(LogLevel::Warn),
+            3 => Some(LogLevel::Error),
+            _ => None,
+        }
+    }
+}
+
+/// Maximum number of bytes of a formatted message stored in a LogRecord
+pub const MESSAGE_CAPACITY: usize = 64;
+
+/// Message of a LogRecord created by the FSW. Either a static string, or the text of a
+/// message formatted at runtime, copied into the record and truncated to
+/// `MESSAGE_CAPACITY` bytes. Derefs to `str`.
+#[derive(Copy, Clone)]
+pub struct LogMessage(MessageRepr);
+
+#[derive(Copy, Clone)]
+enum MessageRepr {
+    Static(&'static str),
+    Inline {
+        len: u8,
+        bytes: [u8; MESSAGE_CAPACITY],
+    },
+}
+
+impl LogMessage {
+    /// Copies `text` into a message, truncated at a char boundary to fit
+    pub fn copy_from(text: &str) -> Self {
+        let mut message = Self::empty();
+        message.push_str(text);
+        message
+    }
+
+    /// Formats `args` into a message. Arguments without runtime values are kept as a static
+    /// string, everything else is copied and truncated to fit.
+    pub fn format(args: fmt::Arguments) -> Self {
+        if let Some(message) = args.as_str() {
+            return Self(MessageRepr::Static(message));
+        }
+        let mut message = Self::empty();
+        // Writing never fails, text that does not fit is dropped
+        let _ = fmt::write(&mut message, args);
+        message
+    }
+
+    /// The message text
+    pub fn as_str(&self) -> &str {
+        match &self.0 {
+            MessageRepr::Static(message) => message,
+            // Only whole chars are copied in, so the bytes are valid UTF-8
+            MessageRepr::Inline { len, bytes } => {
+                core::str::from_utf8(&bytes[..*len as usize]).unwrap_or_default()
+            }
+        }
+    }
+
+    fn empty() -> Self {
+        Self(MessageRepr::Inline {
+            len: 0,
+            bytes: [0; MESSAGE_CAPACITY],
+        })
+    }
+
+    /// Appends as much of `text` as fits, up to the last fitting char boundary
+    fn push_str(&mut self, text: &str) {
+        let MessageRepr::Inline { len, bytes } = &mut self.0 else {
+            return;
+        };
+        let start = *len as usize;
+        let mut end = text.len().min(MESSAGE_CAPACITY - start);
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        bytes[start..start + end].copy_from_slice(&text.as_bytes()[..end]);
+        *len += end as u8;
+    }
+}
+
+impl fmt::Write for LogMessage {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+impl From<&'static str> for LogMessage {
+    fn from(message: &'static str) -> Self {
+        Self(MessageRepr::Static(message))
+    }
+}
+
+impl Deref for LogMessage {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for LogMessage {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq for LogMessage {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for LogMessage {}
+
+impl PartialEq<str> for LogMessage {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for LogMessage {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl fmt::Debug for LogMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for LogMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// A represenation of a log entry
-#[derive(Copy, Clone, Debug)]
-pub struct LogRecord {
+///
+/// Records created by the FSW carry a `LogMessage`. Tools that decode records from a file
+/// or a packet can use a borrowed or owned message instead.
+///
+/// * `timestamp`: Mission time at which the record was created, negative during the countdown
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LogRecord<M = LogMessage> {
     pub level: LogLevel,
     pub component: ComponentId,
-    pub message: &'static str,
+    pub timestamp: MissionTime,
+    pub message: M,
+}
+
+/// Size of the fixed part of a binary encoded LogRecord
+/// `[level: u8][component: u16][timestamp ns: i64][len: u16]`
+pub const RECORD_HEADER_LEN: usize = 13;
+
+/// Errors that can occur decoding a binary LogRecord
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RecordDecodeError {
+    /// Not enough bytes for a complete record
+    Truncated,
+    /// Level code does not map to a LogLevel
+    InvalidLevel(u8),
+    /// Message is not valid UTF-8
+    InvalidMessage,
+}
+
+impl<M: AsRef<str>> LogRecord<M> {
+    /// Number of bytes the record occupies when binary encoded
+    pub fn encoded_len(&self) -> usize {
+        RECORD_HEADER_LEN + self.encoded_message().len()
+    }
+
+    /// Message as encoded, truncated at a char boundary to at most `u16::MAX` bytes
+    fn encoded_message(&self) -> &str {
+        let message = self.message.as_ref();
+        let mut end = message.len().min(u16::MAX as usize);
+        while !message.is_char_boundary(end) {
+            end -= 1;
+        }
+        &message[..end]
+    }
+
+    /// Binary encodes the record into `out`, little endian:
+    /// `[level: u8][component: u16][timestamp ns: i64][len: u16][message: len bytes]`
+    ///
+    /// Messages longer than `u16::MAX` bytes are truncated at a char boundary. Returns the
+    /// number of bytes written, or None if `out` is too small.
+    pub fn encode(&self, out: &mut [u8]) -> Option<usize> {
+        let total = self.encoded_len();
+        if out.len() < total {
+            return None;
+        }
+
+        let msg = self.encoded_message().as_bytes();
+        let nanos = self.timestamp.as_nanos();
+
+        out[0] = self.level.code();
+        out[1..3].copy_from_slice(&self.component.0.to_le_bytes());
+        out[3..11].copy_from_slice(&nanos.to_le_bytes());
+        out[11..13].copy_from_slice(&(msg.len() as u16).to_le_bytes());
+        out[RECORD_HEADER_LEN..total].copy_from_slice(msg);
+        Some(total)
+    }
+}
+
+impl<'a> LogRecord<&'a str> {
+    /// Decodes a binary encoded record from the start of `buf`, borrowing the message.
+    /// Returns the record and the number of bytes consumed.
+    pub fn decode(buf: &'a [u8]) -> Result<(Self, usize), RecordDecodeError> {
+        if buf.len() < RECORD_HEADER_LEN {
+            return Err(RecordDecodeError::Truncated);
+        }
+
+        let level = LogLevel::from_code(buf[0]).ok_or(RecordDecodeError::InvalidLevel(buf[0]))?;
+        let component = u16::from_le_bytes([buf[1], buf[2]]);
+        let mut nanos = [0u8; 8];
+        nanos.copy_from_slice(&buf[3..11]);
+        let len = u16::from_le_bytes([buf[11], buf[12]]) as usize;
+
+        let total = RECORD_HEADER_LEN + len;
+        if buf.len() < total {
+            return Err(RecordDecodeError::Truncated);
+        }
+        let message = core::str::from_utf8(&buf[RECORD_HEADER_LEN..total])
+            .map_err(|_| RecordDecodeError::InvalidMessage)?;
+
+        Ok((
+            LogRecord {
+                level,
+                component: ComponentId(component),
+                timestamp: MissionTime::from_nanos(i64::from_le_bytes(nanos)),
+                message,
+            },
+            total,
+        ))
+    }
 }
 
 /// In-memory ring buffer for log records. Loggers will manage these and store
@@ -56,9 +330,14 @@ impl<const LOGGER_CAPACITY: usize> LogBuffer<LOGGER_CAPACITY> {
     }
 
     /// Push a log record, overwriting the oldest when full.
-    pub fn push(&mut self, rec: LogRecord) {
+    pub fn push<M: Into<LogMessage>>(&mut self, rec: LogRecord<M>) {
         let idx = (self.head + self.len) % LOGGER_CAPACITY;
-        self.records[idx] = Some(rec);
+        self.records[idx] = Some(LogRecord {
+            level: rec.level,
+            component: rec.component,
+            timestamp: rec.timestamp,
+            message: rec.message.into(),
+        });
         if self.len < LOGGER_CAPACITY {
             self.len += 1;
         } else {
@@ -100,6 +379,22 @@ impl<const LOGGER_CAPACITY: usize> LogBuffer<LOGGER_CAPACITY> {
     pub fn is_empty(&self) -> bool {
         self.len == 0
     }
+
+    /// Number of records currently held
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the next push will overwrite the oldest record
+    pub fn is_full(&self) -> bool {
+        self.len == LOGGER_CAPACITY
+    }
+}
+
+impl<const LOGGER_CAPACITY: usize> Default for LogBuffer<LOGGER_CAPACITY> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 // ========== TESTS ==========
@@ -110,13 +405,13 @@ mod tests {
 
     /// Helper function for extracting messages of LogRecord from a LogBuffer
     /// Collects the messages into a fixed size array
-    fn collect_messages<const CAP: usize>(buf: &LogBuffer<CAP>) -> [Option<&'static str>; CAP] {
-        let mut out: [Option<&'static str>; CAP] = [None; CAP];
+    fn collect_messages<const CAP: usize>(buf: &LogBuffer<CAP>) -> [Option<&str>; CAP] {
+        let mut out: [Option<&str>; CAP] = [None; CAP];
         let mut idx = 0;
 
         for rec in buf.iter() {
             if idx < CAP {
-                out[idx] = Some(rec.message);
+                out[idx] = Some(rec.message.as_str());
                 idx += 1;
             }
         }
@@ -136,12 +431,14 @@ mod tests {
 
     #[test]
     /// Pushes a single record to the log buffer
+    #[allow(clippy::needless_range_loop)]
     fn single_push() {
         const CAP: usize = 4;
         let mut buf: LogBuffer<CAP> = LogBuffer::new();
 
         buf.push(LogRecord {
             level: LogLevel::Info,
+            timestamp: MissionTime::ZERO,
             component: ComponentId(0),
             message: "Hello World!",
         });
@@ -163,16 +460,19 @@ mod tests {
 
         buf.push(LogRecord {
             level: LogLevel::Info,
+            timestamp: MissionTime::ZERO,
             component: ComponentId(0),
             message: "one",
         });
         buf.push(LogRecord {
             level: LogLevel::Info,
+            timestamp: MissionTime::ZERO,
             component: ComponentId(1),
             message: "two",
         });
         buf.push(LogRecord {
             level: LogLevel::Info,
+            timestamp: MissionTime::ZERO,
             component: ComponentId(2),
             message: "three",
         });
@@ -193,16 +493,19 @@ mod tests {
 
         buf.push(LogRecord {
             level: LogLevel::Info,
+            timestamp: MissionTime::ZERO,
             component: ComponentId(0),
             message: "one",
         });
         buf.push(LogRecord {
             level: LogLevel::Info,
+            timestamp: MissionTime::ZERO,
             component: ComponentId(1),
             message: "two",
         });
         buf.push(LogRecord {
             level: LogLevel::Info,
+            timestamp: MissionTime::ZERO,
             component: ComponentId(2),
             message: "three",
         });
@@ -221,11 +524,13 @@ mod tests {
 
         buf.push(LogRecord {
             level: LogLevel::Info,
+            timestamp: MissionTime::ZERO,
             component: ComponentId(0),
             message: "one",
         });
         buf.push(LogRecord {
             level: LogLevel::Info,
+            timestamp: MissionTime::ZERO,
             component: ComponentId(1),
             message: "two",
         });
@@ -240,6 +545,7 @@ mod tests {
         buf.push(LogRecord {
             level: LogLevel::Warn,
             message: "after clear",
+            timestamp: MissionTime::ZERO,
             component: ComponentId(0),
         });
 
@@ -249,22 +555,26 @@ mod tests {
 
     #[test]
     /// Pushes log entries from multipe components
+    #[allow(clippy::explicit_counter_loop)]
     fn multiple_components() {
         const CAP: usize = 4;
         let mut buf: LogBuffer<CAP> = LogBuffer::new();
 
         buf.push(LogRecord {
             level: LogLevel::Info,
+            timestamp: MissionTime::ZERO,
             component: ComponentId(0),
             message: "fsw msg",
         });
         buf.push(LogRecord {
             level: LogLevel::Warn,
+            timestamp: MissionTime::ZERO,
             component: ComponentId(1),
             message: "imu warn",
         });
         buf.push(LogRecord {
             level: LogLevel::Error,
+            timestamp: MissionTime::ZERO,
             component: ComponentId(2),
             message: "nav error",
         });
@@ -282,4 +592,114 @@ mod tests {
         assert_eq!(comps[1], Some(ComponentId(1)));
         assert_eq!(comps[2], Some(ComponentId(2)));
     }
+
+    #[test]
+    /// Encodes a record and decodes it back
+    fn binary_roundtrip() {
+        let rec = LogRecord {
+            level: LogLevel::Warn,
+            component: ComponentId(513),
+            timestamp: MissionTime::before_epoch(core::time::Duration::new(12, 345_678_000)),
+            message: "imu warn",
+        };
+
+        let mut out = [0u8; 32];
+        let n = rec.encode(&mut out).unwrap();
+        assert_eq!(n, RECORD_HEADER_LEN + 8);
+        assert_eq!(n, rec.encoded_len());
+
+        let (decoded, used) = LogRecord::decode(&out[..n]).unwrap();
+        assert_eq!(used, n);
+        assert_eq!(decoded, rec);
+    }
+
+    #[test]
+    /// Decoding rejects incomplete and malformed records
+    fn binary_decode_errors() {
+        let rec = LogRecord {
+            level: LogLevel::Info,
+            component: ComponentId(1),
+            timestamp: MissionTime::ZERO,
+            message: "hello",
+        };
+
+        let mut out = [0u8; 32];
+        assert_eq!(rec.encode(&mut out[..4]), None);
+        let n = rec.encode(&mut out).unwrap();
+
+        assert_eq!(
+            LogRecord::decode(&out[..n - 1]),
+            Err(RecordDecodeError::Truncated)
+        );
+        out[0] = 9;
+        assert_eq!(
+            LogRecord::decode(&out[..n]),
+            Err(RecordDecodeError::InvalidLevel(9))
+        );
+    }
+
+    /// 0x10000 bytes of two byte chars ("é"), one more byte than a record can carry
+    static LONG_MESSAGE: [u8; 0x10000] = {
+        let mut bytes = [0u8; 0x10000];
+        let mut i = 0;
+        while i < bytes.len() {
+            bytes[i] = if i % 2 == 0 { 0xC3 } else { 0xA9 };
+            i += 1;
+        }
+        bytes
+    };
+
+    #[test]
+    /// Messages longer than a record can carry are truncated at a char boundary
+    fn binary_truncates_long_message() {
+        let rec = LogRecord {
+            level: LogLevel::Info,
+            component: ComponentId(1),
+            timestamp: MissionTime::ZERO,
+            message: core::str::from_utf8(&LONG_MESSAGE).unwrap(),
+        };
+
+        let mut out = [0u8; RECORD_HEADER_LEN + 0x10000];
+        let n = rec.encode(&mut out).unwrap();
+        assert_eq!(n, rec.encoded_len());
+        assert_eq!(n, RECORD_HEADER_LEN + 0xFFFE);
+
+        let (decoded, _) = LogRecord::decode(&out[..n]).unwrap();
+        assert!(rec.message.starts_with(decoded.message));
+    }
+
+    #[test]
+    /// Level names and codes convert in both directions
+    fn level_conversions() {
+        for level in [
+            LogLevel::Debug,
+            LogLevel::Info,
+            LogLevel::Warn,
+            LogLevel::Error,
+        ] {
+            assert_eq!(LogLevel::from_code(level.code()), Some(level));
+            assert_eq!(LogLevel::from_name(level.as_str()), Some(level));
+        }
+        assert_eq!(LogLevel::from_name("warn"), Some(LogLevel::Warn));
+        assert_eq!(LogLevel::from_name("fatal"), None);
+        assert!(LogLevel::Debug < LogLevel::Error);
+    }
+
+    #[test]
+    /// Formatted messages are copied into the record and truncated at a char boundary,
+    /// messages without runtime values stay static
+    fn formatted_messages() {
+        let sats = core::hint::black_box(7);
+        let message = LogMessage::format(format_args!("sats={}", sats));
+        assert_eq!(message, "sats=7");
+        assert_eq!(LogMessage::format(format_args!("no fix")), "no fix");
+
+        // One byte followed by two byte chars, the last one does not fit
+        let mut long = LogMessage::copy_from("x");
+        for _ in 0..MESSAGE_CAPACITY {
+            fmt::Write::write_str(&mut long, "ü").unwrap();
+        }
+        assert_eq!(long.len(), MESSAGE_CAPACITY - 1);
+        assert!(long.ends_with('ü'));
+    }
 }
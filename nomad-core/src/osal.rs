@@ -0,0 +1,54 @@
+//! Nomad OSAL Interfaces
+//!
+//! Traits for the resources an operating system provides, such as tasks, synchronisation,
+//! message queues, filesystems, network sockets, serial ports and signals. Components are
+//! written against these traits and stay platform-agnostic, `nomad-osal` implements them for
+//! each supported OS.
+
+pub mod fs;
+pub mod net;
+pub mod queue;
+pub mod serial;
+pub mod signal;
+pub mod sync;
+pub mod task;
+pub use fs::{DirEntry, File, FileSystem, Metadata, OpenOptions, SeekFrom};
+pub use net::{IoMode, NetworkApi, TcpListener, TcpStream, UdpSocket};
+pub use queue::{Queue, QueueStats, SendError};
+pub use serial::{DataBits, FlowControl, Parity, SerialConfig, SerialPort, StopBits};
+pub use signal::{Signal, Signals};
+pub use sync::{EventFlags, FlagWait, Mutex, Semaphore};
+pub use task::{Task, TaskApi, TaskConfig, TaskPriority};
+
+/// Errors reported by OSAL implementations
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OsalError {
+    /// An argument is out of range for this platform, e.g. a stack size below the minimum
+    InvalidArgument,
+    /// Not supported on this platform
+    Unsupported,
+    /// Not permitted, e.g. realtime priorities without the required privileges
+    PermissionDenied,
+    /// The OS ran out of resources, e.g. memory or handles
+    ResourceExhausted,
+    /// The task ended by panicking
+    TaskPanicked,
+    /// A wait ran into its timeout
+    Timeout,
+    /// A non-blocking call could not complete right away, e.g. sending to a full queue
+    WouldBlock,
+    /// A counter would exceed its maximum, e.g. giving a full semaphore
+    Overflow,
+    /// The file or directory does not exist
+    NotFound,
+    /// The file or directory already exists, or the address is in use
+    AlreadyExists,
+    /// The storage is full
+    NoSpace,
+    /// The remote end refused the connection
+    ConnectionRefused,
+    /// The connection was closed or reset
+    Disconnected,
+    /// Any other error, with the OS error code
+    Os(i32),
+}
@@ -0,0 +1,166 @@
+//! OSAL Filesystem
+//!
+//! File access that works the same on a Linux host and on embedded flash. Paths are
+//! absolute virtual paths separated by `/`, the first component names a mount point, e.g.
+//! `/cf/logs/fsw.log` on the persistent `/cf` volume or `/ram/tmp` on a RAM disk.
+
+use super::OsalError;
+
+/// How a file is opened
+///
+/// * `read`: Allow reading
+/// * `write`: Allow writing
+/// * `create`: Create the file if it does not exist
+/// * `truncate`: Empty the file when opening it
+/// * `append`: Write at the end of the file
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct OpenOptions {
+    pub read: bool,
+    pub write: bool,
+    pub create: bool,
+    pub truncate: bool,
+    pub append: bool,
+}
+
+impl OpenOptions {
+    /// Opens an existing file for reading
+    pub const READ: Self = Self {
+        read: true,
+        write: false,
+        create: false,
+        truncate: false,
+        append: false,
+    };
+    /// Opens an existing file for reading and writing
+    pub const READ_WRITE: Self = Self {
+        write: true,
+        ..Self::READ
+    };
+    /// Creates a file, or empties an existing one, for writing
+    pub const CREATE: Self = Self {
+        read: false,
+        write: true,
+        create: true,
+        truncate: true,
+        append: false,
+    };
+    /// Appends to a file, creating it if needed
+    pub const APPEND: Self = Self {
+        truncate: false,
+        append: true,
+        ..Self::CREATE
+    };
+}
+
+/// Position to seek to
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SeekFrom {
+    /// Bytes from the start of the file
+    Start(u64),
+    /// Bytes from the end of the file
+    End(i64),
+    /// Bytes from the current position
+    Current(i64),
+}
+
+/// Size and kind of a filesystem entry
+///
+/// * `size`: Size in bytes, zero for directories
+/// * `is_dir`: Whether the entry is a directory
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Metadata {
+    pub size: u64,
+    pub is_dir: bool,
+}
+
+/// An entry of a directory listing
+///
+/// * `name`: Name of the entry, without the directory
+/// * `metadata`: Size and kind of the entry
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DirEntry<'a> {
+    pub name: &'a str,
+    pub metadata: Metadata,
+}
+
+/// An open file. Closed when dropped.
+pub trait File {
+    /// Reads into `buf` from the current position, returning the bytes read. Zero at the
+    /// end of the file.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, OsalError>;
+
+    /// Writes `buf` at the current position, returning the bytes written
+    fn write(&mut self, buf: &[u8]) -> Result<usize, OsalError>;
+
+    /// Moves the current position, returning it as bytes from the start
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, OsalError>;
+
+    /// Writes buffered data through to the storage
+    fn flush(&mut self) -> Result<(), OsalError>;
+
+    /// Writes all of `buf`. Fails with `OsalError::NoSpace` when the storage filled up.
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<(), OsalError> {
+        while !buf.is_empty() {
+            match self.write(buf)? {
+                0 => return Err(OsalError::NoSpace),
+                n => buf = &buf[n..],
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A filesystem, or several mounted under their mount points
+pub trait FileSystem {
+    type File: File;
+
+    /// Opens the file at `path`
+    fn open(&self, path: &str, options: OpenOptions) -> Result<Self::File, OsalError>;
+
+    /// Removes a file, or an empty directory
+    fn remove(&self, path: &str) -> Result<(), OsalError>;
+
+    /// Renames a file or directory. Replaces an existing file at `to`. Renaming across
+    /// mount points is not supported.
+    fn rename(&self, from: &str, to: &str) -> Result<(), OsalError>;
+
+    /// Creates a directory. The parent must exist.
+    fn create_dir(&self, path: &str) -> Result<(), OsalError>;
+
+    /// Size and kind of the entry at `path`
+    fn metadata(&self, path: &str) -> Result<Metadata, OsalError>;
+
+    /// Calls `visit` for every entry of the directory at `path`, in no particular order
+    fn list_dir(&self, path: &str, visit: &mut dyn FnMut(DirEntry<'_>)) -> Result<(), OsalError>;
+
+    /// Bytes available on the volume holding `path`
+    fn free_space(&self, path: &str) -> Result<u64, OsalError>;
+}
+
+/// Splits a virtual path into its components, skipping empty and `.` components. Fails
+/// with `InvalidArgument` for relative paths and for `..`, which could escape a mount
+/// point.
+pub fn path_components(path: &str) -> Result<impl Iterator<Item = &str>, OsalError> {
+    if !path.starts_with('/') || path.split('/').any(|c| c == "..") {
+        return Err(OsalError::InvalidArgument);
+    }
+    Ok(path.split('/').filter(|c| !c.is_empty() && *c != "."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Paths are split into components, escaping paths are rejected
+    fn split_paths() {
+        let components: [&str; 3] = {
+            let mut it = path_components("/cf//logs/./fsw.log").unwrap();
+            [it.next().unwrap(), it.next().unwrap(), it.next().unwrap()]
+        };
+        assert_eq!(components, ["cf", "logs", "fsw.log"]);
+        assert_eq!(path_components("/").unwrap().count(), 0);
+        assert!(path_components("cf/logs").is_err());
+        assert!(path_components("/cf/../etc").is_err());
+    }
+}
@@ -0,0 +1,99 @@
+//! OSAL Network Sockets
+//!
+//! UDP and TCP sockets for ground links, log streaming and simulation interfaces. Addresses
+//! are `core::net` addresses, so components never depend on a particular network stack.
+
+use super::OsalError;
+use core::net::SocketAddr;
+use core::time::Duration;
+
+/// How socket calls wait for data, room or connections
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum IoMode {
+    /// Wait as long as it takes
+    #[default]
+    Blocking,
+    /// Fail with `OsalError::WouldBlock` when the call cannot complete right away
+    NonBlocking,
+    /// Wait up to the duration, then fail with `OsalError::Timeout`
+    Timeout(Duration),
+}
+
+/// A bound UDP socket
+pub trait UdpSocket {
+    /// Address the socket is bound to
+    fn local_addr(&self) -> Result<SocketAddr, OsalError>;
+
+    /// Sets how sends and receives wait
+    fn set_mode(&self, mode: IoMode) -> Result<(), OsalError>;
+
+    /// Sends the datagram `buf` to `addr`, returning the bytes sent
+    fn send_to(&self, buf: &[u8], addr: SocketAddr) -> Result<usize, OsalError>;
+
+    /// Receives a datagram into `buf`, returning its length and sender. The rest of a
+    /// datagram longer than `buf` is discarded.
+    fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr), OsalError>;
+}
+
+/// A connected TCP stream. Closed when dropped.
+pub trait TcpStream {
+    /// Address of the local end
+    fn local_addr(&self) -> Result<SocketAddr, OsalError>;
+
+    /// Address of the remote end
+    fn peer_addr(&self) -> Result<SocketAddr, OsalError>;
+
+    /// Sets how reads and writes wait
+    fn set_mode(&self, mode: IoMode) -> Result<(), OsalError>;
+
+    /// Reads into `buf`, returning the bytes read. Zero once the peer closed the stream.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, OsalError>;
+
+    /// Writes from `buf`, returning the bytes written
+    fn write(&mut self, buf: &[u8]) -> Result<usize, OsalError>;
+
+    /// Writes all of `buf`. Fails with `OsalError::Disconnected` when the stream closed.
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<(), OsalError> {
+        while !buf.is_empty() {
+            match self.write(buf)? {
+                0 => return Err(OsalError::Disconnected),
+                n => buf = &buf[n..],
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A TCP socket listening for connections
+pub trait TcpListener {
+    type Stream: TcpStream;
+
+    /// Address the listener is bound to
+    fn local_addr(&self) -> Result<SocketAddr, OsalError>;
+
+    /// Sets how accepting waits for a connection. Accepted streams start out blocking.
+    fn set_mode(&self, mode: IoMode) -> Result<(), OsalError>;
+
+    /// Accepts a connection, returning the stream and the address of the peer
+    fn accept(&self) -> Result<(Self::Stream, SocketAddr), OsalError>;
+}
+
+/// Creates sockets on a network stack
+pub trait NetworkApi {
+    type UdpSocket: UdpSocket;
+    type TcpListener: TcpListener<Stream = Self::TcpStream>;
+    type TcpStream: TcpStream;
+
+    /// Binds a UDP socket to `addr`. Port 0 picks a free port.
+    fn udp_bind(&self, addr: SocketAddr) -> Result<Self::UdpSocket, OsalError>;
+
+    /// Listens for TCP connections on `addr`. Port 0 picks a free port.
+    fn tcp_listen(&self, addr: SocketAddr) -> Result<Self::TcpListener, OsalError>;
+
+    /// Connects to the TCP listener at `addr`, waiting up to `timeout`
+    fn tcp_connect(
+        &self,
+        addr: SocketAddr,
+        timeout: Option<Duration>,
+    ) -> Result<Self::TcpStream, OsalError>;
+}
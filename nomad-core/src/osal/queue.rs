@@ -0,0 +1,70 @@
+//! OSAL Message Queues
+//!
+//! Bounded FIFO queues passing messages between tasks. This is the transport between
+//! publishers and subscribers that live on different tasks.
+
+use super::OsalError;
+use core::time::Duration;
+
+/// A message that could not be sent, handed back to the sender
+///
+/// * `msg`: The message
+/// * `error`: Why it was not sent, `Timeout` or `WouldBlock` when the queue stayed full
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SendError<T> {
+    pub msg: T,
+    pub error: OsalError,
+}
+
+/// Fill level of a queue
+///
+/// * `depth`: Messages currently queued
+/// * `capacity`: Most messages the queue holds
+/// * `high_water`: Highest depth reached since creation or the last reset
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct QueueStats {
+    pub depth: usize,
+    pub capacity: usize,
+    pub high_water: usize,
+}
+
+/// Bounded queue of messages of type `T`, shared between the sending and the receiving
+/// tasks
+pub trait Queue<T> {
+    /// Creates an empty queue holding up to `capacity` messages. A capacity of zero is an
+    /// `InvalidArgument`.
+    fn new(capacity: usize) -> Result<Self, OsalError>
+    where
+        Self: Sized;
+
+    /// Sends a message, waiting up to `timeout` for room. Fails with `OsalError::Timeout`
+    /// when the queue stayed full, or `OsalError::WouldBlock` for a zero timeout.
+    fn send(&self, msg: T, timeout: Option<Duration>) -> Result<(), SendError<T>>;
+
+    /// Sends a message if there is room, failing with `OsalError::WouldBlock` otherwise
+    fn try_send(&self, msg: T) -> Result<(), SendError<T>>;
+
+    /// Receives the oldest message, waiting up to `timeout` for one. Fails with
+    /// `OsalError::Timeout` when none arrived, or `OsalError::WouldBlock` for a zero
+    /// timeout.
+    fn recv(&self, timeout: Option<Duration>) -> Result<T, OsalError>;
+
+    /// Receives the oldest message if there is one
+    fn try_recv(&self) -> Option<T>;
+
+    /// Fill level of the queue
+    fn stats(&self) -> QueueStats;
+
+    /// Resets the high-water mark to the current depth
+    fn reset_high_water(&self);
+
+    /// Messages currently queued
+    fn depth(&self) -> usize {
+        self.stats().depth
+    }
+
+    /// Highest depth reached
+    fn high_water(&self) -> usize {
+        self.stats().high_water
+    }
+}
@@ -0,0 +1,107 @@
+//! OSAL Serial Ports
+//!
+//! UART access for devices such as radios and GNSS receivers. A port is opened by the
+//! platform with a `SerialConfig` and can be reconfigured while open.
+
+use super::OsalError;
+use core::time::Duration;
+
+/// Bits per character
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DataBits {
+    Five,
+    Six,
+    Seven,
+    Eight,
+}
+
+/// Parity bit of each character
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Parity {
+    None,
+    Odd,
+    Even,
+}
+
+/// Stop bits after each character
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StopBits {
+    One,
+    Two,
+}
+
+/// Flow control between the port and the device
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FlowControl {
+    None,
+    /// RTS/CTS lines
+    Hardware,
+    /// XON/XOFF characters
+    Software,
+}
+
+/// Serial port settings
+///
+/// * `baud_rate`: Line rate in bits per second
+/// * `data_bits`: Bits per character
+/// * `parity`: Parity bit
+/// * `stop_bits`: Stop bits
+/// * `flow_control`: Flow control
+/// * `read_timeout`: How long reads wait for data, None waits forever
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SerialConfig {
+    pub baud_rate: u32,
+    pub data_bits: DataBits,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+    pub flow_control: FlowControl,
+    pub read_timeout: Option<Duration>,
+}
+
+impl SerialConfig {
+    /// 8N1 at `baud_rate`, without flow control, reads waiting forever
+    pub const fn new(baud_rate: u32) -> Self {
+        Self {
+            baud_rate,
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            flow_control: FlowControl::None,
+            read_timeout: None,
+        }
+    }
+}
+
+/// An open serial port. Closed when dropped.
+pub trait SerialPort {
+    /// Settings in effect
+    fn config(&self) -> SerialConfig;
+
+    /// Applies new settings. Fails with `OsalError::InvalidArgument` for settings the
+    /// port does not support, e.g. a non-standard baud rate, leaving the old ones in effect.
+    fn configure(&mut self, config: &SerialConfig) -> Result<(), OsalError>;
+
+    /// Reads the bytes available into `buf`, waiting up to the read timeout for at least
+    /// one. Fails with `OsalError::Timeout` when none arrived.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, OsalError>;
+
+    /// Writes from `buf`, returning the bytes written
+    fn write(&mut self, buf: &[u8]) -> Result<usize, OsalError>;
+
+    /// Waits until all written bytes have been transmitted
+    fn flush(&mut self) -> Result<(), OsalError>;
+
+    /// Discards bytes received but not read yet
+    fn discard_input(&mut self) -> Result<(), OsalError>;
+
+    /// Writes all of `buf`
+    fn write_all(&mut self, mut buf: &[u8]) -> Result<(), OsalError> {
+        while !buf.is_empty() {
+            match self.write(buf)? {
+                0 => return Err(OsalError::Disconnected),
+                n => buf = &buf[n..],
+            }
+        }
+        Ok(())
+    }
+}
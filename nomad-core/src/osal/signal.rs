@@ -0,0 +1,50 @@
+//! OSAL Signals
+//!
+//! Requests delivered to the FSW from outside, e.g. by an operator or the init system of a
+//! Linux flight computer. The FSW polls or waits for them in its main loop, so they are
+//! handled like any other event rather than asynchronously.
+
+use super::OsalError;
+use core::time::Duration;
+
+/// A request delivered to the FSW
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Signal {
+    /// Interrupted by an operator, e.g. Ctrl+C (SIGINT)
+    Interrupt,
+    /// Asked to terminate, e.g. by the init system (SIGTERM)
+    Terminate,
+    /// Asked to reload the configuration (SIGHUP)
+    Hangup,
+}
+
+impl Signal {
+    /// Whether the signal requests an orderly shutdown
+    pub fn is_shutdown(self) -> bool {
+        matches!(self, Signal::Interrupt | Signal::Terminate)
+    }
+}
+
+/// Receives the signals delivered to the FSW. While it exists, signals no longer take
+/// their default action, such as terminating the process.
+pub trait Signals {
+    /// Takes the oldest pending signal
+    fn poll(&self) -> Option<Signal>;
+
+    /// Takes the oldest pending signal, waiting up to `timeout` for one. Fails with
+    /// `OsalError::Timeout` when none arrived.
+    fn wait(&self, timeout: Option<Duration>) -> Result<Signal, OsalError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Interrupt and Terminate request a shutdown, Hangup does not
+    fn shutdown_signals() {
+        assert!(Signal::Interrupt.is_shutdown());
+        assert!(Signal::Terminate.is_shutdown());
+        assert!(!Signal::Hangup.is_shutdown());
+    }
+}
@@ -0,0 +1,119 @@
+//! OSAL Synchronisation
+//!
+//! Mutexes, semaphores and event flags for tasks sharing data or signalling each other.
+//! Waits take an optional timeout, None waits forever.
+
+use super::OsalError;
+use core::ops::DerefMut;
+use core::time::Duration;
+
+/// Mutual exclusion around a value of type `T`. The lock is released when the guard is
+/// dropped.
+pub trait Mutex<T> {
+    type Guard<'a>: DerefMut<Target = T>
+    where
+        Self: 'a;
+
+    /// Creates an unlocked mutex holding `value`
+    fn new(value: T) -> Self
+    where
+        Self: Sized;
+
+    /// Locks the mutex, waiting up to `timeout`. Fails with `OsalError::Timeout` when the
+    /// mutex stayed locked.
+    fn lock(&self, timeout: Option<Duration>) -> Result<Self::Guard<'_>, OsalError>;
+
+    /// Locks the mutex if it is free
+    fn try_lock(&self) -> Option<Self::Guard<'_>>;
+}
+
+/// Counting semaphore. A binary semaphore is a counting semaphore with a maximum of one.
+pub trait Semaphore {
+    /// Creates a semaphore with `initial` tokens, holding at most `max`
+    fn new(initial: u32, max: u32) -> Self
+    where
+        Self: Sized;
+
+    /// Creates a binary semaphore, given when `given` is set
+    fn binary(given: bool) -> Self
+    where
+        Self: Sized,
+    {
+        Self::new(u32::from(given), 1)
+    }
+
+    /// Takes a token, waiting up to `timeout` for one to be given. Fails with
+    /// `OsalError::Timeout` when none was.
+    fn take(&self, timeout: Option<Duration>) -> Result<(), OsalError>;
+
+    /// Takes a token if one is available
+    fn try_take(&self) -> bool;
+
+    /// Gives a token, waking a waiting task. Fails with `OsalError::Overflow` when the
+    /// semaphore already holds its maximum.
+    fn give(&self) -> Result<(), OsalError>;
+
+    /// Tokens currently held
+    fn count(&self) -> u32;
+}
+
+/// Condition an event flag wait is satisfied by
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FlagWait {
+    /// Any of the flags in the mask is set
+    Any,
+    /// All of the flags in the mask are set
+    All,
+}
+
+impl FlagWait {
+    /// Whether `flags` satisfy the wait for `mask`
+    pub fn satisfied(self, flags: u32, mask: u32) -> bool {
+        match self {
+            FlagWait::Any => flags & mask != 0,
+            FlagWait::All => flags & mask == mask,
+        }
+    }
+}
+
+/// A set of 32 event flags tasks can wait on, e.g. one flag per event a task handles
+pub trait EventFlags {
+    /// Creates the flags, all cleared
+    fn new() -> Self
+    where
+        Self: Sized;
+
+    /// Sets the flags in `mask`, waking tasks waiting for them
+    fn set(&self, mask: u32);
+
+    /// Clears the flags in `mask`
+    fn clear(&self, mask: u32);
+
+    /// Flags currently set
+    fn get(&self) -> u32;
+
+    /// Waits up to `timeout` until the flags in `mask` satisfy `wait`. Returns the flags set
+    /// at that moment, clearing those in `mask` when `clear` is set. Fails with
+    /// `OsalError::Timeout` otherwise.
+    fn wait(
+        &self,
+        mask: u32,
+        wait: FlagWait,
+        clear: bool,
+        timeout: Option<Duration>,
+    ) -> Result<u32, OsalError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Any waits for one flag of the mask, All for every flag
+    fn flag_wait_conditions() {
+        assert!(FlagWait::Any.satisfied(0b0100, 0b0110));
+        assert!(!FlagWait::All.satisfied(0b0100, 0b0110));
+        assert!(FlagWait::All.satisfied(0b1110, 0b0110));
+        assert!(!FlagWait::Any.satisfied(0b1001, 0b0110));
+    }
+}
@@ -0,0 +1,151 @@
+//! OSAL Tasks
+//!
+//! Tasks are the unit of concurrency of the FSW, a thread on POSIX or a task on an RTOS.
+
+use super::OsalError;
+use crate::time::{MonotonicInstant, TimeSource};
+use core::time::Duration;
+
+/// Priority of a task, higher values are more important. `NORMAL` runs the task with the
+/// default scheduling of the OS. Other values request realtime scheduling, mapped onto
+/// the priority range of the OS.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TaskPriority(pub u8);
+
+impl TaskPriority {
+    /// Default scheduling of the OS
+    pub const NORMAL: Self = Self(0);
+    /// Lowest realtime priority
+    pub const MIN_REALTIME: Self = Self(1);
+    /// Highest realtime priority
+    pub const MAX_REALTIME: Self = Self(u8::MAX);
+
+    /// Whether realtime scheduling is requested
+    pub fn is_realtime(self) -> bool {
+        self != Self::NORMAL
+    }
+}
+
+/// Parameters of a new task
+///
+/// * `name`: Name of the task, shown by OS tools. May be truncated by the OS.
+/// * `priority`: Scheduling priority
+/// * `stack_size`: Stack size in bytes, None for the OS default
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TaskConfig<'a> {
+    pub name: &'a str,
+    pub priority: TaskPriority,
+    pub stack_size: Option<usize>,
+}
+
+impl<'a> TaskConfig<'a> {
+    /// A task at normal priority with the default stack size
+    pub const fn new(name: &'a str) -> Self {
+        Self {
+            name,
+            priority: TaskPriority::NORMAL,
+            stack_size: None,
+        }
+    }
+}
+
+/// A running task
+pub trait Task {
+    /// Name the task was spawned with
+    fn name(&self) -> &str;
+
+    /// Whether the entry function returned
+    fn is_finished(&self) -> bool;
+
+    /// Waits for the entry function to return
+    fn join(self) -> Result<(), OsalError>;
+}
+
+/// Creation of tasks, and sleeping on the current one
+pub trait TaskApi {
+    type Task: Task;
+
+    /// Spawns a task running `entry`
+    fn spawn<F>(&self, config: &TaskConfig, entry: F) -> Result<Self::Task, OsalError>
+    where
+        F: FnOnce() + Send + 'static;
+
+    /// Puts the current task to sleep for at least `duration`
+    fn sleep(&self, duration: Duration);
+
+    /// Puts the current task to sleep until `clock` reaches `deadline`. Returns right away
+    /// when the deadline passed. Sleeping towards deadlines, rather than for durations,
+    /// keeps periodic tasks from drifting.
+    fn sleep_until<T: TimeSource + ?Sized>(&self, clock: &T, deadline: MonotonicInstant) {
+        if let Some(remaining) = deadline.checked_duration_since(clock.monotonic())
+            && !remaining.is_zero()
+        {
+            self.sleep(remaining);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::SimulatedTimeSource;
+    use core::cell::Cell;
+
+    /// Task API advancing a simulated clock instead of sleeping
+    struct SimTasks<'a>(&'a SimulatedTimeSource, Cell<u32>);
+
+    struct NoTask;
+
+    impl Task for NoTask {
+        fn name(&self) -> &str {
+            ""
+        }
+
+        fn is_finished(&self) -> bool {
+            true
+        }
+
+        fn join(self) -> Result<(), OsalError> {
+            Ok(())
+        }
+    }
+
+    impl TaskApi for SimTasks<'_> {
+        type Task = NoTask;
+
+        fn spawn<F>(&self, _config: &TaskConfig, _entry: F) -> Result<NoTask, OsalError>
+        where
+            F: FnOnce() + Send + 'static,
+        {
+            Err(OsalError::Unsupported)
+        }
+
+        fn sleep(&self, duration: Duration) {
+            self.1.set(self.1.get() + 1);
+            self.0.advance(duration);
+        }
+    }
+
+    #[test]
+    /// Sleeping until a deadline sleeps for the remaining time only
+    fn sleep_until_deadline() {
+        let clock = SimulatedTimeSource::new();
+        let tasks = SimTasks(&clock, Cell::new(0));
+        let period = Duration::from_millis(10);
+
+        let mut deadline = clock.monotonic();
+        for _ in 0..3 {
+            deadline += period;
+            clock.advance(Duration::from_millis(4));
+            tasks.sleep_until(&clock, deadline);
+            assert_eq!(clock.monotonic(), deadline);
+        }
+
+        // Deadlines in the past return right away
+        tasks.sleep_until(&clock, MonotonicInstant::ZERO);
+        tasks.sleep_until(&clock, deadline);
+        assert_eq!(tasks.1.get(), 3);
+        assert_eq!(TaskConfig::new("x").priority, TaskPriority::NORMAL);
+        assert!(TaskPriority::MIN_REALTIME.is_realtime());
+    }
+}
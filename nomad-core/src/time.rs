@@ -2,30 +2,269 @@
 //!
 //! Core time model provided by Nomad
 
+use core::cell::Cell;
 use core::time::Duration;
 
+pub mod ccsds;
+pub mod health;
+pub mod instant;
+pub mod scale;
+pub use health::{FailoverTimeSource, TimeSourceHealth, TimeSourceKind};
+pub use instant::{MissionTime, MonotonicInstant};
+pub use scale::{Epoch, GpsTime, LeapSecondTable, TaiTime, TimeScale, UtcTime};
+
 /// TimeMode selects between HW time vs simulated time.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub enum TimeMode {
+pub enum TimeMode<'a> {
     Real,
     Simulated,
+    /// Time played back from the recording at the given path, to re-run a flight
+    Replay(&'a str),
 }
 
 #[derive(Copy, Clone, Debug)]
 /// Configuration for the Time Subsystem
 ///
 /// * `mode`: Time mode (Real time or simulated)
-/// * `mission_epoch_unix`: Optional UNIX timestamp, represent T0 of the mission. Mission time
-/// measured relative to it
-pub struct TimeConfig {
-    pub mode: TimeMode,
-    pub mission_epoch_unix: Option<u64>,
+/// * `mission_epoch`: Optional T0 of the mission, in any time scale. Mission time measured
+///   relative to it
+pub struct TimeConfig<'a> {
+    pub mode: TimeMode<'a>,
+    pub mission_epoch: Option<Epoch>,
 }
 
 /// A source of time that can provide monotonic and mission time. Monotonic time is
 /// always increasing, used for timeouts, scheduling, etc
-/// Mission time is time relative to mission epoch, used for logs and telemetry.
+/// Mission time is time relative to mission epoch, used for logs and telemetry. It is
+/// negative before the mission epoch, during the countdown.
+/// Both are distinct types, so one cannot be used in place of the other.
 pub trait TimeSource {
-    fn monotonic(&self) -> Duration;
-    fn mission_time(&self) -> Duration;
+    fn monotonic(&self) -> MonotonicInstant;
+    fn mission_time(&self) -> MissionTime;
+
+    /// Current UTC, for sources backed by an absolute clock such as the OS clock or an
+    /// RTC. None for sources that only know relative time.
+    fn utc(&self) -> Option<UtcTime> {
+        None
+    }
+
+    /// How far the source can be trusted. Defaults to a valid, free running source of
+    /// unknown kind and error.
+    fn health(&self) -> TimeSourceHealth {
+        TimeSourceHealth::free_running(TimeSourceKind::Other)
+    }
+
+    /// Called by the TimeService with its leap-second table, when attached and whenever the
+    /// table is updated. Sources that convert a mission epoch to the scale of their clock
+    /// use it instead of a table of their own.
+    fn leap_seconds_changed(&self, _leap_seconds: &LeapSecondTable) {}
+}
+
+/// Borrowed time sources are time sources too, allowing several users (e.g. a Logger
+/// stamping records) to share one source.
+impl<T: TimeSource + ?Sized> TimeSource for &T {
+    fn monotonic(&self) -> MonotonicInstant {
+        (**self).monotonic()
+    }
+
+    fn mission_time(&self) -> MissionTime {
+        (**self).mission_time()
+    }
+
+    fn utc(&self) -> Option<UtcTime> {
+        (**self).utc()
+    }
+
+    fn health(&self) -> TimeSourceHealth {
+        (**self).health()
+    }
+
+    fn leap_seconds_changed(&self, leap_seconds: &LeapSecondTable) {
+        (**self).leap_seconds_changed(leap_seconds)
+    }
+}
+
+/// A TimeSource that is always at zero. Used where no time source is wired up.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NoTimeSource;
+
+impl TimeSource for NoTimeSource {
+    fn monotonic(&self) -> MonotonicInstant {
+        MonotonicInstant::ZERO
+    }
+
+    fn mission_time(&self) -> MissionTime {
+        MissionTime::ZERO
+    }
+
+    fn health(&self) -> TimeSourceHealth {
+        TimeSourceHealth::invalid(TimeSourceKind::Other)
+    }
+}
+
+/// A TimeSource whose clock only advances when told to, for unit tests and SITL runs.
+///
+/// Time starts at zero. Monotonic and mission time both read the simulated clock, the
+/// simulation is considered to start at the mission epoch. An optional scale factor
+/// multiplies every `advance`, e.g. a scale of 10 turns 10ms steps of a loop into 100ms
+/// of simulated time for faster than real time runs.
+///
+/// * `now`: Current simulated time
+/// * `scale`: Factor applied to every advance
+pub struct SimulatedTimeSource {
+    // NOTE: NOT THREADSAFE
+    now: Cell<Duration>,
+    scale: Cell<f64>,
+}
+
+impl SimulatedTimeSource {
+    /// Creates a simulated clock at zero, advancing in real time steps
+    pub fn new() -> Self {
+        Self {
+            now: Cell::new(Duration::ZERO),
+            scale: Cell::new(1.0),
+        }
+    }
+
+    /// Creates a simulated clock at zero with a scale factor applied to every advance
+    ///
+    /// # Panics
+    /// If `scale` is not a finite number greater than zero
+    pub fn with_scale(scale: f64) -> Self {
+        let source = Self::new();
+        source.set_scale(scale);
+        source
+    }
+
+    /// Advances the clock by `dt` multiplied by the scale factor, saturating at
+    /// `Duration::MAX`
+    pub fn advance(&self, dt: Duration) {
+        let scaled = if self.scale.get() == 1.0 {
+            dt
+        } else {
+            // Duration::mul_f64 panics when the product does not fit
+            Duration::try_from_secs_f64(dt.as_secs_f64() * self.scale.get())
+                .unwrap_or(Duration::MAX)
+        };
+        self.now.set(self.now.get().saturating_add(scaled));
+    }
+
+    /// Sets the clock to `t`. Moving the clock backwards breaks monotonicity and is up
+    /// to the caller.
+    pub fn set(&self, t: Duration) {
+        self.now.set(t);
+    }
+
+    /// Current scale factor
+    pub fn scale(&self) -> f64 {
+        self.scale.get()
+    }
+
+    /// Sets the scale factor
+    ///
+    /// # Panics
+    /// If `scale` is not a finite number greater than zero, such a factor would freeze
+    /// the clock or run it backwards
+    pub fn set_scale(&self, scale: f64) {
+        assert!(
+            scale.is_finite() && scale > 0.0,
+            "invalid simulated time scale {scale}"
+        );
+        self.scale.set(scale);
+    }
+}
+
+impl Default for SimulatedTimeSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimeSource for SimulatedTimeSource {
+    fn monotonic(&self) -> MonotonicInstant {
+        MonotonicInstant::from_start(self.now.get())
+    }
+
+    fn mission_time(&self) -> MissionTime {
+        MissionTime::after_epoch(self.now.get())
+    }
+
+    /// Simulated time is exact by definition
+    fn health(&self) -> TimeSourceHealth {
+        TimeSourceHealth {
+            kind: TimeSourceKind::Simulated,
+            valid: true,
+            synchronized: true,
+            error_bound: Some(Duration::ZERO),
+        }
+    }
+}
+
+// ========== TESTS ==========
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// The simulated clock only moves when told to
+    fn simulated_advance_and_set() {
+        let sim = SimulatedTimeSource::new();
+        assert_eq!(sim.monotonic().since_start(), Duration::ZERO);
+        assert_eq!(sim.monotonic().since_start(), Duration::ZERO);
+
+        sim.advance(Duration::from_millis(10));
+        sim.advance(Duration::from_millis(15));
+        assert_eq!(sim.monotonic().since_start(), Duration::from_millis(25));
+        assert_eq!(
+            sim.mission_time(),
+            MissionTime::after_epoch(Duration::from_millis(25))
+        );
+
+        sim.set(Duration::from_secs(100));
+        assert_eq!(sim.monotonic().since_start(), Duration::from_secs(100));
+    }
+
+    #[test]
+    /// The scale factor multiplies every advance
+    fn simulated_scale() {
+        let sim = SimulatedTimeSource::with_scale(10.0);
+        sim.advance(Duration::from_millis(10));
+        assert_eq!(sim.monotonic().since_start(), Duration::from_millis(100));
+
+        sim.set_scale(0.5);
+        sim.advance(Duration::from_millis(10));
+        assert_eq!(sim.monotonic().since_start(), Duration::from_millis(105));
+    }
+
+    #[test]
+    /// Scaled advances that overflow saturate instead of panicking
+    fn simulated_scale_saturates() {
+        let sim = SimulatedTimeSource::with_scale(1e6);
+        sim.advance(Duration::MAX / 2);
+        assert_eq!(sim.monotonic().since_start(), Duration::MAX);
+        sim.advance(Duration::from_secs(1));
+        assert_eq!(sim.monotonic().since_start(), Duration::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid simulated time scale")]
+    /// Negative scales are rejected
+    fn simulated_scale_negative() {
+        SimulatedTimeSource::new().set_scale(-1.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid simulated time scale")]
+    /// Zero scales are rejected, they would freeze the clock
+    fn simulated_scale_zero() {
+        SimulatedTimeSource::with_scale(0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid simulated time scale")]
+    /// NaN scales are rejected
+    fn simulated_scale_nan() {
+        SimulatedTimeSource::new().set_scale(f64::NAN);
+    }
 }
@@ -0,0 +1,534 @@
+//! CCSDS Time Codes
+//!
+//! Encoders and decoders for the CCSDS Unsegmented (CUC) and Day-Segmented (CDS) time
+//! codes of CCSDS 301.0-B-4, as used in packet secondary headers.
+//!
+//! Both codes are made of an optional P-field (preamble, describing the code) and a
+//! T-field (the time itself). Times are given as a `Duration` since the epoch of the code.
+//! Nomad uses the agency-defined epoch for the mission epoch, so mission time from
+//! `TimeSource::mission_time` is encoded as is, see `MissionTime::to_duration`. Codes
+//! using the CCSDS epoch take TAI since 1958-01-01 (`TaiTime`).
+
+use core::time::Duration;
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+const NANOS_PER_MILLI: u32 = 1_000_000;
+const MILLIS_PER_DAY: u64 = 86_400_000;
+
+/// CUC time code identification, P-field bits 1-3
+const CUC_ID_TAI: u8 = 0b001;
+const CUC_ID_AGENCY: u8 = 0b010;
+/// CDS time code identification
+const CDS_ID: u8 = 0b100;
+
+/// Largest CUC coarse time width, 4 octets plus 3 from the P-field extension
+pub const CUC_MAX_COARSE_OCTETS: u8 = 7;
+/// Largest CUC fine time width, 3 octets plus 7 from the P-field extension
+pub const CUC_MAX_FINE_OCTETS: u8 = 10;
+
+/// Epoch a time code counts from
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CcsdsEpoch {
+    /// CCSDS recommended epoch, 1958-01-01T00:00:00 TAI
+    Tai1958,
+    /// Agency-defined epoch, for Nomad the mission epoch
+    Agency,
+}
+
+/// Errors encoding or decoding CCSDS time codes
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CcsdsError {
+    /// Octet widths outside of what the time code supports
+    InvalidConfig,
+    /// Output buffer too small for the code
+    BufferTooSmall,
+    /// Input ended before the end of the code
+    Truncated,
+    /// P-field does not describe a supported time code
+    InvalidPField,
+    /// Time does not fit the coarse or day field
+    Overflow,
+    /// T-field holds an invalid value, e.g. more milliseconds than in a day
+    InvalidValue,
+}
+
+/// Configuration of a CCSDS Unsegmented time code (CUC).
+///
+/// The T-field is a binary count of seconds (coarse time) followed by a binary fraction
+/// of a second (fine time).
+///
+/// * `coarse_octets`: Width of the coarse time, 1 to 7 octets
+/// * `fine_octets`: Width of the fine time, 0 to 10 octets
+/// * `epoch`: Epoch the coarse time counts from
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CucConfig {
+    pub coarse_octets: u8,
+    pub fine_octets: u8,
+    pub epoch: CcsdsEpoch,
+}
+
+impl CucConfig {
+    /// CUC counting from the mission epoch
+    pub const fn new(coarse_octets: u8, fine_octets: u8) -> Self {
+        Self {
+            coarse_octets,
+            fine_octets,
+            epoch: CcsdsEpoch::Agency,
+        }
+    }
+
+    /// Whether the octet widths are supported
+    pub fn is_valid(&self) -> bool {
+        (1..=CUC_MAX_COARSE_OCTETS).contains(&self.coarse_octets)
+            && self.fine_octets <= CUC_MAX_FINE_OCTETS
+    }
+
+    /// Length of the P-field, 2 octets when the widths need the extension octet
+    pub fn p_field_len(&self) -> usize {
+        if self.coarse_octets > 4 || self.fine_octets > 3 {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Length of the T-field
+    pub fn t_field_len(&self) -> usize {
+        usize::from(self.coarse_octets) + usize::from(self.fine_octets)
+    }
+
+    /// Writes the P-field, returning its length
+    pub fn encode_p_field(&self, buf: &mut [u8]) -> Result<usize, CcsdsError> {
+        if !self.is_valid() {
+            return Err(CcsdsError::InvalidConfig);
+        }
+        let len = self.p_field_len();
+        let buf = buf.get_mut(..len).ok_or(CcsdsError::BufferTooSmall)?;
+
+        let id = match self.epoch {
+            CcsdsEpoch::Tai1958 => CUC_ID_TAI,
+            CcsdsEpoch::Agency => CUC_ID_AGENCY,
+        };
+        let coarse = self.coarse_octets.min(4);
+        let fine = self.fine_octets.min(3);
+        buf[0] = (id << 4) | ((coarse - 1) << 2) | fine;
+        if len == 2 {
+            buf[0] |= 0x80;
+            buf[1] = ((self.coarse_octets - coarse) << 5) | ((self.fine_octets - fine) << 2);
+        }
+        Ok(len)
+    }
+
+    /// Parses a P-field, returning the configuration it describes and its length
+    pub fn decode_p_field(buf: &[u8]) -> Result<(Self, usize), CcsdsError> {
+        let first = *buf.first().ok_or(CcsdsError::Truncated)?;
+        let epoch = match (first >> 4) & 0b111 {
+            CUC_ID_TAI => CcsdsEpoch::Tai1958,
+            CUC_ID_AGENCY => CcsdsEpoch::Agency,
+            _ => return Err(CcsdsError::InvalidPField),
+        };
+        let mut config = Self {
+            coarse_octets: ((first >> 2) & 0b11) + 1,
+            fine_octets: first & 0b11,
+            epoch,
+        };
+        if first & 0x80 == 0 {
+            return Ok((config, 1));
+        }
+
+        let second = *buf.get(1).ok_or(CcsdsError::Truncated)?;
+        // A further extension octet is reserved for future use
+        if second & 0x80 != 0 {
+            return Err(CcsdsError::InvalidPField);
+        }
+        config.coarse_octets += (second >> 5) & 0b11;
+        config.fine_octets += (second >> 2) & 0b111;
+        Ok((config, 2))
+    }
+
+    /// Writes the T-field for `time` since the epoch, returning its length. Fractions
+    /// below the fine resolution are truncated.
+    pub fn encode(&self, time: Duration, buf: &mut [u8]) -> Result<usize, CcsdsError> {
+        if !self.is_valid() {
+            return Err(CcsdsError::InvalidConfig);
+        }
+        let coarse = usize::from(self.coarse_octets);
+        let fine = usize::from(self.fine_octets);
+        let buf = buf
+            .get_mut(..coarse + fine)
+            .ok_or(CcsdsError::BufferTooSmall)?;
+
+        let secs = time.as_secs();
+        if coarse < 8 && secs >> (8 * coarse) != 0 {
+            return Err(CcsdsError::Overflow);
+        }
+        write_be(&mut buf[..coarse], u128::from(secs));
+
+        // Binary fraction of a second, subsec_nanos / 1e9 * 2^(8 * fine)
+        let fraction = (u128::from(time.subsec_nanos()) << (8 * fine)) / NANOS_PER_SEC;
+        write_be(&mut buf[coarse..], fraction);
+        Ok(coarse + fine)
+    }
+
+    /// Parses a T-field, returning the time since the epoch and the T-field length.
+    /// Fine time beyond nanosecond resolution is truncated.
+    pub fn decode(&self, buf: &[u8]) -> Result<(Duration, usize), CcsdsError> {
+        if !self.is_valid() {
+            return Err(CcsdsError::InvalidConfig);
+        }
+        let coarse = usize::from(self.coarse_octets);
+        let fine = usize::from(self.fine_octets);
+        let buf = buf.get(..coarse + fine).ok_or(CcsdsError::Truncated)?;
+
+        let secs = read_be(&buf[..coarse]) as u64;
+        let nanos = (read_be(&buf[coarse..]) * NANOS_PER_SEC) >> (8 * fine);
+        Ok((Duration::new(secs, nanos as u32), coarse + fine))
+    }
+
+    /// Writes P-field and T-field, returning the total length
+    pub fn encode_with_p_field(&self, time: Duration, buf: &mut [u8]) -> Result<usize, CcsdsError> {
+        let p = self.encode_p_field(buf)?;
+        Ok(p + self.encode(time, &mut buf[p..])?)
+    }
+
+    /// Parses a time code starting with its P-field, returning the configuration, the time
+    /// since the epoch and the total length
+    pub fn decode_with_p_field(buf: &[u8]) -> Result<(Self, Duration, usize), CcsdsError> {
+        let (config, p) = Self::decode_p_field(buf)?;
+        let (time, t) = config.decode(&buf[p..])?;
+        Ok((config, time, p + t))
+    }
+}
+
+/// Resolution of the CDS sub-millisecond field
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CdsResolution {
+    /// No sub-millisecond field
+    Millis,
+    /// 16 bit count of microseconds within the millisecond
+    Micros,
+    /// 32 bit count of picoseconds within the millisecond
+    Picos,
+}
+
+impl CdsResolution {
+    /// Width of the sub-millisecond field
+    pub fn octets(&self) -> usize {
+        match self {
+            CdsResolution::Millis => 0,
+            CdsResolution::Micros => 2,
+            CdsResolution::Picos => 4,
+        }
+    }
+}
+
+/// Configuration of a CCSDS Day-Segmented time code (CDS).
+///
+/// The T-field is a count of days, followed by the milliseconds of the day and an optional
+/// sub-millisecond field.
+///
+/// * `day_octets`: Width of the day count, 2 or 3 octets
+/// * `resolution`: Sub-millisecond field
+/// * `epoch`: Epoch the day count starts from
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CdsConfig {
+    pub day_octets: u8,
+    pub resolution: CdsResolution,
+    pub epoch: CcsdsEpoch,
+}
+
+impl CdsConfig {
+    /// CDS counting from the mission epoch
+    pub const fn new(day_octets: u8, resolution: CdsResolution) -> Self {
+        Self {
+            day_octets,
+            resolution,
+            epoch: CcsdsEpoch::Agency,
+        }
+    }
+
+    /// Whether the day width is supported
+    pub fn is_valid(&self) -> bool {
+        matches!(self.day_octets, 2 | 3)
+    }
+
+    /// Length of the P-field, always a single octet
+    pub fn p_field_len(&self) -> usize {
+        1
+    }
+
+    /// Length of the T-field
+    pub fn t_field_len(&self) -> usize {
+        usize::from(self.day_octets) + 4 + self.resolution.octets()
+    }
+
+    /// Writes the P-field, returning its length
+    pub fn encode_p_field(&self, buf: &mut [u8]) -> Result<usize, CcsdsError> {
+        if !self.is_valid() {
+            return Err(CcsdsError::InvalidConfig);
+        }
+        let byte = buf.first_mut().ok_or(CcsdsError::BufferTooSmall)?;
+
+        let epoch = match self.epoch {
+            CcsdsEpoch::Tai1958 => 0,
+            CcsdsEpoch::Agency => 1,
+        };
+        let resolution = match self.resolution {
+            CdsResolution::Millis => 0b00,
+            CdsResolution::Micros => 0b01,
+            CdsResolution::Picos => 0b10,
+        };
+        *byte = (CDS_ID << 4) | (epoch << 3) | ((self.day_octets - 2) << 2) | resolution;
+        Ok(1)
+    }
+
+    /// Parses a P-field, returning the configuration it describes and its length
+    pub fn decode_p_field(buf: &[u8]) -> Result<(Self, usize), CcsdsError> {
+        let byte = *buf.first().ok_or(CcsdsError::Truncated)?;
+        if byte & 0x80 != 0 || (byte >> 4) & 0b111 != CDS_ID {
+            return Err(CcsdsError::InvalidPField);
+        }
+        let resolution = match byte & 0b11 {
+            0b00 => CdsResolution::Millis,
+            0b01 => CdsResolution::Micros,
+            0b10 => CdsResolution::Picos,
+            _ => return Err(CcsdsError::InvalidPField),
+        };
+        let epoch = if byte & 0x08 != 0 {
+            CcsdsEpoch::Agency
+        } else {
+            CcsdsEpoch::Tai1958
+        };
+        Ok((
+            Self {
+                day_octets: 2 + ((byte >> 2) & 1),
+                resolution,
+                epoch,
+            },
+            1,
+        ))
+    }
+
+    /// Writes the T-field for `time` since the epoch, returning its length. Fractions
+    /// below the resolution are truncated.
+    pub fn encode(&self, time: Duration, buf: &mut [u8]) -> Result<usize, CcsdsError> {
+        if !self.is_valid() {
+            return Err(CcsdsError::InvalidConfig);
+        }
+        let days_len = usize::from(self.day_octets);
+        let buf = buf
+            .get_mut(..self.t_field_len())
+            .ok_or(CcsdsError::BufferTooSmall)?;
+
+        let millis = time.as_millis();
+        let days = millis / u128::from(MILLIS_PER_DAY);
+        if days >> (8 * days_len) != 0 {
+            return Err(CcsdsError::Overflow);
+        }
+        write_be(&mut buf[..days_len], days);
+        write_be(
+            &mut buf[days_len..days_len + 4],
+            millis % u128::from(MILLIS_PER_DAY),
+        );
+
+        let sub_nanos = time.subsec_nanos() % NANOS_PER_MILLI;
+        let sub = match self.resolution {
+            CdsResolution::Millis => 0,
+            CdsResolution::Micros => sub_nanos / 1_000,
+            CdsResolution::Picos => sub_nanos * 1_000,
+        };
+        write_be(&mut buf[days_len + 4..], u128::from(sub));
+        Ok(buf.len())
+    }
+
+    /// Parses a T-field, returning the time since the epoch and the T-field length.
+    /// Picoseconds are truncated to nanoseconds.
+    pub fn decode(&self, buf: &[u8]) -> Result<(Duration, usize), CcsdsError> {
+        if !self.is_valid() {
+            return Err(CcsdsError::InvalidConfig);
+        }
+        let days_len = usize::from(self.day_octets);
+        let buf = buf.get(..self.t_field_len()).ok_or(CcsdsError::Truncated)?;
+
+        let days = read_be(&buf[..days_len]) as u64;
+        let millis = read_be(&buf[days_len..days_len + 4]) as u64;
+        let sub = read_be(&buf[days_len + 4..]) as u32;
+        let sub_nanos = match self.resolution {
+            CdsResolution::Millis => 0,
+            CdsResolution::Micros if sub < 1_000 => sub * 1_000,
+            CdsResolution::Picos if sub < 1_000_000_000 => sub / 1_000,
+            _ => return Err(CcsdsError::InvalidValue),
+        };
+        // Mission time and TAI have no leap seconds, a day is always 86400 seconds
+        if millis >= MILLIS_PER_DAY {
+            return Err(CcsdsError::InvalidValue);
+        }
+
+        let millis = days * MILLIS_PER_DAY + millis;
+        let time = Duration::from_millis(millis) + Duration::from_nanos(u64::from(sub_nanos));
+        Ok((time, buf.len()))
+    }
+
+    /// Writes P-field and T-field, returning the total length
+    pub fn encode_with_p_field(&self, time: Duration, buf: &mut [u8]) -> Result<usize, CcsdsError> {
+        let p = self.encode_p_field(buf)?;
+        Ok(p + self.encode(time, &mut buf[p..])?)
+    }
+
+    /// Parses a time code starting with its P-field, returning the configuration, the time
+    /// since the epoch and the total length
+    pub fn decode_with_p_field(buf: &[u8]) -> Result<(Self, Duration, usize), CcsdsError> {
+        let (config, p) = Self::decode_p_field(buf)?;
+        let (time, t) = config.decode(&buf[p..])?;
+        Ok((config, time, p + t))
+    }
+}
+
+/// Writes `value` big-endian into all of `buf`, dropping bits that do not fit
+fn write_be(buf: &mut [u8], mut value: u128) {
+    for byte in buf.iter_mut().rev() {
+        *byte = value as u8;
+        value >>= 8;
+    }
+}
+
+/// Reads all of `buf` as a big-endian integer, at most 16 octets
+fn read_be(buf: &[u8]) -> u128 {
+    buf.iter().fold(0, |acc, &b| (acc << 8) | u128::from(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// CUC round trips through P-field and T-field
+    fn cuc_roundtrip() {
+        let cfg = CucConfig::new(4, 2);
+        let time = Duration::new(123_456, 500_000_000);
+        let mut buf = [0u8; 16];
+
+        let len = cfg.encode_with_p_field(time, &mut buf).unwrap();
+        assert_eq!(len, 7);
+        // Agency epoch, 4 coarse octets, 2 fine octets
+        assert_eq!(buf[0], 0b0010_1110);
+        assert_eq!(&buf[1..7], &[0x00, 0x01, 0xE2, 0x40, 0x80, 0x00]);
+
+        assert_eq!(
+            CucConfig::decode_with_p_field(&buf[..len]),
+            Ok((cfg, time, 7))
+        );
+    }
+
+    #[test]
+    /// Wide CUC codes use the P-field extension, fine time keeps nanoseconds
+    fn cuc_extended() {
+        let cfg = CucConfig {
+            coarse_octets: 6,
+            fine_octets: 5,
+            epoch: CcsdsEpoch::Tai1958,
+        };
+        let time = Duration::new(2_100_000_000, 123_456_789);
+        let mut buf = [0u8; 16];
+
+        let len = cfg.encode_with_p_field(time, &mut buf).unwrap();
+        assert_eq!(len, 2 + 11);
+        assert_eq!(buf[0] & 0x80, 0x80);
+
+        let (decoded_cfg, decoded, _) = CucConfig::decode_with_p_field(&buf).unwrap();
+        assert_eq!(decoded_cfg, cfg);
+        // 40 bit fraction is finer than a nanosecond, truncation loses at most one
+        assert!(time - decoded < Duration::from_nanos(2));
+    }
+
+    #[test]
+    /// Times and widths the code cannot hold are rejected
+    fn cuc_errors() {
+        let mut buf = [0u8; 4];
+        assert_eq!(
+            CucConfig::new(1, 0).encode(Duration::from_secs(256), &mut buf),
+            Err(CcsdsError::Overflow)
+        );
+        assert_eq!(
+            CucConfig::new(8, 0).encode(Duration::ZERO, &mut buf),
+            Err(CcsdsError::InvalidConfig)
+        );
+        assert_eq!(
+            CucConfig::new(4, 1).encode(Duration::ZERO, &mut buf),
+            Err(CcsdsError::BufferTooSmall)
+        );
+        assert_eq!(
+            CucConfig::new(4, 0).decode(&buf[..3]),
+            Err(CcsdsError::Truncated)
+        );
+        assert_eq!(
+            CucConfig::decode_p_field(&[0b0100_0000]),
+            Err(CcsdsError::InvalidPField)
+        );
+    }
+
+    #[test]
+    /// CDS round trips at every resolution
+    fn cds_roundtrip() {
+        // 3 days, 1 hour, 2.5 ms and 7 ns
+        let time = Duration::new(3 * 86_400 + 3_600, 2_500_007);
+        let mut buf = [0u8; 16];
+
+        let cfg = CdsConfig::new(2, CdsResolution::Micros);
+        let len = cfg.encode_with_p_field(time, &mut buf).unwrap();
+        assert_eq!(len, 1 + 8);
+        assert_eq!(buf[0], 0b0100_1001);
+        assert_eq!(&buf[1..3], &[0x00, 0x03]);
+        assert_eq!(&buf[3..7], &3_600_002u32.to_be_bytes());
+        assert_eq!(&buf[7..9], &500u16.to_be_bytes());
+        assert_eq!(
+            CdsConfig::decode_with_p_field(&buf[..len]),
+            Ok((cfg, Duration::new(3 * 86_400 + 3_600, 2_500_000), len))
+        );
+
+        let cfg = CdsConfig {
+            day_octets: 3,
+            resolution: CdsResolution::Picos,
+            epoch: CcsdsEpoch::Tai1958,
+        };
+        let len = cfg.encode_with_p_field(time, &mut buf).unwrap();
+        assert_eq!(len, 1 + 11);
+        assert_eq!(
+            CdsConfig::decode_with_p_field(&buf[..len]),
+            Ok((cfg, time, len))
+        );
+
+        let cfg = CdsConfig::new(2, CdsResolution::Millis);
+        let len = cfg.encode(time, &mut buf).unwrap();
+        assert_eq!(
+            cfg.decode(&buf[..len]),
+            Ok((Duration::new(3 * 86_400 + 3_600, 2_000_000), 6))
+        );
+    }
+
+    #[test]
+    /// Invalid CDS fields are rejected
+    fn cds_errors() {
+        let mut buf = [0u8; 16];
+        let cfg = CdsConfig::new(2, CdsResolution::Micros);
+        assert_eq!(
+            cfg.encode(Duration::from_secs(65_536 * 86_400), &mut buf),
+            Err(CcsdsError::Overflow)
+        );
+
+        // Milliseconds of day out of range
+        cfg.encode(Duration::ZERO, &mut buf).unwrap();
+        buf[2..6].copy_from_slice(&86_400_000u32.to_be_bytes());
+        assert_eq!(cfg.decode(&buf), Err(CcsdsError::InvalidValue));
+
+        // Microseconds of millisecond out of range
+        cfg.encode(Duration::ZERO, &mut buf).unwrap();
+        buf[6..8].copy_from_slice(&1_000u16.to_be_bytes());
+        assert_eq!(cfg.decode(&buf), Err(CcsdsError::InvalidValue));
+
+        assert_eq!(
+            CdsConfig::decode_p_field(&[0b0100_0011]),
+            Err(CcsdsError::InvalidPField)
+        );
+    }
+}
@@ -0,0 +1,423 @@
+//! Time Source Health
+//!
+//! How far a TimeSource can be trusted, and a TimeSource failing over between several
+//! prioritised sources when the preferred one becomes invalid.
+
+use super::{LeapSecondTable, MissionTime, MonotonicInstant, TimeSource, UtcTime};
+use core::cell::Cell;
+use core::time::Duration;
+
+/// Kind of clock backing a TimeSource
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimeSourceKind {
+    /// Clock of the operating system
+    OsClock,
+    /// Battery backed real time clock
+    Rtc,
+    /// Time received from a GNSS receiver
+    Gnss,
+    /// Simulated or replayed time
+    Simulated,
+    /// Anything else, e.g. time distributed by another node
+    Other,
+}
+
+/// Health of a TimeSource
+///
+/// * `kind`: Kind of clock backing the source
+/// * `valid`: Whether the source currently provides time at all. Invalid sources are
+///   skipped by the FailoverTimeSource.
+/// * `synchronized`: Whether the source is disciplined to an external reference
+/// * `error_bound`: Estimated bound of the error of the absolute time, None if unknown
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TimeSourceHealth {
+    pub kind: TimeSourceKind,
+    pub valid: bool,
+    pub synchronized: bool,
+    pub error_bound: Option<Duration>,
+}
+
+impl TimeSourceHealth {
+    /// A valid, free running source of the given kind with an unknown error
+    pub const fn free_running(kind: TimeSourceKind) -> Self {
+        Self {
+            kind,
+            valid: true,
+            synchronized: false,
+            error_bound: None,
+        }
+    }
+
+    /// A source of the given kind that provides no time
+    pub const fn invalid(kind: TimeSourceKind) -> Self {
+        Self {
+            kind,
+            valid: false,
+            synchronized: false,
+            error_bound: None,
+        }
+    }
+}
+
+/// Default interval between health checks of a FailoverTimeSource
+pub const DEFAULT_HEALTH_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Queries after which a FailoverTimeSource checks health, however little time passed
+pub const MAX_QUERIES_BETWEEN_CHECKS: u32 = 1000;
+
+/// A TimeSource reading from the first valid source of a list ordered by priority, e.g.
+/// GNSS, then the RTC, then the OS clock.
+///
+/// Every source counts monotonic and mission time from its own start. On a switch offsets
+/// are applied to the new source so both continue from where the previous source left
+/// off, monotonic time never goes backwards. UTC of the active source is corrected by the
+/// same offset as mission time, so both scales keep agreeing with each other; the offsets
+/// are carried along when a recovered source is taken back.
+///
+/// Querying health can be costly (e.g. a syscall per source), so the sources are only
+/// checked once per check interval of monotonic time, and at least every
+/// `MAX_QUERIES_BETWEEN_CHECKS` queries in case the clock of the active source stopped.
+///
+/// * `sources`: Sources, highest priority first
+/// * `active`: Index of the source in use
+/// * `offset_ns`: Offset applied to the monotonic time of the active source
+/// * `mission_offset_ns`: Offset applied to the mission time and UTC of the active source
+/// * `last`: Latest monotonic time returned
+/// * `last_mission`: Latest mission time returned, and the monotonic time it was taken at
+/// * `check_interval`: Monotonic time between health checks
+/// * `checked`: Monotonic time of the latest health check, None before the first one
+/// * `queries`: Queries since the latest health check
+/// * `failovers`: Number of switches between sources
+pub struct FailoverTimeSource<'a, const N: usize> {
+    // NOTE: NOT THREADSAFE
+    sources: [&'a dyn TimeSource; N],
+    active: Cell<usize>,
+    offset_ns: Cell<i128>,
+    mission_offset_ns: Cell<i128>,
+    last: Cell<MonotonicInstant>,
+    last_mission: Cell<(MonotonicInstant, MissionTime)>,
+    check_interval: Duration,
+    checked: Cell<Option<MonotonicInstant>>,
+    queries: Cell<u32>,
+    failovers: Cell<u32>,
+}
+
+impl<'a, const N: usize> FailoverTimeSource<'a, N> {
+    /// Creates a failover source starting with the highest priority valid source
+    pub fn new(sources: [&'a dyn TimeSource; N]) -> Self {
+        let source = Self {
+            sources,
+            active: Cell::new(0),
+            offset_ns: Cell::new(0),
+            mission_offset_ns: Cell::new(0),
+            last: Cell::new(MonotonicInstant::ZERO),
+            last_mission: Cell::new((MonotonicInstant::ZERO, MissionTime::ZERO)),
+            check_interval: DEFAULT_HEALTH_CHECK_INTERVAL,
+            checked: Cell::new(None),
+            queries: Cell::new(0),
+            failovers: Cell::new(0),
+        };
+        if let Some(index) = source.select() {
+            let now = sources[index].monotonic();
+            source.active.set(index);
+            source.last.set(now);
+            source
+                .last_mission
+                .set((now, sources[index].mission_time()));
+            source.checked.set(Some(now));
+        }
+        source
+    }
+
+    /// Sets the monotonic time between health checks, `DEFAULT_HEALTH_CHECK_INTERVAL` by
+    /// default. A failed source is noticed at most one interval late, zero checks on every
+    /// query.
+    pub fn with_check_interval(mut self, interval: Duration) -> Self {
+        self.check_interval = interval;
+        self
+    }
+
+    /// Index of the source in use
+    pub fn active(&self) -> usize {
+        self.active.get()
+    }
+
+    /// Number of switches between sources so far
+    pub fn failovers(&self) -> u32 {
+        self.failovers.get()
+    }
+
+    /// Health of every source, in priority order
+    pub fn source_health(&self) -> [TimeSourceHealth; N] {
+        core::array::from_fn(|i| self.sources[i].health())
+    }
+
+    /// Highest priority valid source
+    fn select(&self) -> Option<usize> {
+        self.sources.iter().position(|s| s.health().valid)
+    }
+
+    /// Monotonic time of the active source with the offset applied, not clamped to the
+    /// latest value returned
+    fn raw_monotonic(&self, source: &dyn TimeSource) -> MonotonicInstant {
+        let raw = source.monotonic().since_start().as_nanos() as i128;
+        let nanos = (raw + self.offset_ns.get()).clamp(0, u64::MAX as i128) as u64;
+        MonotonicInstant::from_start(Duration::from_nanos(nanos))
+    }
+
+    /// Switches to the highest priority valid source, if it is not the active one. The
+    /// sources are checked once per check interval, so a recovered primary is taken back
+    /// as well.
+    fn update(&self) -> &'a dyn TimeSource {
+        let active = self.sources[self.active.get()];
+        let now = self.raw_monotonic(active);
+        // A source whose clock stopped never reaches the interval, so the number of
+        // queries between checks is bounded as well
+        let queries = self.queries.get().saturating_add(1);
+        let due = queries >= MAX_QUERIES_BETWEEN_CHECKS
+            || self.checked.get().is_none_or(|checked| {
+                now.saturating_duration_since(checked) >= self.check_interval
+            });
+        if !due {
+            self.queries.set(queries);
+            return active;
+        }
+        self.checked.set(Some(now));
+        self.queries.set(0);
+
+        let Some(index) = self.select() else {
+            return active;
+        };
+        if index != self.active.get() {
+            let source = self.sources[index];
+            let last = self.last.get().max(now);
+
+            let raw = source.monotonic().since_start().as_nanos() as i128;
+            self.offset_ns
+                .set(last.since_start().as_nanos() as i128 - raw);
+
+            // Mission time continues from the latest value returned, advanced by the
+            // monotonic time passed since
+            let (taken, mission) = self.last_mission.get();
+            let expected = i128::from(mission.as_nanos())
+                + last.saturating_duration_since(taken).as_nanos() as i128;
+            let raw = i128::from(source.mission_time().as_nanos());
+            self.mission_offset_ns.set(expected - raw);
+
+            self.last.set(last);
+            self.checked.set(Some(last));
+            self.active.set(index);
+            self.failovers.set(self.failovers.get().saturating_add(1));
+        }
+        self.sources[index]
+    }
+}
+
+impl<const N: usize> TimeSource for FailoverTimeSource<'_, N> {
+    fn monotonic(&self) -> MonotonicInstant {
+        let source = self.update();
+        let now = self.raw_monotonic(source).max(self.last.get());
+        self.last.set(now);
+        now
+    }
+
+    fn mission_time(&self) -> MissionTime {
+        let source = self.update();
+        let raw = i128::from(source.mission_time().as_nanos());
+        let nanos = (raw + self.mission_offset_ns.get()).clamp(i64::MIN.into(), i64::MAX.into());
+        let mission = MissionTime::from_nanos(nanos as i64);
+        self.last_mission.set((self.monotonic(), mission));
+        mission
+    }
+
+    fn utc(&self) -> Option<UtcTime> {
+        let raw = self.update().utc()?.0.as_nanos() as i128;
+        let nanos = (raw + self.mission_offset_ns.get()).max(0);
+        Some(UtcTime(Duration::new(
+            (nanos / 1_000_000_000) as u64,
+            (nanos % 1_000_000_000) as u32,
+        )))
+    }
+
+    fn health(&self) -> TimeSourceHealth {
+        self.update().health()
+    }
+
+    /// Passed on to every source, so a backup is aligned by the time it takes over
+    fn leap_seconds_changed(&self, leap_seconds: &LeapSecondTable) {
+        for source in self.sources {
+            source.leap_seconds_changed(leap_seconds);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::SimulatedTimeSource;
+
+    /// UTC at the start of every simulated source
+    const UTC_BASE: Duration = Duration::from_secs(1_735_689_600);
+
+    /// A simulated source that can be switched invalid, counting its health checks
+    struct Flaky {
+        clock: SimulatedTimeSource,
+        kind: TimeSourceKind,
+        valid: Cell<bool>,
+        checks: Cell<u32>,
+    }
+
+    impl Flaky {
+        fn new(kind: TimeSourceKind) -> Self {
+            Self {
+                clock: SimulatedTimeSource::new(),
+                kind,
+                valid: Cell::new(true),
+                checks: Cell::new(0),
+            }
+        }
+    }
+
+    impl TimeSource for Flaky {
+        fn monotonic(&self) -> MonotonicInstant {
+            self.clock.monotonic()
+        }
+
+        fn mission_time(&self) -> MissionTime {
+            self.clock.mission_time()
+        }
+
+        fn utc(&self) -> Option<UtcTime> {
+            Some(UtcTime(UTC_BASE + self.clock.monotonic().since_start()))
+        }
+
+        fn health(&self) -> TimeSourceHealth {
+            self.checks.set(self.checks.get() + 1);
+            TimeSourceHealth {
+                kind: self.kind,
+                valid: self.valid.get(),
+                synchronized: self.kind == TimeSourceKind::Gnss,
+                error_bound: None,
+            }
+        }
+    }
+
+    #[test]
+    /// The failover source switches to the next valid source and back, keeping monotonic
+    /// time continuous
+    fn failover_between_sources() {
+        let gnss = Flaky::new(TimeSourceKind::Gnss);
+        let rtc = Flaky::new(TimeSourceKind::Rtc);
+        gnss.clock.set(Duration::from_secs(100));
+        rtc.clock.set(Duration::from_secs(5));
+
+        let source = FailoverTimeSource::new([&gnss, &rtc]).with_check_interval(Duration::ZERO);
+        assert_eq!(source.active(), 0);
+        assert_eq!(source.health().kind, TimeSourceKind::Gnss);
+        assert_eq!(source.monotonic().since_start(), Duration::from_secs(100));
+
+        // Primary fails: the RTC takes over without a jump in monotonic time
+        gnss.valid.set(false);
+        assert_eq!(source.monotonic().since_start(), Duration::from_secs(100));
+        assert_eq!(source.active(), 1);
+        assert_eq!(source.health().kind, TimeSourceKind::Rtc);
+        rtc.clock.advance(Duration::from_secs(2));
+        assert_eq!(source.monotonic().since_start(), Duration::from_secs(102));
+        // Mission time continues from the primary as well
+        assert_eq!(
+            source.mission_time(),
+            MissionTime::after_epoch(Duration::from_secs(102))
+        );
+
+        // Primary recovers behind the current time: monotonic time does not go backwards
+        gnss.valid.set(true);
+        assert_eq!(source.monotonic().since_start(), Duration::from_secs(102));
+        gnss.clock.advance(Duration::from_secs(1));
+        assert_eq!(source.monotonic().since_start(), Duration::from_secs(103));
+        assert_eq!(source.active(), 0);
+        assert_eq!(source.failovers(), 2);
+
+        // Nothing valid: the last source stays in use
+        gnss.valid.set(false);
+        rtc.valid.set(false);
+        assert!(!source.health().valid);
+        assert_eq!(source.source_health().map(|h| h.valid), [false, false]);
+    }
+
+    #[test]
+    /// Mission time and UTC stay continuous and agree with each other across a failover
+    /// and the recovery of the primary
+    fn recovery_keeps_scales_consistent() {
+        let gnss = Flaky::new(TimeSourceKind::Gnss);
+        let rtc = Flaky::new(TimeSourceKind::Rtc);
+        gnss.clock.set(Duration::from_secs(100));
+        rtc.clock.set(Duration::from_secs(5));
+        let source = FailoverTimeSource::new([&gnss, &rtc]).with_check_interval(Duration::ZERO);
+
+        // UTC minus mission time, constant while the scales agree
+        let gap = |source: &FailoverTimeSource<2>| {
+            let mission = source.mission_time().to_duration().unwrap();
+            source.utc().unwrap().0 - mission
+        };
+        assert_eq!(gap(&source), UTC_BASE);
+
+        gnss.valid.set(false);
+        assert_eq!(
+            source.mission_time(),
+            MissionTime::after_epoch(Duration::from_secs(100))
+        );
+        assert_eq!(source.active(), 1);
+        rtc.clock.advance(Duration::from_secs(2));
+        assert_eq!(
+            source.utc(),
+            Some(UtcTime(UTC_BASE + Duration::from_secs(102)))
+        );
+        assert_eq!(gap(&source), UTC_BASE);
+
+        // The primary recovers, 2s behind: both scales continue from the backup
+        gnss.valid.set(true);
+        assert_eq!(
+            source.mission_time(),
+            MissionTime::after_epoch(Duration::from_secs(102))
+        );
+        assert_eq!(source.active(), 0);
+        gnss.clock.advance(Duration::from_secs(1));
+        assert_eq!(
+            source.mission_time(),
+            MissionTime::after_epoch(Duration::from_secs(103))
+        );
+        assert_eq!(
+            source.utc(),
+            Some(UtcTime(UTC_BASE + Duration::from_secs(103)))
+        );
+        assert_eq!(gap(&source), UTC_BASE);
+        assert_eq!(source.failovers(), 2);
+    }
+
+    #[test]
+    /// Health is checked once per check interval while the clocks advance, a failed
+    /// source is noticed at the next check
+    fn bounded_health_checks() {
+        let gnss = Flaky::new(TimeSourceKind::Gnss);
+        let rtc = Flaky::new(TimeSourceKind::Rtc);
+        let source =
+            FailoverTimeSource::new([&gnss, &rtc]).with_check_interval(Duration::from_secs(1));
+        gnss.checks.set(0);
+
+        for _ in 0..100 {
+            gnss.clock.advance(Duration::from_millis(10));
+            source.monotonic();
+            source.mission_time();
+        }
+        assert_eq!(gnss.checks.get(), 1);
+        assert_eq!(rtc.checks.get(), 0);
+
+        gnss.valid.set(false);
+        gnss.clock.advance(Duration::from_millis(10));
+        source.monotonic();
+        assert_eq!(source.active(), 0);
+        gnss.clock.advance(Duration::from_secs(1));
+        source.monotonic();
+        assert_eq!(source.active(), 1);
+    }
+}
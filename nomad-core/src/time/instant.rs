@@ -0,0 +1,272 @@
+//! Time Instants
+//!
+//! Distinct types for the two time domains, so monotonic time cannot be mixed up with
+//! mission time. Arithmetic is limited to meaningful combinations: an instant plus or minus
+//! a `Duration` is an instant of the same domain, the difference of two instants of the
+//! same domain is a `Duration`. Instants of different domains cannot be combined or
+//! compared.
+
+use core::fmt;
+use core::ops::{Add, AddAssign, Sub, SubAssign};
+use core::time::Duration;
+
+/// A point on the monotonic timeline, as time since the TimeSource started (usually boot).
+/// Used for timeouts, scheduling and measuring intervals.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MonotonicInstant(Duration);
+
+impl MonotonicInstant {
+    /// The start of the monotonic timeline
+    pub const ZERO: Self = Self(Duration::ZERO);
+
+    /// Instant `d` after the start of the timeline
+    pub const fn from_start(d: Duration) -> Self {
+        Self(d)
+    }
+
+    /// Time since the start of the timeline
+    pub const fn since_start(&self) -> Duration {
+        self.0
+    }
+
+    /// Time elapsed from `earlier` to this instant, None if `earlier` is later
+    pub fn checked_duration_since(&self, earlier: MonotonicInstant) -> Option<Duration> {
+        self.0.checked_sub(earlier.0)
+    }
+
+    /// Time elapsed from `earlier` to this instant, zero if `earlier` is later
+    pub fn saturating_duration_since(&self, earlier: MonotonicInstant) -> Duration {
+        self.0.saturating_sub(earlier.0)
+    }
+
+    /// Instant `d` after this one, None on overflow
+    pub fn checked_add(&self, d: Duration) -> Option<Self> {
+        self.0.checked_add(d).map(Self)
+    }
+
+    /// Instant `d` before this one, None before the start of the timeline
+    pub fn checked_sub(&self, d: Duration) -> Option<Self> {
+        self.0.checked_sub(d).map(Self)
+    }
+}
+
+/// Saturates at the end of the timeline
+impl Add<Duration> for MonotonicInstant {
+    type Output = Self;
+
+    fn add(self, d: Duration) -> Self {
+        Self(self.0.saturating_add(d))
+    }
+}
+
+impl AddAssign<Duration> for MonotonicInstant {
+    fn add_assign(&mut self, d: Duration) {
+        *self = *self + d;
+    }
+}
+
+/// Saturates at the start of the timeline
+impl Sub<Duration> for MonotonicInstant {
+    type Output = Self;
+
+    fn sub(self, d: Duration) -> Self {
+        Self(self.0.saturating_sub(d))
+    }
+}
+
+impl SubAssign<Duration> for MonotonicInstant {
+    fn sub_assign(&mut self, d: Duration) {
+        *self = *self - d;
+    }
+}
+
+/// Time elapsed between two instants, saturating at zero
+impl Sub for MonotonicInstant {
+    type Output = Duration;
+
+    fn sub(self, earlier: Self) -> Duration {
+        self.saturating_duration_since(earlier)
+    }
+}
+
+/// Signed mission elapsed time. Negative before the mission epoch (T-minus, during the
+/// countdown), positive after it (T-plus). Nanosecond resolution, covers about 292 years
+/// either side of the epoch.
+///
+/// Displays as `T-HH:MM:SS.ffffff` before the epoch and `T+HH:MM:SS.ffffff` from it on.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MissionTime {
+    nanos: i64,
+}
+
+impl MissionTime {
+    /// The mission epoch, T0
+    pub const ZERO: Self = Self { nanos: 0 };
+
+    /// Mission time from signed nanoseconds relative to the epoch
+    pub const fn from_nanos(nanos: i64) -> Self {
+        Self { nanos }
+    }
+
+    /// Signed nanoseconds relative to the epoch
+    pub const fn as_nanos(&self) -> i64 {
+        self.nanos
+    }
+
+    /// Mission time `d` after the epoch, saturating
+    pub fn after_epoch(d: Duration) -> Self {
+        Self::from_nanos(signed_nanos(d))
+    }
+
+    /// Mission time `d` before the epoch, saturating
+    pub fn before_epoch(d: Duration) -> Self {
+        Self::from_nanos(i64::try_from(d.as_nanos()).map_or(i64::MIN, |n| -n))
+    }
+
+    /// Mission time at `now` for an epoch at `epoch`, both on the same timeline
+    pub fn between(epoch: Duration, now: Duration) -> Self {
+        match now.checked_sub(epoch) {
+            Some(elapsed) => Self::after_epoch(elapsed),
+            None => Self::before_epoch(epoch - now),
+        }
+    }
+
+    /// Time elapsed from `earlier` to this mission time, None if `earlier` is later
+    pub fn checked_duration_since(&self, earlier: MissionTime) -> Option<Duration> {
+        let nanos = self.nanos.checked_sub(earlier.nanos)?;
+        u64::try_from(nanos).ok().map(Duration::from_nanos)
+    }
+
+    /// Time elapsed from `earlier` to this mission time, zero if `earlier` is later
+    pub fn saturating_duration_since(&self, earlier: MissionTime) -> Duration {
+        self.checked_duration_since(earlier)
+            .unwrap_or(Duration::ZERO)
+    }
+
+    /// Whether the time lies before the epoch, i.e. in the countdown
+    pub fn is_before_epoch(&self) -> bool {
+        self.nanos < 0
+    }
+
+    /// Distance from the epoch, regardless of direction
+    pub fn magnitude(&self) -> Duration {
+        Duration::from_nanos(self.nanos.unsigned_abs())
+    }
+
+    /// Time since the epoch, None before the epoch
+    pub fn to_duration(&self) -> Option<Duration> {
+        (!self.is_before_epoch()).then(|| self.magnitude())
+    }
+}
+
+/// Durations longer than about 292 years saturate
+fn signed_nanos(d: Duration) -> i64 {
+    i64::try_from(d.as_nanos()).unwrap_or(i64::MAX)
+}
+
+/// Saturates at the limits of the mission timeline
+impl Add<Duration> for MissionTime {
+    type Output = Self;
+
+    fn add(self, d: Duration) -> Self {
+        Self::from_nanos(self.nanos.saturating_add(signed_nanos(d)))
+    }
+}
+
+impl AddAssign<Duration> for MissionTime {
+    fn add_assign(&mut self, d: Duration) {
+        *self = *self + d;
+    }
+}
+
+/// Saturates at the limits of the mission timeline
+impl Sub<Duration> for MissionTime {
+    type Output = Self;
+
+    fn sub(self, d: Duration) -> Self {
+        Self::from_nanos(self.nanos.saturating_sub(signed_nanos(d)))
+    }
+}
+
+impl SubAssign<Duration> for MissionTime {
+    fn sub_assign(&mut self, d: Duration) {
+        *self = *self - d;
+    }
+}
+
+/// Time elapsed between two mission times, saturating at zero
+impl Sub for MissionTime {
+    type Output = Duration;
+
+    fn sub(self, earlier: Self) -> Duration {
+        self.saturating_duration_since(earlier)
+    }
+}
+
+impl fmt::Display for MissionTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.is_before_epoch() { '-' } else { '+' };
+        let magnitude = self.magnitude();
+        let secs = magnitude.as_secs();
+        write!(
+            f,
+            "T{}{:02}:{:02}:{:02}.{:06}",
+            sign,
+            secs / 3600,
+            (secs / 60) % 60,
+            secs % 60,
+            magnitude.subsec_micros()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    extern crate std;
+    use std::string::ToString;
+
+    #[test]
+    /// Mission time is signed around the epoch and displays as T-minus or T-plus
+    fn signed_mission_time() {
+        let countdown = MissionTime::before_epoch(Duration::from_secs(10));
+        assert!(countdown.is_before_epoch());
+        assert_eq!(countdown.magnitude(), Duration::from_secs(10));
+        assert_eq!(countdown.to_duration(), None);
+        assert!(countdown < MissionTime::ZERO);
+
+        assert_eq!(countdown.to_string(), "T-00:00:10.000000");
+
+        let met = MissionTime::after_epoch(Duration::new(3723, 456_789_000));
+        assert_eq!(met.to_string(), "T+01:02:03.456789");
+        assert_eq!(met.to_duration(), Some(Duration::new(3723, 456_789_000)));
+    }
+
+    #[test]
+    /// Instants move by durations, and their differences are durations
+    fn instant_arithmetic() {
+        let start = MonotonicInstant::from_start(Duration::from_secs(5));
+        let later = start + Duration::from_millis(1500);
+        assert_eq!(later - start, Duration::from_millis(1500));
+        assert_eq!(start - later, Duration::ZERO);
+        assert_eq!(start.checked_duration_since(later), None);
+        assert_eq!(start - Duration::from_secs(10), MonotonicInstant::ZERO);
+        let end = MonotonicInstant::from_start(Duration::MAX);
+        assert_eq!(start + Duration::MAX, end);
+        assert_eq!(start.checked_add(Duration::MAX), None);
+
+        // Crossing the epoch
+        let countdown = MissionTime::before_epoch(Duration::from_secs(3));
+        let liftoff = countdown + Duration::from_secs(3);
+        assert_eq!(liftoff, MissionTime::ZERO);
+        assert_eq!(
+            (liftoff + Duration::from_secs(2)) - countdown,
+            Duration::from_secs(5)
+        );
+        assert_eq!(countdown.checked_duration_since(liftoff), None);
+        assert_eq!(
+            liftoff - Duration::from_secs(1),
+            MissionTime::before_epoch(Duration::from_secs(1))
+        );
+    }
+}
@@ -0,0 +1,391 @@
+//! Time Scales
+//!
+//! UTC, TAI and GPS time, and conversions between them driven by a leap-second table.
+//!
+//! * UTC is represented as POSIX time: time since 1970-01-01T00:00:00Z, not counting leap
+//!   seconds. During an inserted leap second UTC repeats the first second of the next day.
+//! * TAI is represented as SI seconds since 1958-01-01T00:00:00 TAI.
+//! * GPS time is represented as SI seconds since 1980-01-06T00:00:00 UTC, it is a constant
+//!   19 seconds behind TAI.
+
+use core::time::Duration;
+
+/// TAI seconds from the TAI epoch (1958-01-01) to the Unix epoch (1970-01-01), not
+/// including the TAI-UTC offset
+pub const TAI_TO_UNIX_EPOCH: Duration = Duration::from_secs(378_691_200);
+
+/// TAI seconds from the TAI epoch to the GPS epoch (1980-01-06), including the 19 seconds
+/// TAI-UTC offset in effect at the GPS epoch
+pub const TAI_TO_GPS_EPOCH: Duration = Duration::from_secs(694_656_019);
+
+/// Seconds in a GPS week
+const SECS_PER_WEEK: u64 = 604_800;
+
+/// UTC as time since the Unix epoch, not counting leap seconds
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UtcTime(pub Duration);
+
+/// TAI as time since 1958-01-01T00:00:00 TAI
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TaiTime(pub Duration);
+
+/// GPS time as time since 1980-01-06T00:00:00 UTC
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GpsTime(pub Duration);
+
+/// Time scales Nomad can report time in
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimeScale {
+    Utc,
+    Tai,
+    Gps,
+}
+
+/// A point in time given in any of the supported time scales, e.g. a mission epoch
+/// taken from a GNSS receiver in GPS time
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Epoch {
+    Utc(UtcTime),
+    Tai(TaiTime),
+    Gps(GpsTime),
+}
+
+impl Epoch {
+    /// Time scale the epoch is given in
+    pub fn scale(&self) -> TimeScale {
+        match self {
+            Epoch::Utc(_) => TimeScale::Utc,
+            Epoch::Tai(_) => TimeScale::Tai,
+            Epoch::Gps(_) => TimeScale::Gps,
+        }
+    }
+
+    /// The epoch as UTC
+    pub fn to_utc(&self, leap_seconds: &LeapSecondTable) -> UtcTime {
+        match *self {
+            Epoch::Utc(utc) => utc,
+            Epoch::Tai(tai) => leap_seconds.tai_to_utc(tai),
+            Epoch::Gps(gps) => leap_seconds.gps_to_utc(gps),
+        }
+    }
+}
+
+impl UtcTime {
+    /// UTC from whole seconds since the Unix epoch
+    pub const fn from_unix_secs(secs: u64) -> Self {
+        UtcTime(Duration::from_secs(secs))
+    }
+}
+
+impl GpsTime {
+    /// GPS week number, counted from the GPS epoch without rollover
+    pub fn week(&self) -> u32 {
+        (self.0.as_secs() / SECS_PER_WEEK) as u32
+    }
+
+    /// Time since the start of the GPS week
+    pub fn time_of_week(&self) -> Duration {
+        Duration::new(self.0.as_secs() % SECS_PER_WEEK, self.0.subsec_nanos())
+    }
+
+    /// GPS time from a week number and time of week, as broadcast by GNSS receivers
+    pub fn from_week(week: u32, time_of_week: Duration) -> Self {
+        GpsTime(Duration::from_secs(u64::from(week) * SECS_PER_WEEK) + time_of_week)
+    }
+}
+
+/// A leap-second table entry: from `utc` on, TAI-UTC is `tai_minus_utc` seconds
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LeapSecond {
+    pub utc: UtcTime,
+    pub tai_minus_utc: i32,
+}
+
+/// Maximum number of entries a LeapSecondTable can hold
+pub const MAX_LEAP_SECONDS: usize = 64;
+
+/// Leap seconds announced by IERS up to and including 2017-01-01
+const BUILTIN_LEAP_SECONDS: [(u64, i32); 28] = [
+    (63_072_000, 10),    // 1972-01-01
+    (78_796_800, 11),    // 1972-07-01
+    (94_694_400, 12),    // 1973-01-01
+    (126_230_400, 13),   // 1974-01-01
+    (157_766_400, 14),   // 1975-01-01
+    (189_302_400, 15),   // 1976-01-01
+    (220_924_800, 16),   // 1977-01-01
+    (252_460_800, 17),   // 1978-01-01
+    (283_996_800, 18),   // 1979-01-01
+    (315_532_800, 19),   // 1980-01-01
+    (362_793_600, 20),   // 1981-07-01
+    (394_329_600, 21),   // 1982-07-01
+    (425_865_600, 22),   // 1983-07-01
+    (489_024_000, 23),   // 1985-07-01
+    (567_993_600, 24),   // 1988-01-01
+    (631_152_000, 25),   // 1990-01-01
+    (662_688_000, 26),   // 1991-01-01
+    (709_948_800, 27),   // 1992-07-01
+    (741_484_800, 28),   // 1993-07-01
+    (773_020_800, 29),   // 1994-07-01
+    (820_454_400, 30),   // 1996-01-01
+    (867_715_200, 31),   // 1997-07-01
+    (915_148_800, 32),   // 1999-01-01
+    (1_136_073_600, 33), // 2006-01-01
+    (1_230_768_000, 34), // 2009-01-01
+    (1_341_100_800, 35), // 2012-07-01
+    (1_435_708_800, 36), // 2015-07-01
+    (1_483_228_800, 37), // 2017-01-01
+];
+
+/// Errors updating a LeapSecondTable
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LeapSecondError {
+    /// The table has no room for another entry
+    TableFull,
+    /// Entries must be added in chronological order
+    OutOfOrder,
+}
+
+/// Updatable table of leap seconds, ordered by date.
+///
+/// Starts out with the leap seconds known at build time (`builtin`), newly announced
+/// leap seconds can be appended in flight, e.g. from a ground command or GNSS almanac.
+///
+/// * `entries`: Leap seconds, oldest first
+/// * `len`: Number of valid entries
+/// * `expires`: Date until which the table is known to be complete
+#[derive(Copy, Clone, Debug)]
+pub struct LeapSecondTable {
+    entries: [LeapSecond; MAX_LEAP_SECONDS],
+    len: usize,
+    expires: Option<UtcTime>,
+}
+
+impl LeapSecondTable {
+    /// An empty table, TAI-UTC reads as 0
+    pub const fn empty() -> Self {
+        Self {
+            entries: [LeapSecond {
+                utc: UtcTime(Duration::ZERO),
+                tai_minus_utc: 0,
+            }; MAX_LEAP_SECONDS],
+            len: 0,
+            expires: None,
+        }
+    }
+
+    /// Table with the leap seconds known at build time
+    pub fn builtin() -> Self {
+        let mut table = Self::empty();
+        for (secs, tai_minus_utc) in BUILTIN_LEAP_SECONDS {
+            // Cannot fail, the builtin list is ordered and fits the table
+            let _ = table.push(LeapSecond {
+                utc: UtcTime::from_unix_secs(secs),
+                tai_minus_utc,
+            });
+        }
+        table
+    }
+
+    /// Appends a leap second. Entries must be newer than the current last entry.
+    pub fn push(&mut self, entry: LeapSecond) -> Result<(), LeapSecondError> {
+        if self.len == MAX_LEAP_SECONDS {
+            return Err(LeapSecondError::TableFull);
+        }
+        if self
+            .entries()
+            .last()
+            .is_some_and(|last| last.utc >= entry.utc)
+        {
+            return Err(LeapSecondError::OutOfOrder);
+        }
+        self.entries[self.len] = entry;
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Entries of the table, oldest first
+    pub fn entries(&self) -> &[LeapSecond] {
+        &self.entries[..self.len]
+    }
+
+    /// Sets the date until which the table is known to be complete
+    pub fn set_expiry(&mut self, expires: Option<UtcTime>) {
+        self.expires = expires;
+    }
+
+    /// Whether the table may be missing leap seconds at `at`
+    pub fn is_expired(&self, at: UtcTime) -> bool {
+        self.expires.is_some_and(|expires| at >= expires)
+    }
+
+    /// TAI-UTC in seconds at the given UTC. Before the first entry the offset of the
+    /// first entry applies.
+    pub fn tai_minus_utc(&self, utc: UtcTime) -> i32 {
+        let entries = self.entries();
+        entries
+            .iter()
+            .rev()
+            .find(|e| e.utc <= utc)
+            .or(entries.first())
+            .map_or(0, |e| e.tai_minus_utc)
+    }
+
+    /// Converts UTC to TAI
+    pub fn utc_to_tai(&self, utc: UtcTime) -> TaiTime {
+        TaiTime(offset(utc.0 + TAI_TO_UNIX_EPOCH, self.tai_minus_utc(utc)))
+    }
+
+    /// Converts TAI to UTC. Times before the Unix epoch are clamped to it.
+    pub fn tai_to_utc(&self, tai: TaiTime) -> UtcTime {
+        let entries = self.entries();
+        // Find the newest entry already in effect at this TAI
+        let tai_minus_utc = entries
+            .iter()
+            .rev()
+            .find(|e| offset(e.utc.0 + TAI_TO_UNIX_EPOCH, e.tai_minus_utc) <= tai.0)
+            .or(entries.first())
+            .map_or(0, |e| e.tai_minus_utc);
+
+        UtcTime(offset(tai.0, -tai_minus_utc).saturating_sub(TAI_TO_UNIX_EPOCH))
+    }
+
+    /// Converts UTC to GPS time. Times before the GPS epoch are clamped to it.
+    pub fn utc_to_gps(&self, utc: UtcTime) -> GpsTime {
+        tai_to_gps(self.utc_to_tai(utc))
+    }
+
+    /// Converts GPS time to UTC
+    pub fn gps_to_utc(&self, gps: GpsTime) -> UtcTime {
+        self.tai_to_utc(gps_to_tai(gps))
+    }
+}
+
+impl Default for LeapSecondTable {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+/// Converts TAI to GPS time, a constant offset. Times before the GPS epoch are clamped to it.
+pub fn tai_to_gps(tai: TaiTime) -> GpsTime {
+    GpsTime(tai.0.saturating_sub(TAI_TO_GPS_EPOCH))
+}
+
+/// Converts GPS time to TAI, a constant offset
+pub fn gps_to_tai(gps: GpsTime) -> TaiTime {
+    TaiTime(gps.0 + TAI_TO_GPS_EPOCH)
+}
+
+/// Adds a signed number of seconds to a duration, saturating at zero
+fn offset(d: Duration, secs: i32) -> Duration {
+    if secs >= 0 {
+        d + Duration::from_secs(secs as u64)
+    } else {
+        d.saturating_sub(Duration::from_secs(secs.unsigned_abs() as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2017-01-01T00:00:00Z, the latest leap second
+    const LEAP_2017: u64 = 1_483_228_800;
+
+    #[test]
+    /// TAI-UTC follows the table
+    fn tai_minus_utc() {
+        let table = LeapSecondTable::builtin();
+        assert_eq!(table.tai_minus_utc(UtcTime::from_unix_secs(0)), 10);
+        assert_eq!(
+            table.tai_minus_utc(UtcTime::from_unix_secs(LEAP_2017 - 1)),
+            36
+        );
+        assert_eq!(table.tai_minus_utc(UtcTime::from_unix_secs(LEAP_2017)), 37);
+        assert_eq!(
+            LeapSecondTable::empty().tai_minus_utc(UtcTime::from_unix_secs(0)),
+            0
+        );
+    }
+
+    #[test]
+    /// Conversions between UTC, TAI and GPS round trip
+    fn conversions() {
+        let table = LeapSecondTable::builtin();
+        let utc = UtcTime(Duration::new(1_735_689_600, 250_000_000)); // 2025-01-01
+
+        let tai = table.utc_to_tai(utc);
+        assert_eq!(tai.0, utc.0 + TAI_TO_UNIX_EPOCH + Duration::from_secs(37));
+        assert_eq!(table.tai_to_utc(tai), utc);
+
+        // GPS is 18 seconds ahead of UTC since 2017
+        let gps = table.utc_to_gps(utc);
+        assert_eq!(
+            gps.0 + Duration::from_secs(315_964_800),
+            utc.0 + Duration::from_secs(18)
+        );
+        assert_eq!(table.gps_to_utc(gps), utc);
+        assert_eq!(gps_to_tai(gps), tai);
+    }
+
+    #[test]
+    /// The GPS epoch maps onto GPS time zero
+    fn gps_epoch() {
+        let table = LeapSecondTable::builtin();
+        let gps = table.utc_to_gps(UtcTime::from_unix_secs(315_964_800));
+        assert_eq!(gps, GpsTime(Duration::ZERO));
+
+        let gps = GpsTime::from_week(2345, Duration::from_secs(3600));
+        assert_eq!(gps.week(), 2345);
+        assert_eq!(gps.time_of_week(), Duration::from_secs(3600));
+    }
+
+    #[test]
+    /// UTC is continuous in TAI across a leap second
+    fn across_leap_second() {
+        let table = LeapSecondTable::builtin();
+        let before = table.utc_to_tai(UtcTime::from_unix_secs(LEAP_2017 - 1));
+        let after = table.utc_to_tai(UtcTime::from_unix_secs(LEAP_2017));
+        // 23:59:59, 23:59:60, 00:00:00, two TAI seconds for one POSIX second
+        assert_eq!(after.0 - before.0, Duration::from_secs(2));
+        assert_eq!(table.tai_to_utc(after), UtcTime::from_unix_secs(LEAP_2017));
+    }
+
+    #[test]
+    /// Epochs in any scale resolve to the same UTC
+    fn epochs() {
+        let table = LeapSecondTable::builtin();
+        let utc = UtcTime::from_unix_secs(1_735_689_600);
+        assert_eq!(Epoch::Utc(utc).to_utc(&table), utc);
+        assert_eq!(Epoch::Tai(table.utc_to_tai(utc)).to_utc(&table), utc);
+        assert_eq!(Epoch::Gps(table.utc_to_gps(utc)).to_utc(&table), utc);
+        assert_eq!(Epoch::Gps(GpsTime(Duration::ZERO)).scale(), TimeScale::Gps);
+    }
+
+    #[test]
+    /// Newly announced leap seconds are appended to the table
+    fn update_table() {
+        let mut table = LeapSecondTable::builtin();
+        let future = UtcTime::from_unix_secs(2_000_000_000);
+
+        assert_eq!(
+            table.push(LeapSecond {
+                utc: UtcTime::from_unix_secs(LEAP_2017),
+                tai_minus_utc: 38
+            }),
+            Err(LeapSecondError::OutOfOrder)
+        );
+        table
+            .push(LeapSecond {
+                utc: future,
+                tai_minus_utc: 38,
+            })
+            .unwrap();
+        assert_eq!(table.tai_minus_utc(future), 38);
+        assert_eq!(table.entries().len(), 29);
+
+        table.set_expiry(Some(future));
+        assert!(!table.is_expired(UtcTime::from_unix_secs(LEAP_2017)));
+        assert!(table.is_expired(future));
+    }
+}
@@ -4,6 +4,7 @@
 
 /// TODO: Import if building for POSIX
 use std::env;
+use std::time::Duration;
 
 // Nomad Core
 use nomad_core::component::ComponentId;
@@ -14,27 +15,65 @@ use nomad_core::components::log;
 use nomad_core::components::time::TimeService;
 use nomad_core::time::TimeConfig;
 // Logging Macros
-use nomad_core::{log_info, log_warn};
+use nomad_core::{FormattedLogHandle, LogLevel, log_error, log_info, log_warn};
+// OSAL
+use nomad_core::osal::{OsalError, Signal, Signals};
 use nomad_osal::logsinks::make_default_log_sink;
-use nomad_osal::timesource::make_default_time_source;
+use nomad_osal::signal::PosixSignals;
+use nomad_osal::timesource::{DefaultTimeSource, PosixTimeSource, try_make_default_time_source};
+
+/// How often the main loop runs while no signal arrives
+const MAIN_LOOP_PERIOD: Duration = Duration::from_millis(100);
 
 /// FSW Entrypoint
 fn main() {
     // TODO: Only print this if building for POSIX
     print_fsw_info();
 
+    // Installed first, so SIGINT and SIGTERM during startup still lead to an orderly shutdown
+    let signals = PosixSignals::install().expect("failed to install signal handlers");
+
     // Instantiates some Component ID's
     // TODO: This will all be handled by the component system when implemented
     const FSW_MAIN: ComponentId = ComponentId(0);
     const IMU: ComponentId = ComponentId(1);
 
+    // Time Subsystem and TimeService
+
+    // Create a basic TimeConfig
+    // TODO: Have config subsystem handle this
+    let time_config = TimeConfig {
+        mode: nomad_core::time::TimeMode::Real,
+        mission_epoch: None,
+    };
+
+    // A time source that cannot be created (e.g. an unreadable replay recording) must not
+    // keep the FSW from starting, it falls back to the OS clock and reports it once logging
+    // is up
+    let (time_source, time_source_error) = match try_make_default_time_source(&time_config) {
+        Ok(source) => (source, None),
+        Err(e) => (
+            DefaultTimeSource::Posix(PosixTimeSource::new(&time_config)),
+            Some(e),
+        ),
+    };
+    let time_service = TimeService::new(time_source);
+
     // Starts the LogService component
     // TODO: Startup the logging service using the component system
-    let mut logging = log::LogService::<256>::new();
+    // Records are stamped with mission time from the TimeService
+    let mut logging = log::LogService::<256, _>::with_time_source(&time_service);
 
     // FSW components can use macros to log to a logger
     // TODO: When the component bus exists, the macro should be cleaner
     log_info!(&mut logging, FSW_MAIN, "Nomad FSW starting");
+    if let Some(e) = time_source_error {
+        logging.log_formatted(
+            FSW_MAIN,
+            LogLevel::Error,
+            format_args!("Time source unavailable, using the OS clock: {e}"),
+        );
+    }
     log_info!(&mut logging, IMU, "IMU init sequence starting");
     log_warn!(&mut logging, IMU, "IMU using default calibration");
 
@@ -44,31 +83,64 @@ fn main() {
     let mut sink = make_default_log_sink();
     logging.drain(&mut sink);
 
-    // Time Subsystem and TimeService
-
-    // Create a basic TimeConfig
-    // TODO: Have config subsystem handle this
-    let time_config = TimeConfig {
-        mode: nomad_core::time::TimeMode::Real,
-        mission_epoch_unix: None,
-    };
-
-    let time_source = make_default_time_source(&time_config);
-    let time_service = TimeService::new(time_source);
-
     let time_sample1 = time_service.monotonic();
     let time_sample2 = time_service.mission_time();
     let time_sample3 = time_service.monotonic();
     let time_sample4 = time_service.mission_time();
 
     println!(
-        "Monotonic Sample1: {:?}, Mission Sample2: {:?}",
-        time_sample1, time_sample2
+        "Monotonic Sample1: {:?}, Mission Sample2: {}",
+        time_sample1.since_start(),
+        time_sample2
     );
     println!(
-        "Monotonic Sample3: {:?}, Mission Sample4: {:?}",
-        time_sample3, time_sample4
+        "Monotonic Sample3: {:?}, Mission Sample4: {}",
+        time_sample3.since_start(),
+        time_sample4
     );
+
+    // Main loop, runs until SIGINT or SIGTERM requests a shutdown
+    // TODO: Run the scheduler here once it exists
+    log_info!(&mut logging, FSW_MAIN, "Nomad FSW running");
+    logging.drain(&mut sink);
+    loop {
+        match signals.wait(Some(MAIN_LOOP_PERIOD)) {
+            Ok(Signal::Hangup) => {
+                // TODO: Reload through the config subsystem once it exists
+                log_info!(&mut logging, FSW_MAIN, "Configuration reload requested");
+            }
+            Ok(Signal::Interrupt) => {
+                log_info!(&mut logging, FSW_MAIN, "Interrupted, shutting down");
+                break;
+            }
+            Ok(Signal::Terminate) => {
+                log_info!(&mut logging, FSW_MAIN, "Terminated, shutting down");
+                break;
+            }
+            Err(OsalError::Timeout) => {}
+            Err(_) => {
+                // Without signals the FSW could no longer be stopped in order
+                log_error!(
+                    &mut logging,
+                    FSW_MAIN,
+                    "Signal handling failed, shutting down"
+                );
+                break;
+            }
+        }
+
+        if logging.has_pending() || logging.drain_requested() {
+            logging.drain(&mut sink);
+        }
+    }
+
+    // Orderly shutdown
+    // TODO: Stop the scheduler and call the component stop hooks once the component system
+    // exists
+    log_info!(&mut logging, FSW_MAIN, "Nomad FSW stopped");
+
+    // Drains the remaining logs to all sinks, which close their files when dropped
+    logging.drain(&mut sink);
 }
 
 /// Prints build information about the FSW binary
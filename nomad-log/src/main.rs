@@ -0,0 +1,325 @@
+//! Nomad Log Tool
+//!
+//! Host tool for reading Nomad log files. Parses logs in text, JSON or binary encoding,
+//! filters them by level, component, mission time and message, and can follow a log
+//! that is still being written.
+
+use std::env;
+use std::fs::{File, Metadata};
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use std::thread;
+use std::time::Duration;
+
+use nomad_core::{LogLevel, LogRecord, component::ComponentId, time::MissionTime};
+use nomad_osal::logformat::{LogEncoding, LogReader, encode_record, parse_met};
+
+/// How often a followed file is checked for new records
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+const USAGE: &str = "\
+Usage: nomad-log [OPTIONS] <FILE>
+
+Reads a Nomad log file and prints the records that match all given filters.
+
+Options:
+  -e, --encoding <ENC>   Encoding of the log file: text, json or binary (detected if omitted)
+  -l, --level <LEVEL>    Only show records at or above LEVEL (debug, info, warn, error)
+  -c, --component <ID>   Only show records from component ID, can be repeated
+      --since <MET>      Only show records at or after mission time MET
+      --until <MET>      Only show records at or before mission time MET
+  -g, --grep <PATTERN>   Only show records whose message contains PATTERN
+  -f, --follow           Keep waiting for records appended to the file, like tail -f
+  -o, --output <ENC>     Output encoding: text or json (default text)
+  -h, --help             Print this help
+
+MET is given in seconds (e.g. 90.5, negative before the mission epoch) or as
+T+HH:MM:SS.ffffff, T-HH:MM:SS.ffffff during the countdown";
+
+/// Record filter built from the command line
+#[derive(Debug, Default)]
+struct Filter {
+    min_level: Option<LogLevel>,
+    components: Vec<ComponentId>,
+    since: Option<MissionTime>,
+    until: Option<MissionTime>,
+    pattern: Option<String>,
+}
+
+impl Filter {
+    /// Whether a record passes every configured filter
+    fn matches(&self, record: &LogRecord<String>) -> bool {
+        self.min_level.is_none_or(|min| record.level >= min)
+            && (self.components.is_empty() || self.components.contains(&record.component))
+            && self.since.is_none_or(|since| record.timestamp >= since)
+            && self.until.is_none_or(|until| record.timestamp <= until)
+            && self
+                .pattern
+                .as_ref()
+                .is_none_or(|pattern| record.message.contains(pattern.as_str()))
+    }
+}
+
+/// Parsed command line
+#[derive(Debug)]
+struct Options {
+    path: String,
+    encoding: Option<LogEncoding>,
+    output: LogEncoding,
+    follow: bool,
+    filter: Filter,
+}
+
+/// Parses a mission time given either in seconds or as T±HH:MM:SS.ffffff. Negative
+/// seconds are before the mission epoch.
+fn parse_met_arg(s: &str) -> Option<MissionTime> {
+    parse_met(s).or_else(|| {
+        let secs: f64 = s.parse().ok()?;
+        let magnitude = Duration::try_from_secs_f64(secs.abs()).ok()?;
+        Some(if secs < 0.0 {
+            MissionTime::before_epoch(magnitude)
+        } else {
+            MissionTime::after_epoch(magnitude)
+        })
+    })
+}
+
+/// Parses the command line. Returns Ok(None) when help was requested.
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Option<Options>, String> {
+    let mut path = None;
+    let mut encoding = None;
+    let mut output = LogEncoding::Text;
+    let mut follow = false;
+    let mut filter = Filter::default();
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or(format!("{name} requires a value"));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-f" | "--follow" => follow = true,
+            "-e" | "--encoding" => {
+                let v = value(&arg)?;
+                encoding = Some(LogEncoding::from_name(&v).ok_or(format!("unknown encoding {v}"))?);
+            }
+            "-o" | "--output" => {
+                let v = value(&arg)?;
+                output = match LogEncoding::from_name(&v) {
+                    Some(enc @ (LogEncoding::Text | LogEncoding::Json)) => enc,
+                    _ => return Err(format!("unsupported output encoding {v}")),
+                };
+            }
+            "-l" | "--level" => {
+                let v = value(&arg)?;
+                filter.min_level =
+                    Some(LogLevel::from_name(&v).ok_or(format!("unknown level {v}"))?);
+            }
+            "-c" | "--component" => {
+                let v = value(&arg)?;
+                let id = v.parse().map_err(|_| format!("invalid component id {v}"))?;
+                filter.components.push(ComponentId(id));
+            }
+            "--since" => {
+                let v = value(&arg)?;
+                filter.since = Some(parse_met_arg(&v).ok_or(format!("invalid mission time {v}"))?);
+            }
+            "--until" => {
+                let v = value(&arg)?;
+                filter.until = Some(parse_met_arg(&v).ok_or(format!("invalid mission time {v}"))?);
+            }
+            "-g" | "--grep" => filter.pattern = Some(value(&arg)?),
+            _ if arg.starts_with('-') && arg != "-" => return Err(format!("unknown option {arg}")),
+            _ if path.is_none() => path = Some(arg),
+            _ => return Err(format!("unexpected argument {arg}")),
+        }
+    }
+
+    Ok(Some(Options {
+        path: path.ok_or("no log file given")?,
+        encoding,
+        output,
+        follow,
+        filter,
+    }))
+}
+
+/// Identity of a file on disk, changes when the file at a path is replaced, e.g. when a
+/// log is rotated by renaming it and creating a new one
+#[cfg(unix)]
+fn file_id(meta: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_meta: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Reads the log and prints matching records, following the file if requested
+fn run(opts: &Options) -> io::Result<()> {
+    let path = Path::new(&opts.path);
+    let file = File::open(path)?;
+    let mut opened_id = file_id(&file.metadata()?);
+    let mut reader = LogReader::new(file, opts.encoding);
+    let mut seen_len = 0;
+    let stdout = io::stdout();
+    let mut out = Vec::new();
+
+    loop {
+        match reader.next_record() {
+            Ok(Some(record)) => {
+                if opts.filter.matches(&record) {
+                    out.clear();
+                    encode_record(&record, opts.output, &mut out);
+                    stdout.lock().write_all(&out)?;
+                }
+                continue;
+            }
+            Ok(None) => {}
+            // The reader moved past the malformed record, carry on with the next one. Any
+            // other error, such as a file header that cannot be read, ends the run.
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                eprintln!("nomad-log: skipping {e}");
+                continue;
+            }
+            Err(e) => return Err(e),
+        }
+
+        if !opts.follow {
+            return Ok(());
+        }
+        stdout.lock().flush()?;
+        thread::sleep(FOLLOW_POLL_INTERVAL);
+
+        let meta = match std::fs::metadata(path) {
+            Ok(meta) => meta,
+            // Rotated away, wait for the new file to appear
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        // A different file at the path was rotated in, a file that shrank was truncated.
+        // Either way, start over from the beginning.
+        let rotated = file_id(&meta) != opened_id;
+        if rotated || meta.len() < seen_len {
+            let what = if rotated { "rotated" } else { "truncated" };
+            eprintln!("nomad-log: {} {what}, reopening", opts.path);
+            let file = File::open(path)?;
+            opened_id = file_id(&file.metadata()?);
+            reader = LogReader::new(file, opts.encoding);
+        }
+        seen_len = meta.len();
+    }
+}
+
+fn main() -> ExitCode {
+    let opts = match parse_args(env::args().skip(1)) {
+        Ok(Some(opts)) => opts,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("nomad-log: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(&opts) {
+        Ok(()) => ExitCode::SUCCESS,
+        // Output piped into e.g. head was closed, nothing more to do
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("nomad-log: {}: {e}", opts.path);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> impl Iterator<Item = String> {
+        s.split_whitespace()
+            .map(String::from)
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn record(level: LogLevel, component: u16, secs: i64, message: &str) -> LogRecord<String> {
+        LogRecord {
+            level,
+            component: ComponentId(component),
+            timestamp: MissionTime::from_nanos(secs * 1_000_000_000),
+            message: message.into(),
+        }
+    }
+
+    #[test]
+    /// A binary log of another version ends the run with an error instead of skipping
+    fn stops_on_unsupported_header() {
+        let path = env::temp_dir().join(format!("nomad-log-v1-{}.log", std::process::id()));
+        std::fs::write(&path, b"NMDLOG\x00\x01 old records").unwrap();
+        let opts = parse_args(args(path.to_str().unwrap())).unwrap().unwrap();
+
+        let err = run(&opts).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    /// Every filter option is parsed
+    fn parses_options() {
+        let opts = parse_args(args(
+            "-l warn -c 1 -c 2 --since 10.5 --until T+00:01:00.000000 -g imu -f -e json fsw.log",
+        ))
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(opts.path, "fsw.log");
+        assert_eq!(opts.encoding, Some(LogEncoding::Json));
+        assert!(opts.follow);
+        assert_eq!(opts.filter.min_level, Some(LogLevel::Warn));
+        assert_eq!(opts.filter.components, [ComponentId(1), ComponentId(2)]);
+        assert_eq!(
+            opts.filter.since,
+            Some(MissionTime::after_epoch(Duration::from_millis(10_500)))
+        );
+        assert_eq!(
+            opts.filter.until,
+            Some(MissionTime::after_epoch(Duration::from_secs(60)))
+        );
+        assert_eq!(
+            parse_met_arg("-10"),
+            Some(MissionTime::before_epoch(Duration::from_secs(10)))
+        );
+        assert_eq!(parse_met_arg("T-00:00:10.000000"), parse_met_arg("-10"));
+        assert_eq!(opts.filter.pattern.as_deref(), Some("imu"));
+
+        assert!(parse_args(args("--level loud fsw.log")).is_err());
+        assert!(parse_args(args("-l warn")).is_err());
+        assert!(parse_args(args("--help")).unwrap().is_none());
+    }
+
+    #[test]
+    /// Records must pass every filter
+    fn filters_records() {
+        let filter = Filter {
+            min_level: Some(LogLevel::Warn),
+            components: vec![ComponentId(1)],
+            since: Some(MissionTime::before_epoch(Duration::from_secs(10))),
+            until: Some(MissionTime::after_epoch(Duration::from_secs(20))),
+            pattern: Some("imu".into()),
+        };
+
+        assert!(filter.matches(&record(LogLevel::Warn, 1, 15, "imu stalled")));
+        assert!(!filter.matches(&record(LogLevel::Info, 1, 15, "imu stalled")));
+        assert!(!filter.matches(&record(LogLevel::Warn, 2, 15, "imu stalled")));
+        assert!(filter.matches(&record(LogLevel::Warn, 1, -5, "imu stalled")));
+        assert!(!filter.matches(&record(LogLevel::Warn, 1, -15, "imu stalled")));
+        assert!(!filter.matches(&record(LogLevel::Warn, 1, 25, "imu stalled")));
+        assert!(!filter.matches(&record(LogLevel::Warn, 1, 15, "gnss lost")));
+        assert!(Filter::default().matches(&record(LogLevel::Debug, 9, 0, "")));
+    }
+}
@@ -0,0 +1,63 @@
+//! Time sync between two processes
+//!
+//! Run a server and a client, e.g. on localhost:
+//!
+//! ```sh
+//! cargo run -p nomad-osal --example time_sync -- server 127.0.0.1:5770
+//! cargo run -p nomad-osal --example time_sync -- client 127.0.0.1:5770
+//! ```
+//!
+//! The client synchronises its TimeService to the server once per second and prints the
+//! offset, round trip delay and sync state.
+
+use core::time::Duration;
+use nomad_core::components::time::TimeService;
+use nomad_core::time::{TimeConfig, TimeMode};
+use nomad_osal::timesource::PosixTimeSource;
+use nomad_osal::timesync::{TimeSyncClient, TimeSyncServer};
+use std::{env, io, process, thread};
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().collect();
+    let (mode, addr) = match args.as_slice() {
+        [_, mode, addr] => (mode.as_str(), addr.as_str()),
+        _ => {
+            eprintln!("usage: time_sync <server|client> <addr:port>");
+            process::exit(2);
+        }
+    };
+    let clock = PosixTimeSource::new(&TimeConfig {
+        mode: TimeMode::Real,
+        mission_epoch: None,
+    });
+
+    match mode {
+        "server" => {
+            let server = TimeSyncServer::bind(addr)?;
+            println!("serving time on {}", server.local_addr()?);
+            loop {
+                server.serve(&clock, None)?;
+            }
+        }
+        "client" => {
+            let mut service = TimeService::new(clock);
+            let mut client = TimeSyncClient::connect(addr)?;
+            loop {
+                match client.sync(&mut service, Duration::from_secs(1)) {
+                    Ok(status) => println!(
+                        "offset {:>12} ns  rtt {:>10?}  {:?}",
+                        status.offset_ns.unwrap_or_default(),
+                        status.rtt,
+                        status.state
+                    ),
+                    Err(e) => println!("exchange failed: {e}"),
+                }
+                thread::sleep(Duration::from_secs(1));
+            }
+        }
+        _ => {
+            eprintln!("unknown mode {mode}, expected server or client");
+            process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,28 @@
+//! Nomad OSAL Errors
+//!
+//! Maps OS errors onto `OsalError`
+
+use nomad_core::osal::OsalError;
+use std::io;
+
+/// Maps an IO error onto the closest OSAL error
+pub(crate) fn io_error(e: io::Error) -> OsalError {
+    match e.kind() {
+        io::ErrorKind::NotFound => OsalError::NotFound,
+        io::ErrorKind::AlreadyExists | io::ErrorKind::AddrInUse => OsalError::AlreadyExists,
+        io::ErrorKind::PermissionDenied => OsalError::PermissionDenied,
+        io::ErrorKind::StorageFull => OsalError::NoSpace,
+        io::ErrorKind::WouldBlock => OsalError::WouldBlock,
+        io::ErrorKind::TimedOut => OsalError::Timeout,
+        io::ErrorKind::InvalidInput => OsalError::InvalidArgument,
+        io::ErrorKind::Unsupported => OsalError::Unsupported,
+        io::ErrorKind::OutOfMemory => OsalError::ResourceExhausted,
+        io::ErrorKind::ConnectionRefused => OsalError::ConnectionRefused,
+        io::ErrorKind::ConnectionReset
+        | io::ErrorKind::ConnectionAborted
+        | io::ErrorKind::BrokenPipe
+        | io::ErrorKind::NotConnected
+        | io::ErrorKind::UnexpectedEof => OsalError::Disconnected,
+        _ => OsalError::Os(e.raw_os_error().unwrap_or(libc::EIO)),
+    }
+}
@@ -0,0 +1,824 @@
+//! Nomad OSAL Filesystem
+//!
+//! Provides the OS implementations of the filesystem API, and a RAM disk for unit tests
+
+use nomad_core::osal::fs::path_components;
+use nomad_core::osal::{DirEntry, File, FileSystem, Metadata, OpenOptions, OsalError, SeekFrom};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// Normalised form of a virtual path, e.g. `/cf/logs` for `/cf//logs/`
+fn normalize(path: &str) -> Result<String, OsalError> {
+    let mut normalized = String::with_capacity(path.len());
+    for component in path_components(path)? {
+        normalized.push('/');
+        normalized.push_str(component);
+    }
+    if normalized.is_empty() {
+        normalized.push('/');
+    }
+    Ok(normalized)
+}
+
+/// Parent of a normalised path, None for the root
+fn parent(path: &str) -> Option<&str> {
+    match path.rfind('/') {
+        _ if path == "/" => None,
+        Some(0) => Some("/"),
+        Some(i) => Some(&path[..i]),
+        None => None,
+    }
+}
+
+/// Position after seeking from `pos` in a file of `len` bytes
+fn seek_position(pos: u64, len: u64, to: SeekFrom) -> Result<u64, OsalError> {
+    let (base, offset) = match to {
+        SeekFrom::Start(offset) => return Ok(offset),
+        SeekFrom::End(offset) => (len, offset),
+        SeekFrom::Current(offset) => (pos, offset),
+    };
+    base.checked_add_signed(offset)
+        .ok_or(OsalError::InvalidArgument)
+}
+
+#[cfg(feature = "posix")]
+mod posix_fs {
+    use super::*;
+    use crate::error::io_error;
+    use std::ffi::CString;
+    use std::io::{self, Read, Seek, Write};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+
+    /// POSIX filesystem. Virtual mount points such as `/cf` are mapped onto host
+    /// directories, paths outside of a mount point are not accessible. The root lists the
+    /// mount points.
+    ///
+    /// * `mounts`: Mount point names, without the leading `/`, and their host directories
+    #[derive(Clone, Debug, Default)]
+    pub struct PosixFileSystem {
+        mounts: Vec<(String, PathBuf)>,
+    }
+
+    impl PosixFileSystem {
+        /// Filesystem without mount points
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Filesystem with the standard mount points, `/cf` for persistent storage and
+        /// `/ram` for scratch data, mapped onto the `cf` and `ram` directories under `root`.
+        /// Creates the directories if needed.
+        pub fn with_default_mounts(root: impl AsRef<Path>) -> io::Result<Self> {
+            let mut fs = Self::new();
+            for name in ["cf", "ram"] {
+                let dir = root.as_ref().join(name);
+                std::fs::create_dir_all(&dir)?;
+                fs.mount(&format!("/{name}"), dir)
+                    .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+            }
+            Ok(fs)
+        }
+
+        /// Maps the mount point `mount_point`, e.g. `/ram`, onto the host directory
+        /// `host_dir`. On a target `/ram` would typically be a tmpfs such as `/dev/shm`.
+        pub fn mount(
+            &mut self,
+            mount_point: &str,
+            host_dir: impl Into<PathBuf>,
+        ) -> Result<(), OsalError> {
+            let mut components = path_components(mount_point)?;
+            let (Some(name), None) = (components.next(), components.next()) else {
+                return Err(OsalError::InvalidArgument);
+            };
+            if self.mounts.iter().any(|(n, _)| n == name) {
+                return Err(OsalError::AlreadyExists);
+            }
+            self.mounts.push((name.to_string(), host_dir.into()));
+            Ok(())
+        }
+
+        /// Mount points and their host directories
+        pub fn mounts(&self) -> impl Iterator<Item = (&str, &Path)> {
+            self.mounts.iter().map(|(n, d)| (n.as_str(), d.as_path()))
+        }
+
+        /// Host path of the virtual path `path`
+        pub fn host_path(&self, path: &str) -> Result<PathBuf, OsalError> {
+            self.resolve(path).map(|(_, host)| host)
+        }
+
+        /// Index of the mount point holding `path` and the host path. Fails with NotFound
+        /// outside of the mount points, including the root.
+        fn resolve(&self, path: &str) -> Result<(usize, PathBuf), OsalError> {
+            let mut components = path_components(path)?;
+            let name = components.next().ok_or(OsalError::NotFound)?;
+            let index = self
+                .mounts
+                .iter()
+                .position(|(n, _)| n == name)
+                .ok_or(OsalError::NotFound)?;
+            let mut host = self.mounts[index].1.clone();
+            host.extend(components);
+            Ok((index, host))
+        }
+
+        fn is_root(path: &str) -> Result<bool, OsalError> {
+            Ok(path_components(path)?.next().is_none())
+        }
+    }
+
+    /// A file of the POSIX filesystem
+    #[derive(Debug)]
+    pub struct PosixFile {
+        file: std::fs::File,
+    }
+
+    impl File for PosixFile {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, OsalError> {
+            self.file.read(buf).map_err(io_error)
+        }
+
+        fn write(&mut self, buf: &[u8]) -> Result<usize, OsalError> {
+            self.file.write(buf).map_err(io_error)
+        }
+
+        fn seek(&mut self, pos: SeekFrom) -> Result<u64, OsalError> {
+            let pos = match pos {
+                SeekFrom::Start(offset) => io::SeekFrom::Start(offset),
+                SeekFrom::End(offset) => io::SeekFrom::End(offset),
+                SeekFrom::Current(offset) => io::SeekFrom::Current(offset),
+            };
+            self.file.seek(pos).map_err(io_error)
+        }
+
+        fn flush(&mut self) -> Result<(), OsalError> {
+            self.file.sync_data().map_err(io_error)
+        }
+    }
+
+    impl FileSystem for PosixFileSystem {
+        type File = PosixFile;
+
+        fn open(&self, path: &str, options: OpenOptions) -> Result<PosixFile, OsalError> {
+            let (_, host) = self.resolve(path)?;
+            let file = std::fs::OpenOptions::new()
+                .read(options.read)
+                .write(options.write)
+                .create(options.create)
+                .truncate(options.truncate)
+                .append(options.append)
+                .open(host)
+                .map_err(io_error)?;
+            Ok(PosixFile { file })
+        }
+
+        fn remove(&self, path: &str) -> Result<(), OsalError> {
+            let (_, host) = self.resolve(path)?;
+            if std::fs::symlink_metadata(&host).map_err(io_error)?.is_dir() {
+                std::fs::remove_dir(host).map_err(io_error)
+            } else {
+                std::fs::remove_file(host).map_err(io_error)
+            }
+        }
+
+        fn rename(&self, from: &str, to: &str) -> Result<(), OsalError> {
+            let (from_mount, from) = self.resolve(from)?;
+            let (to_mount, to) = self.resolve(to)?;
+            if from_mount != to_mount {
+                return Err(OsalError::Unsupported);
+            }
+            std::fs::rename(from, to).map_err(io_error)
+        }
+
+        fn create_dir(&self, path: &str) -> Result<(), OsalError> {
+            let (_, host) = self.resolve(path)?;
+            std::fs::create_dir(host).map_err(io_error)
+        }
+
+        fn metadata(&self, path: &str) -> Result<Metadata, OsalError> {
+            if Self::is_root(path)? {
+                return Ok(Metadata {
+                    size: 0,
+                    is_dir: true,
+                });
+            }
+            let (_, host) = self.resolve(path)?;
+            let metadata = std::fs::metadata(host).map_err(io_error)?;
+            Ok(Metadata {
+                size: if metadata.is_dir() { 0 } else { metadata.len() },
+                is_dir: metadata.is_dir(),
+            })
+        }
+
+        fn list_dir(
+            &self,
+            path: &str,
+            visit: &mut dyn FnMut(DirEntry<'_>),
+        ) -> Result<(), OsalError> {
+            if Self::is_root(path)? {
+                for (name, _) in &self.mounts {
+                    visit(DirEntry {
+                        name,
+                        metadata: Metadata {
+                            size: 0,
+                            is_dir: true,
+                        },
+                    });
+                }
+                return Ok(());
+            }
+            let (_, host) = self.resolve(path)?;
+            for entry in std::fs::read_dir(host).map_err(io_error)? {
+                let entry = entry.map_err(io_error)?;
+                let metadata = entry.metadata().map_err(io_error)?;
+                let name = entry.file_name();
+                // Names that are not UTF-8 cannot be addressed through virtual paths
+                let Some(name) = name.to_str() else {
+                    continue;
+                };
+                visit(DirEntry {
+                    name,
+                    metadata: Metadata {
+                        size: if metadata.is_dir() { 0 } else { metadata.len() },
+                        is_dir: metadata.is_dir(),
+                    },
+                });
+            }
+            Ok(())
+        }
+
+        fn free_space(&self, path: &str) -> Result<u64, OsalError> {
+            let (_, host) = self.resolve(path)?;
+            let host = CString::new(host.as_os_str().as_bytes())
+                .map_err(|_| OsalError::InvalidArgument)?;
+            // SAFETY: statvfs writes into the zeroed plain data struct, the path is a valid
+            // NUL terminated string
+            unsafe {
+                let mut stat: libc::statvfs = core::mem::zeroed();
+                if libc::statvfs(host.as_ptr(), &mut stat) != 0 {
+                    return Err(io_error(io::Error::last_os_error()));
+                }
+                #[allow(clippy::unnecessary_cast)]
+                Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// Empty host directory for a test
+        fn host_dir(name: &str) -> PathBuf {
+            let dir = std::env::temp_dir().join(format!("nomad-fs-{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            dir
+        }
+
+        #[test]
+        /// Virtual paths under the mount points map onto the host directories
+        fn mount_points() {
+            let root = host_dir("mounts");
+            let fs = PosixFileSystem::with_default_mounts(&root).unwrap();
+            assert_eq!(
+                fs.host_path("/cf/logs/a.log").unwrap(),
+                root.join("cf/logs/a.log")
+            );
+            assert_eq!(fs.host_path("/etc/passwd").err(), Some(OsalError::NotFound));
+            assert_eq!(
+                fs.host_path("/cf/../../x").err(),
+                Some(OsalError::InvalidArgument)
+            );
+
+            fs.create_dir("/cf/logs").unwrap();
+            let mut file = fs.open("/cf/logs/a.log", OpenOptions::CREATE).unwrap();
+            file.write_all(b"hello world").unwrap();
+            file.flush().unwrap();
+            drop(file);
+            assert_eq!(
+                std::fs::read(root.join("cf/logs/a.log")).unwrap(),
+                b"hello world"
+            );
+
+            let mut file = fs.open("/cf/logs/a.log", OpenOptions::READ).unwrap();
+            assert_eq!(file.seek(SeekFrom::End(-5)).unwrap(), 6);
+            let mut buf = [0; 8];
+            assert_eq!(file.read(&mut buf).unwrap(), 5);
+            assert_eq!(&buf[..5], b"world");
+
+            // Renaming stays within a mount point
+            assert_eq!(
+                fs.rename("/cf/logs/a.log", "/ram/a.log"),
+                Err(OsalError::Unsupported)
+            );
+            fs.rename("/cf/logs/a.log", "/cf/logs/b.log").unwrap();
+
+            let mut names = Vec::new();
+            fs.list_dir("/", &mut |e| names.push(e.name.to_string()))
+                .unwrap();
+            assert_eq!(names, ["cf", "ram"]);
+            let mut entries = Vec::new();
+            fs.list_dir("/cf/logs", &mut |e| {
+                entries.push((e.name.to_string(), e.metadata))
+            })
+            .unwrap();
+            assert_eq!(
+                entries,
+                [(
+                    "b.log".to_string(),
+                    Metadata {
+                        size: 11,
+                        is_dir: false
+                    }
+                )]
+            );
+
+            assert!(fs.free_space("/cf").unwrap() > 0);
+            assert_eq!(
+                fs.open("/cf/logs/a.log", OpenOptions::READ).err(),
+                Some(OsalError::NotFound)
+            );
+            fs.remove("/cf/logs/b.log").unwrap();
+            fs.remove("/cf/logs").unwrap();
+            assert_eq!(fs.metadata("/cf/logs").err(), Some(OsalError::NotFound));
+            std::fs::remove_dir_all(root).unwrap();
+        }
+    }
+}
+
+#[cfg(feature = "posix")]
+pub use posix_fs::{PosixFile, PosixFileSystem};
+
+/// Mount points of the RAM disk, the directories at its root
+const RAM_MOUNT_POINTS: [&str; 2] = ["/cf", "/ram"];
+
+/// Mount point holding the normalised path `path`. Fails with NotFound outside of the
+/// mount points, like `PosixFileSystem`.
+fn mount_point(path: &str) -> Result<&'static str, OsalError> {
+    RAM_MOUNT_POINTS
+        .into_iter()
+        .find(|m| {
+            path.strip_prefix(m)
+                .is_some_and(|r| r.is_empty() || r.starts_with('/'))
+        })
+        .ok_or(OsalError::NotFound)
+}
+
+/// Entry of the RAM disk. Files refer to their data by ID, so open files follow renames.
+#[derive(Copy, Clone, Debug)]
+enum RamNode {
+    Dir,
+    File(u64),
+}
+
+/// Contents of the RAM disk
+///
+/// * `capacity`: Size of the disk in bytes
+/// * `used`: Bytes taken by file data
+/// * `nodes`: Entries by normalised path
+/// * `data`: File data by ID
+/// * `next_id`: ID of the next file created
+#[derive(Debug)]
+struct RamDisk {
+    capacity: u64,
+    used: u64,
+    nodes: BTreeMap<String, RamNode>,
+    data: BTreeMap<u64, Vec<u8>>,
+    next_id: u64,
+}
+
+impl RamDisk {
+    fn node(&self, path: &str) -> Result<RamNode, OsalError> {
+        self.nodes.get(path).copied().ok_or(OsalError::NotFound)
+    }
+
+    /// Checks that the parent of `path` is an existing directory. New entries at the root
+    /// are refused with NotFound, as on POSIX only the mount points exist there.
+    fn check_parent(&self, path: &str) -> Result<(), OsalError> {
+        match parent(path).map(|p| self.node(p)) {
+            _ if parent(path) == Some("/") => Err(OsalError::NotFound),
+            Some(Ok(RamNode::Dir)) => Ok(()),
+            Some(Ok(RamNode::File(_))) => Err(OsalError::InvalidArgument),
+            Some(Err(e)) => Err(e),
+            None => Err(OsalError::InvalidArgument),
+        }
+    }
+
+    fn metadata(&self, node: RamNode) -> Metadata {
+        match node {
+            RamNode::Dir => Metadata {
+                size: 0,
+                is_dir: true,
+            },
+            RamNode::File(id) => Metadata {
+                size: self.data.get(&id).map_or(0, |d| d.len() as u64),
+                is_dir: false,
+            },
+        }
+    }
+
+    /// Releases the data of a file
+    fn release(&mut self, node: RamNode) {
+        if let RamNode::File(id) = node
+            && let Some(data) = self.data.remove(&id)
+        {
+            self.used -= data.len() as u64;
+        }
+    }
+
+    /// Paths of the entries below `path`
+    fn descendants(&self, path: &str) -> Vec<String> {
+        let prefix = if path == "/" {
+            "/".to_string()
+        } else {
+            format!("{path}/")
+        };
+        self.nodes
+            .range(prefix.clone()..)
+            .take_while(|(p, _)| p.starts_with(&prefix))
+            .map(|(p, _)| p.clone())
+            .collect()
+    }
+}
+
+/// In-memory filesystem for unit tests, with a fixed capacity. Its mount points are the
+/// `/cf` and `/ram` directories, so tests use the same paths as flight. It refuses what
+/// `PosixFileSystem` refuses, new entries at the root and renames across mount points.
+/// Clones share the same disk.
+///
+/// Unlike POSIX, removing a file releases its data right away, open handles of the file
+/// fail with NotFound afterwards.
+#[derive(Clone, Debug)]
+pub struct RamFileSystem {
+    disk: Arc<Mutex<RamDisk>>,
+}
+
+impl RamFileSystem {
+    /// RAM disk holding up to `capacity` bytes of file data
+    pub fn new(capacity: u64) -> Self {
+        let nodes = core::iter::once("/")
+            .chain(RAM_MOUNT_POINTS)
+            .map(|p| (p.to_string(), RamNode::Dir))
+            .collect();
+        Self {
+            disk: Arc::new(Mutex::new(RamDisk {
+                capacity,
+                used: 0,
+                nodes,
+                data: BTreeMap::new(),
+                next_id: 0,
+            })),
+        }
+    }
+
+    /// Bytes taken by file data
+    pub fn used(&self) -> u64 {
+        lock(&self.disk).used
+    }
+}
+
+/// Locks the RAM disk, ignoring poisoning
+fn lock(disk: &Mutex<RamDisk>) -> std::sync::MutexGuard<'_, RamDisk> {
+    disk.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A file of the RAM disk
+///
+/// * `disk`: Disk holding the file
+/// * `id`: ID of the file data
+/// * `pos`: Current position
+/// * `options`: Options the file was opened with
+#[derive(Debug)]
+pub struct RamFile {
+    disk: Arc<Mutex<RamDisk>>,
+    id: u64,
+    pos: u64,
+    options: OpenOptions,
+}
+
+impl File for RamFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, OsalError> {
+        if !self.options.read {
+            return Err(OsalError::PermissionDenied);
+        }
+        let disk = lock(&self.disk);
+        let data = disk.data.get(&self.id).ok_or(OsalError::NotFound)?;
+        let start = usize::try_from(self.pos).map_or(data.len(), |p| p.min(data.len()));
+        let n = buf.len().min(data.len() - start);
+        buf[..n].copy_from_slice(&data[start..start + n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize, OsalError> {
+        if !self.options.write {
+            return Err(OsalError::PermissionDenied);
+        }
+        let mut disk = lock(&self.disk);
+        let free = disk.capacity - disk.used;
+        let len = disk.data.get(&self.id).ok_or(OsalError::NotFound)?.len() as u64;
+        if self.options.append {
+            self.pos = len;
+        }
+        // Writing past the end fills the gap with zeros, which takes space as well
+        let end = self
+            .pos
+            .checked_add(buf.len() as u64)
+            .ok_or(OsalError::InvalidArgument)?
+            .min(len + free);
+        if end <= self.pos {
+            return if buf.is_empty() {
+                Ok(0)
+            } else {
+                Err(OsalError::NoSpace)
+            };
+        }
+        let grown = end.saturating_sub(len);
+        let n = (end - self.pos) as usize;
+        let start = self.pos as usize;
+        let data = disk.data.get_mut(&self.id).ok_or(OsalError::NotFound)?;
+        if end > len {
+            data.resize(end as usize, 0);
+        }
+        data[start..start + n].copy_from_slice(&buf[..n]);
+        disk.used += grown;
+        self.pos = end;
+        Ok(n)
+    }
+
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, OsalError> {
+        let disk = lock(&self.disk);
+        let len = disk.data.get(&self.id).ok_or(OsalError::NotFound)?.len() as u64;
+        self.pos = seek_position(self.pos, len, pos)?;
+        Ok(self.pos)
+    }
+
+    fn flush(&mut self) -> Result<(), OsalError> {
+        Ok(())
+    }
+}
+
+impl FileSystem for RamFileSystem {
+    type File = RamFile;
+
+    fn open(&self, path: &str, options: OpenOptions) -> Result<RamFile, OsalError> {
+        if !options.read && !options.write {
+            return Err(OsalError::InvalidArgument);
+        }
+        let path = normalize(path)?;
+        let mut disk = lock(&self.disk);
+        let id = match disk.nodes.get(&path).copied() {
+            Some(RamNode::Dir) => return Err(OsalError::InvalidArgument),
+            Some(RamNode::File(id)) => {
+                if options.truncate && options.write {
+                    let data = disk.data.get_mut(&id).ok_or(OsalError::NotFound)?;
+                    let len = data.len() as u64;
+                    data.clear();
+                    disk.used -= len;
+                }
+                id
+            }
+            None if options.create && options.write => {
+                disk.check_parent(&path)?;
+                let id = disk.next_id;
+                disk.next_id += 1;
+                disk.nodes.insert(path, RamNode::File(id));
+                disk.data.insert(id, Vec::new());
+                id
+            }
+            None => return Err(OsalError::NotFound),
+        };
+        Ok(RamFile {
+            disk: self.disk.clone(),
+            id,
+            pos: 0,
+            options,
+        })
+    }
+
+    fn remove(&self, path: &str) -> Result<(), OsalError> {
+        let path = normalize(path)?;
+        let mut disk = lock(&self.disk);
+        let node = disk.node(&path)?;
+        if parent(&path) == Some("/") || path == "/" || !disk.descendants(&path).is_empty() {
+            return Err(OsalError::InvalidArgument);
+        }
+        disk.nodes.remove(&path);
+        disk.release(node);
+        Ok(())
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<(), OsalError> {
+        let (from, to) = (normalize(from)?, normalize(to)?);
+        let mut disk = lock(&self.disk);
+        let node = disk.node(&from)?;
+        if from == to {
+            return Ok(());
+        }
+        if mount_point(&from)? != mount_point(&to)? {
+            return Err(OsalError::Unsupported);
+        }
+        if parent(&from) == Some("/") || to.starts_with(&format!("{from}/")) {
+            return Err(OsalError::InvalidArgument);
+        }
+        disk.check_parent(&to)?;
+        match (node, disk.nodes.get(&to).copied()) {
+            (_, None) => {}
+            (RamNode::File(_), Some(replaced @ RamNode::File(_))) => disk.release(replaced),
+            (_, Some(_)) => return Err(OsalError::AlreadyExists),
+        }
+        for path in disk.descendants(&from) {
+            let moved = disk.nodes.remove(&path).ok_or(OsalError::NotFound)?;
+            disk.nodes
+                .insert(format!("{to}{}", &path[from.len()..]), moved);
+        }
+        disk.nodes.remove(&from);
+        disk.nodes.insert(to, node);
+        Ok(())
+    }
+
+    fn create_dir(&self, path: &str) -> Result<(), OsalError> {
+        let path = normalize(path)?;
+        let mut disk = lock(&self.disk);
+        if disk.nodes.contains_key(&path) {
+            return Err(OsalError::AlreadyExists);
+        }
+        disk.check_parent(&path)?;
+        disk.nodes.insert(path, RamNode::Dir);
+        Ok(())
+    }
+
+    fn metadata(&self, path: &str) -> Result<Metadata, OsalError> {
+        let path = normalize(path)?;
+        let disk = lock(&self.disk);
+        let node = disk.node(&path)?;
+        Ok(disk.metadata(node))
+    }
+
+    fn list_dir(&self, path: &str, visit: &mut dyn FnMut(DirEntry<'_>)) -> Result<(), OsalError> {
+        let path = normalize(path)?;
+        // Collected first, so `visit` may use the filesystem
+        let entries: Vec<(String, Metadata)> = {
+            let disk = lock(&self.disk);
+            if let RamNode::File(_) = disk.node(&path)? {
+                return Err(OsalError::InvalidArgument);
+            }
+            disk.descendants(&path)
+                .into_iter()
+                .filter(|p| parent(p) == Some(path.as_str()))
+                .map(|p| {
+                    let metadata = disk.metadata(disk.nodes[&p]);
+                    let name = p[p.rfind('/').map_or(0, |i| i + 1)..].to_string();
+                    (name, metadata)
+                })
+                .collect()
+        };
+        for (name, metadata) in &entries {
+            visit(DirEntry {
+                name,
+                metadata: *metadata,
+            });
+        }
+        Ok(())
+    }
+
+    fn free_space(&self, path: &str) -> Result<u64, OsalError> {
+        let path = normalize(path)?;
+        let disk = lock(&self.disk);
+        disk.node(&path)?;
+        Ok(disk.capacity - disk.used)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Names in the directory at `path`, sorted
+    fn names(fs: &impl FileSystem, path: &str) -> Vec<String> {
+        let mut names = Vec::new();
+        fs.list_dir(path, &mut |e| names.push(e.name.to_string()))
+            .unwrap();
+        names.sort();
+        names
+    }
+
+    #[test]
+    /// Files on the RAM disk can be written, read back, seeked, renamed and removed
+    fn ram_disk() {
+        let fs = RamFileSystem::new(1024);
+        assert_eq!(names(&fs, "/"), ["cf", "ram"]);
+        fs.create_dir("/cf/logs").unwrap();
+        assert_eq!(fs.create_dir("/cf/logs"), Err(OsalError::AlreadyExists));
+        assert_eq!(fs.create_dir("/cf/a/b"), Err(OsalError::NotFound));
+
+        let mut file = fs.open("/cf/logs/fsw.log", OpenOptions::CREATE).unwrap();
+        file.write_all(b"hello").unwrap();
+        let mut appended = fs.open("/cf/logs/fsw.log", OpenOptions::APPEND).unwrap();
+        appended.write_all(b" world").unwrap();
+        assert_eq!(file.read(&mut [0; 4]), Err(OsalError::PermissionDenied));
+        drop((file, appended));
+
+        let mut file = fs
+            .open("/cf/logs/fsw.log", OpenOptions::READ_WRITE)
+            .unwrap();
+        file.seek(SeekFrom::Start(6)).unwrap();
+        file.write_all(b"W").unwrap();
+        assert_eq!(file.seek(SeekFrom::Current(-7)).unwrap(), 0);
+        let mut buf = [0; 16];
+        assert_eq!(file.read(&mut buf).unwrap(), 11);
+        assert_eq!(&buf[..11], b"hello World");
+        assert_eq!(file.read(&mut buf).unwrap(), 0);
+        assert_eq!(
+            file.seek(SeekFrom::End(-12)),
+            Err(OsalError::InvalidArgument)
+        );
+
+        // Renaming stays within a mount point, open files follow renames
+        assert_eq!(
+            fs.rename("/cf/logs", "/ram/logs"),
+            Err(OsalError::Unsupported)
+        );
+        fs.rename("/cf/logs", "/cf/old").unwrap();
+        assert_eq!(names(&fs, "/cf"), ["old"]);
+        assert_eq!(names(&fs, "/cf/old"), ["fsw.log"]);
+        file.seek(SeekFrom::End(0)).unwrap();
+        file.write_all(b"!").unwrap();
+        assert_eq!(
+            fs.metadata("/cf/old/fsw.log").unwrap(),
+            Metadata {
+                size: 12,
+                is_dir: false
+            }
+        );
+        assert_eq!(
+            fs.rename("/cf/old", "/cf/old/x"),
+            Err(OsalError::InvalidArgument)
+        );
+        assert_eq!(fs.rename("/cf", "/cf/x"), Err(OsalError::InvalidArgument));
+        assert_eq!(
+            fs.open("/cf/logs/fsw.log", OpenOptions::READ).err(),
+            Some(OsalError::NotFound)
+        );
+
+        assert_eq!(fs.remove("/cf/old"), Err(OsalError::InvalidArgument));
+        fs.remove("/cf/old/fsw.log").unwrap();
+        assert_eq!(file.read(&mut buf), Err(OsalError::NotFound));
+        fs.remove("/cf/old").unwrap();
+        assert_eq!(fs.used(), 0);
+
+        // Only the mount points exist at the root, as on POSIX
+        assert_eq!(fs.create_dir("/x"), Err(OsalError::NotFound));
+        assert_eq!(
+            fs.open("/x", OpenOptions::CREATE).err(),
+            Some(OsalError::NotFound)
+        );
+        assert_eq!(fs.remove("/ram"), Err(OsalError::InvalidArgument));
+    }
+
+    #[test]
+    /// Writes stop when the RAM disk is full, removing files frees the space again
+    fn ram_disk_full() {
+        let fs = RamFileSystem::new(10);
+        let mut file = fs.open("/ram/a", OpenOptions::CREATE).unwrap();
+        assert_eq!(file.write(&[1; 8]).unwrap(), 8);
+        assert_eq!(fs.free_space("/ram").unwrap(), 2);
+        assert_eq!(file.write(&[2; 8]).unwrap(), 2);
+        assert_eq!(file.write_all(&[3]), Err(OsalError::NoSpace));
+
+        // Overwriting takes no additional space
+        file.seek(SeekFrom::Start(0)).unwrap();
+        file.write_all(&[4; 10]).unwrap();
+
+        let mut other = fs.open("/ram/b", OpenOptions::CREATE).unwrap();
+        assert_eq!(other.write_all(&[5]), Err(OsalError::NoSpace));
+        fs.open("/ram/a", OpenOptions::CREATE).unwrap();
+        assert_eq!(fs.free_space("/cf").unwrap(), 10);
+        other.seek(SeekFrom::Start(4)).unwrap();
+        other.write_all(&[6]).unwrap();
+        assert_eq!(fs.metadata("/ram/b").unwrap().size, 5);
+        fs.rename("/ram/b", "/ram/a").unwrap();
+        assert_eq!(names(&fs, "/ram"), ["a"]);
+        assert_eq!(fs.used(), 5);
+    }
+
+    #[test]
+    /// The zeros filling the gap of a write past the end count against the free space
+    fn ram_disk_write_past_end() {
+        let fs = RamFileSystem::new(10);
+        let mut file = fs.open("/ram/a", OpenOptions::CREATE).unwrap();
+        file.seek(SeekFrom::Start(5)).unwrap();
+        assert_eq!(file.write(&[1; 8]).unwrap(), 5);
+        assert_eq!(fs.used(), 10);
+        assert_eq!(fs.free_space("/ram").unwrap(), 0);
+        assert_eq!(file.write(&[2]), Err(OsalError::NoSpace));
+
+        file.seek(SeekFrom::Start(20)).unwrap();
+        assert_eq!(file.write(&[3]), Err(OsalError::NoSpace));
+        file.seek(SeekFrom::Start(u64::MAX)).unwrap();
+        assert_eq!(file.write(&[4]), Err(OsalError::InvalidArgument));
+        assert_eq!(fs.metadata("/ram/a").unwrap().size, 10);
+    }
+}
@@ -2,5 +2,21 @@
 //!
 //! Provides OS abstractions
 
+#[cfg(feature = "posix")]
+mod error;
+pub mod fs;
+#[cfg(feature = "log-bridge")]
+pub mod logbridge;
+pub mod logformat;
 pub mod logsinks;
+pub mod net;
+#[cfg(feature = "posix")]
+mod poll;
+pub mod queue;
+pub mod serial;
+pub mod signal;
+pub mod sync;
+pub mod task;
 pub mod timesource;
+#[cfg(feature = "time-sync")]
+pub mod timesync;
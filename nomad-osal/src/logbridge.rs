@@ -0,0 +1,213 @@
+//! Nomad OSAL Log Bridge
+//!
+//! Bridges the `log` crate facade into a Nomad Logger, so output of third-party crates
+//! (drivers, parsers) ends up in the same LogBuffer as the rest of the FSW.
+//!
+//! `tracing` can be routed through the bridge as well by enabling the `log` feature of
+//! the `tracing` crate, which emits tracing events as `log` records.
+
+use nomad_core::{FormattedLogHandle, LogLevel, component::ComponentId};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Maps a `log::Level` onto a Nomad LogLevel. Trace has no Nomad equivalent and is
+/// folded into Debug.
+pub fn map_level(level: log::Level) -> LogLevel {
+    match level {
+        log::Level::Error => LogLevel::Error,
+        log::Level::Warn => LogLevel::Warn,
+        log::Level::Info => LogLevel::Info,
+        log::Level::Debug | log::Level::Trace => LogLevel::Debug,
+    }
+}
+
+/// A `log::Log` implementation that forwards records into a Nomad Logger.
+///
+/// Records are forwarded through `FormattedLogHandle::log_formatted`, so formatted messages are
+/// copied into the Nomad LogRecord and truncated to `MESSAGE_CAPACITY` bytes, nothing is
+/// allocated per message.
+///
+/// * `logger`: Logger records are forwarded into
+/// * `targets`: Table mapping `log` targets (module paths) to ComponentId's
+/// * `default_component`: ComponentId used when no target matches
+pub struct LogBridge<L> {
+    logger: Arc<Mutex<L>>,
+    targets: &'static [(&'static str, ComponentId)],
+    default_component: ComponentId,
+}
+
+impl<L: FormattedLogHandle + Send + 'static> LogBridge<L> {
+    /// Creates a bridge forwarding into `logger`, every target maps to `default_component`
+    pub fn new(logger: Arc<Mutex<L>>, default_component: ComponentId) -> Self {
+        Self {
+            logger,
+            targets: &[],
+            default_component,
+        }
+    }
+
+    /// Sets the target table. A target matches an entry if it equals the entry or is a
+    /// submodule of it (`driver::imu` matches `driver`), the longest match wins.
+    pub fn with_targets(mut self, targets: &'static [(&'static str, ComponentId)]) -> Self {
+        self.targets = targets;
+        self
+    }
+
+    /// Installs the bridge as the global `log` logger. Can only succeed once per process.
+    pub fn install(self, max_level: log::LevelFilter) -> Result<(), log::SetLoggerError> {
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+
+    /// Resolves the ComponentId of a `log` target
+    pub fn component_for(&self, target: &str) -> ComponentId {
+        self.targets
+            .iter()
+            .filter(|(prefix, _)| {
+                target
+                    .strip_prefix(prefix)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, id)| *id)
+            .unwrap_or(self.default_component)
+    }
+}
+
+impl<L: FormattedLogHandle + Send + 'static> log::Log for LogBridge<L> {
+    fn enabled(&self, _metadata: &log::Metadata) -> bool {
+        // Filtering is left to log::set_max_level and the Logger itself
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        let component = self.component_for(record.target());
+        let level = map_level(record.level());
+
+        let mut logger = self.logger.lock().unwrap_or_else(PoisonError::into_inner);
+        logger.log_formatted(component, level, *record.args());
+    }
+
+    fn flush(&self) {
+        // Records are handed to the Logger immediately, draining is up to the Logger
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt;
+    use log::Log;
+    use nomad_core::{LogHandle, LogMessage, MESSAGE_CAPACITY};
+
+    /// Logger that records everything forwarded to it
+    #[derive(Default)]
+    struct Capture {
+        records: Vec<(ComponentId, LogLevel, LogMessage)>,
+    }
+
+    impl LogHandle for Capture {
+        fn log_message(&mut self, component: ComponentId, level: LogLevel, message: &'static str) {
+            self.records.push((component, level, message.into()));
+        }
+    }
+
+    impl FormattedLogHandle for Capture {
+        fn log_formatted(&mut self, component: ComponentId, level: LogLevel, args: fmt::Arguments) {
+            self.records
+                .push((component, level, LogMessage::format(args)));
+        }
+    }
+
+    const TARGETS: &[(&str, ComponentId)] = &[
+        ("imu_driver", ComponentId(1)),
+        ("imu_driver::calibration", ComponentId(2)),
+        ("gnss", ComponentId(3)),
+    ];
+
+    fn bridge() -> (Arc<Mutex<Capture>>, LogBridge<Capture>) {
+        let capture = Arc::new(Mutex::new(Capture::default()));
+        let bridge = LogBridge::new(capture.clone(), ComponentId(0)).with_targets(TARGETS);
+        (capture, bridge)
+    }
+
+    #[test]
+    /// Targets resolve to the longest matching module prefix
+    fn target_mapping() {
+        let (_, bridge) = bridge();
+
+        assert_eq!(bridge.component_for("imu_driver"), ComponentId(1));
+        assert_eq!(bridge.component_for("imu_driver::spi"), ComponentId(1));
+        assert_eq!(
+            bridge.component_for("imu_driver::calibration::bias"),
+            ComponentId(2)
+        );
+        assert_eq!(bridge.component_for("gnss_parser"), ComponentId(0));
+        assert_eq!(bridge.component_for("something_else"), ComponentId(0));
+    }
+
+    #[test]
+    /// Checks the log::Level to LogLevel mapping
+    fn level_mapping() {
+        assert_eq!(map_level(log::Level::Error), LogLevel::Error);
+        assert_eq!(map_level(log::Level::Warn), LogLevel::Warn);
+        assert_eq!(map_level(log::Level::Info), LogLevel::Info);
+        assert_eq!(map_level(log::Level::Debug), LogLevel::Debug);
+        assert_eq!(map_level(log::Level::Trace), LogLevel::Debug);
+    }
+
+    #[test]
+    /// Static and formatted messages are forwarded
+    fn forwards_records() {
+        let (capture, bridge) = bridge();
+        // Not a literal, so format_args! cannot fold it into a static string
+        let bytes = std::hint::black_box(14);
+
+        bridge.log(
+            &log::Record::builder()
+                .target("gnss")
+                .level(log::Level::Warn)
+                .args(format_args!("no fix"))
+                .build(),
+        );
+        bridge.log(
+            &log::Record::builder()
+                .target("imu_driver::spi")
+                .level(log::Level::Trace)
+                .args(format_args!("read {} bytes", bytes))
+                .build(),
+        );
+
+        let records = &capture.lock().unwrap().records;
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0],
+            (ComponentId(3), LogLevel::Warn, "no fix".into())
+        );
+        assert_eq!(
+            records[1],
+            (ComponentId(1), LogLevel::Debug, "read 14 bytes".into())
+        );
+    }
+
+    #[test]
+    /// Formatted messages longer than the record storage are truncated at a char boundary
+    fn truncates_long_messages() {
+        let (capture, bridge) = bridge();
+        let name = std::hint::black_box("é".repeat(MESSAGE_CAPACITY));
+
+        bridge.log(
+            &log::Record::builder()
+                .target("gnss")
+                .level(log::Level::Info)
+                .args(format_args!("a{}", name))
+                .build(),
+        );
+
+        let records = &capture.lock().unwrap().records;
+        let message = records[0].2;
+        // "a" and 31 two byte chars, the 32nd does not fit
+        assert_eq!(message.len(), MESSAGE_CAPACITY - 1);
+        assert!(message.starts_with("aé"));
+    }
+}
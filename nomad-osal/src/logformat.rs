@@ -0,0 +1,602 @@
+//! Nomad OSAL Log Formats
+//!
+//! Encoding and decoding of Nomad log files. Logs can be stored in three encodings:
+//!
+//! * Text: One human readable line per record, `T+00:00:12.345678 INFO comp=1: message`
+//! * JSON: One JSON object per line,
+//!   `{"met_ns":12345678000,"level":"INFO","component":1,"message":"message"}`
+//! * Binary: A file header followed by binary encoded LogRecords (see `LogRecord::encode`)
+
+use core::time::Duration;
+use nomad_core::{LogLevel, LogRecord, component::ComponentId, time::MissionTime};
+use std::io::{self, Read};
+
+/// Magic identifying a binary log file, followed by a big endian u16 format version
+pub const BINARY_FILE_MAGIC: &[u8; 6] = b"NMDLOG";
+
+/// Format version of binary log files. Version 2 stores signed timestamps.
+pub const BINARY_FILE_VERSION: u16 = 2;
+
+/// Magic and version at the start of a binary log file
+pub const BINARY_FILE_HEADER: &[u8; 8] = b"NMDLOG\x00\x02";
+
+/// Size of the chunks read from the underlying reader
+const READ_CHUNK: usize = 4096;
+
+/// Encoding of a log file
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LogEncoding {
+    Text,
+    Json,
+    Binary,
+}
+
+impl LogEncoding {
+    /// Parses an encoding from its name (`text`, `json` or `binary`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "text" | "txt" => Some(LogEncoding::Text),
+            "json" | "jsonl" => Some(LogEncoding::Json),
+            "binary" | "bin" => Some(LogEncoding::Binary),
+            _ => None,
+        }
+    }
+
+    /// Guesses the encoding from the first bytes of a log file
+    pub fn detect(prefix: &[u8]) -> Self {
+        if prefix.starts_with(BINARY_FILE_MAGIC) {
+            LogEncoding::Binary
+        } else if prefix.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{') {
+            LogEncoding::Json
+        } else {
+            LogEncoding::Text
+        }
+    }
+}
+
+/// Formats a mission elapsed time as `T+HH:MM:SS.ffffff`, or `T-HH:MM:SS.ffffff` before
+/// the mission epoch
+pub fn format_met(met: MissionTime) -> String {
+    met.to_string()
+}
+
+/// Parses a mission elapsed time formatted by `format_met`
+pub fn parse_met(s: &str) -> Option<MissionTime> {
+    let (before_epoch, rest) = match s.strip_prefix("T+") {
+        Some(rest) => (false, rest),
+        None => (true, s.strip_prefix("T-")?),
+    };
+    let (hms, micros) = rest.split_once('.')?;
+    let mut fields = hms.split(':').map(|f| f.parse::<u64>().ok());
+    let (hours, minutes, seconds) = (fields.next()??, fields.next()??, fields.next()??);
+    if fields.next().is_some() || minutes >= 60 || seconds >= 60 || micros.len() != 6 {
+        return None;
+    }
+    let micros: u32 = micros.parse().ok()?;
+
+    let magnitude = Duration::new(hours * 3600 + minutes * 60 + seconds, micros * 1000);
+    Some(if before_epoch {
+        MissionTime::before_epoch(magnitude)
+    } else {
+        MissionTime::after_epoch(magnitude)
+    })
+}
+
+/// Encodes a record in the given encoding and appends it to `out`.
+/// Text and JSON records are terminated by a newline.
+pub fn encode_record<M: AsRef<str>>(
+    record: &LogRecord<M>,
+    encoding: LogEncoding,
+    out: &mut Vec<u8>,
+) {
+    let message = record.message.as_ref();
+    match encoding {
+        LogEncoding::Text => {
+            let line = format!(
+                "{} {} comp={}: {}\n",
+                format_met(record.timestamp),
+                record.level.as_str(),
+                record.component.0,
+                escape_text(message)
+            );
+            out.extend_from_slice(line.as_bytes());
+        }
+        LogEncoding::Json => {
+            let line = format!(
+                "{{\"met_ns\":{},\"level\":\"{}\",\"component\":{},\"message\":\"{}\"}}\n",
+                record.timestamp.as_nanos(),
+                record.level.as_str(),
+                record.component.0,
+                escape_json(message)
+            );
+            out.extend_from_slice(line.as_bytes());
+        }
+        LogEncoding::Binary => {
+            let start = out.len();
+            out.resize(start + record.encoded_len(), 0);
+            // Cannot fail, the buffer was sized for the record
+            let _ = record.encode(&mut out[start..]);
+        }
+    }
+}
+
+/// Decodes a single text encoded line (without the newline)
+pub fn decode_text(line: &str) -> Option<LogRecord<String>> {
+    let (met, rest) = line.split_once(' ')?;
+    let (level, rest) = rest.split_once(' ')?;
+    let (component, message) = rest.strip_prefix("comp=")?.split_once(": ")?;
+
+    Some(LogRecord {
+        level: LogLevel::from_name(level)?,
+        component: ComponentId(component.parse().ok()?),
+        timestamp: parse_met(met)?,
+        message: unescape_text(message),
+    })
+}
+
+/// Decodes a single JSON encoded line (without the newline)
+pub fn decode_json(line: &str) -> Option<LogRecord<String>> {
+    let mut met_ns = None;
+    let mut level = None;
+    let mut component = None;
+    let mut message = None;
+
+    let mut parser = JsonObjectParser::new(line)?;
+    while let Some((key, value)) = parser.next_member()? {
+        match (key.as_str(), value) {
+            ("met_ns", JsonValue::Number(n)) => met_ns = Some(n),
+            ("level", JsonValue::String(s)) => level = LogLevel::from_name(&s),
+            ("component", JsonValue::Number(n)) => component = u16::try_from(n).ok(),
+            ("message", JsonValue::String(s)) => message = Some(s),
+            // Unknown members are ignored so the format can be extended
+            _ => {}
+        }
+    }
+
+    Some(LogRecord {
+        level: level?,
+        component: ComponentId(component?),
+        timestamp: MissionTime::from_nanos(met_ns?),
+        message: message?,
+    })
+}
+
+/// Incrementally decodes records from a log file.
+///
+/// The reader can be used on a file that is still being written: once it runs out of
+/// complete records it returns `Ok(None)`, and picks up from where it left off when called
+/// again after more data has been appended.
+///
+/// * `inner`: Underlying reader
+/// * `encoding`: Encoding of the log, detected from the first bytes when None
+/// * `buf`: Bytes read but not yet decoded
+/// * `header_checked`: Whether the binary file header has been consumed
+/// * `failed`: Whether the file header was rejected, nothing more is read then
+pub struct LogReader<R> {
+    inner: R,
+    encoding: Option<LogEncoding>,
+    buf: Vec<u8>,
+    header_checked: bool,
+    failed: bool,
+}
+
+impl<R: Read> LogReader<R> {
+    /// Creates a reader, detecting the encoding if `encoding` is None
+    pub fn new(inner: R, encoding: Option<LogEncoding>) -> Self {
+        Self {
+            inner,
+            encoding,
+            buf: Vec::new(),
+            header_checked: false,
+            failed: false,
+        }
+    }
+
+    /// Encoding of the log, None until it has been detected
+    pub fn encoding(&self) -> Option<LogEncoding> {
+        self.encoding
+    }
+
+    /// Returns the next record, or None if no complete record is available yet.
+    ///
+    /// Malformed records are consumed and reported as `InvalidData` errors, reading can
+    /// continue afterwards. A file header that cannot be read is reported as an
+    /// `Unsupported` error, the file cannot be decoded at all and every later call returns
+    /// None.
+    pub fn next_record(&mut self) -> io::Result<Option<LogRecord<String>>> {
+        loop {
+            if self.failed {
+                return Ok(None);
+            }
+            if let Some(result) = self.decode_buffered() {
+                return result.map(Some);
+            }
+
+            let mut chunk = [0u8; READ_CHUNK];
+            let n = self.inner.read(&mut chunk)?;
+            if n == 0 {
+                return Ok(None);
+            }
+            self.buf.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    /// Attempts to decode a record from the buffered bytes. Returns None if more
+    /// bytes are needed.
+    fn decode_buffered(&mut self) -> Option<io::Result<LogRecord<String>>> {
+        let encoding = match self.encoding {
+            Some(encoding) => encoding,
+            None => {
+                // Wait until enough bytes are available to recognise a binary header
+                if self.buf.len() < BINARY_FILE_HEADER.len() && !self.buf.contains(&b'\n') {
+                    return None;
+                }
+                let detected = LogEncoding::detect(&self.buf);
+                self.encoding = Some(detected);
+                detected
+            }
+        };
+
+        match encoding {
+            LogEncoding::Binary => self.decode_binary(),
+            LogEncoding::Text | LogEncoding::Json => loop {
+                let end = self.buf.iter().position(|&b| b == b'\n')?;
+                let line: Vec<u8> = self.buf.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                let line = line.trim_end_matches(['\n', '\r']);
+                // Blank lines carry no record
+                if line.is_empty() {
+                    continue;
+                }
+
+                let decoded = if encoding == LogEncoding::Text {
+                    decode_text(line)
+                } else {
+                    decode_json(line)
+                };
+                return Some(
+                    decoded.ok_or_else(|| invalid_data(format!("malformed record: {line}"))),
+                );
+            },
+        }
+    }
+
+    fn decode_binary(&mut self) -> Option<io::Result<LogRecord<String>>> {
+        if !self.header_checked {
+            if self.buf.len() < BINARY_FILE_HEADER.len() {
+                return None;
+            }
+            if !self.buf.starts_with(BINARY_FILE_MAGIC) {
+                // Without a header there is no telling where records start, give up on the file
+                return Some(Err(self.header_error("missing binary log header".into())));
+            }
+            let version = u16::from_be_bytes([self.buf[6], self.buf[7]]);
+            if version != BINARY_FILE_VERSION {
+                // Records of other versions are laid out differently, do not misread them
+                return Some(Err(
+                    self.header_error(format!("unsupported binary log version {version}"))
+                ));
+            }
+            self.buf.drain(..BINARY_FILE_HEADER.len());
+            self.header_checked = true;
+        }
+
+        let (record, used) = match LogRecord::decode(&self.buf) {
+            Ok((record, used)) => (
+                LogRecord {
+                    level: record.level,
+                    component: record.component,
+                    timestamp: record.timestamp,
+                    message: record.message.to_string(),
+                },
+                used,
+            ),
+            Err(nomad_core::RecordDecodeError::Truncated) => return None,
+            Err(e) => {
+                // Records are not resynchronisable, drop the rest of the buffer
+                self.buf.clear();
+                return Some(Err(invalid_data(format!("malformed record: {e:?}"))));
+            }
+        };
+        self.buf.drain(..used);
+        Some(Ok(record))
+    }
+}
+
+impl<R> LogReader<R> {
+    /// Rejects the file header, nothing more is read from the file
+    fn header_error(&mut self, msg: String) -> io::Error {
+        self.failed = true;
+        io::Error::new(io::ErrorKind::Unsupported, msg)
+    }
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Escapes backslashes and line breaks so a message fits on one line
+fn escape_text(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Reverses `escape_text`
+fn unescape_text(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Escapes a string for use inside a JSON string literal
+fn escape_json(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Values that can appear in a Nomad JSON log record
+enum JsonValue {
+    Number(i64),
+    String(String),
+}
+
+/// Minimal parser for the flat JSON objects Nomad writes. Supports string and
+/// integer members only.
+struct JsonObjectParser<'a> {
+    chars: core::iter::Peekable<core::str::Chars<'a>>,
+    first: bool,
+}
+
+impl<'a> JsonObjectParser<'a> {
+    fn new(s: &'a str) -> Option<Self> {
+        let mut chars = s.trim().chars().peekable();
+        if chars.next()? != '{' {
+            return None;
+        }
+        Some(Self { chars, first: true })
+    }
+
+    /// Returns the next member, Some(None) at the end of the object, None on a syntax error
+    fn next_member(&mut self) -> Option<Option<(String, JsonValue)>> {
+        self.skip_ws();
+        if self.chars.peek() == Some(&'}') {
+            self.chars.next();
+            return Some(None);
+        }
+        if !self.first {
+            if self.chars.next()? != ',' {
+                return None;
+            }
+            self.skip_ws();
+        }
+        self.first = false;
+
+        let key = self.parse_string()?;
+        self.skip_ws();
+        if self.chars.next()? != ':' {
+            return None;
+        }
+        self.skip_ws();
+
+        let value = match self.chars.peek()? {
+            '"' => JsonValue::String(self.parse_string()?),
+            c if c.is_ascii_digit() || *c == '-' => {
+                let mut digits = String::from(*c);
+                self.chars.next();
+                while let Some(c) = self.chars.peek().filter(|c| c.is_ascii_digit()) {
+                    digits.push(*c);
+                    self.chars.next();
+                }
+                JsonValue::Number(digits.parse().ok()?)
+            }
+            _ => return None,
+        };
+        Some(Some((key, value)))
+    }
+
+    fn skip_ws(&mut self) {
+        while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+            self.chars.next();
+        }
+    }
+
+    fn parse_string(&mut self) -> Option<String> {
+        if self.chars.next()? != '"' {
+            return None;
+        }
+        let mut out = String::new();
+        loop {
+            match self.chars.next()? {
+                '"' => return Some(out),
+                '\\' => match self.chars.next()? {
+                    'n' => out.push('\n'),
+                    'r' => out.push('\r'),
+                    't' => out.push('\t'),
+                    'b' => out.push('\u{8}'),
+                    'f' => out.push('\u{c}'),
+                    'u' => {
+                        let hex: String = (0..4).filter_map(|_| self.chars.next()).collect();
+                        out.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                    }
+                    c => out.push(c),
+                },
+                c => out.push(c),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(message: &'static str) -> LogRecord {
+        LogRecord {
+            level: LogLevel::Warn,
+            component: ComponentId(42),
+            timestamp: MissionTime::after_epoch(Duration::new(3723, 456_789_000)),
+            message: message.into(),
+        }
+    }
+
+    /// Encodes records and reads them back through a LogReader
+    fn roundtrip(encoding: LogEncoding, detect: bool) {
+        let records = [
+            record("IMU using default calibration"),
+            record("line\nbreak \"quoted\" \\"),
+            LogRecord {
+                timestamp: MissionTime::before_epoch(Duration::from_secs(10)),
+                ..record("countdown")
+            },
+        ];
+
+        let mut file = Vec::new();
+        if encoding == LogEncoding::Binary {
+            file.extend_from_slice(BINARY_FILE_HEADER);
+        }
+        for rec in &records {
+            encode_record(rec, encoding, &mut file);
+        }
+
+        let mut reader = LogReader::new(file.as_slice(), (!detect).then_some(encoding));
+        for rec in &records {
+            let decoded = reader.next_record().unwrap().unwrap();
+            assert_eq!(decoded.level, rec.level);
+            assert_eq!(decoded.component, rec.component);
+            assert_eq!(decoded.message, rec.message.as_str());
+            // The stamps are whole microseconds, the precision the text encoding keeps
+            assert_eq!(decoded.timestamp, rec.timestamp);
+        }
+        assert!(reader.next_record().unwrap().is_none());
+        assert_eq!(reader.encoding(), Some(encoding));
+    }
+
+    #[test]
+    fn text_roundtrip() {
+        roundtrip(LogEncoding::Text, false);
+        roundtrip(LogEncoding::Text, true);
+    }
+
+    #[test]
+    fn json_roundtrip() {
+        roundtrip(LogEncoding::Json, false);
+        roundtrip(LogEncoding::Json, true);
+    }
+
+    #[test]
+    fn binary_roundtrip() {
+        roundtrip(LogEncoding::Binary, false);
+        roundtrip(LogEncoding::Binary, true);
+    }
+
+    #[test]
+    /// Mission elapsed time is formatted and parsed symmetrically
+    fn met_format() {
+        let met = MissionTime::after_epoch(Duration::new(3723, 456_789_000));
+        assert_eq!(format_met(met), "T+01:02:03.456789");
+        assert_eq!(parse_met("T+01:02:03.456789"), Some(met));
+
+        let countdown = MissionTime::before_epoch(Duration::from_secs(10));
+        assert_eq!(format_met(countdown), "T-00:00:10.000000");
+        assert_eq!(parse_met("T-00:00:10.000000"), Some(countdown));
+        assert_eq!(parse_met("T+01:62:03.456789"), None);
+        assert_eq!(parse_met("01:02:03.456789"), None);
+    }
+
+    #[test]
+    /// A partially written record is returned once the rest of it arrives
+    fn partial_records() {
+        let mut encoded = Vec::new();
+        encode_record(&record("partial"), LogEncoding::Text, &mut encoded);
+        let (first, second) = encoded.split_at(10);
+
+        let mut reader = LogReader::new(io::Cursor::new(first.to_vec()), Some(LogEncoding::Text));
+        assert!(reader.next_record().unwrap().is_none());
+
+        reader.inner.get_mut().extend_from_slice(second);
+        assert_eq!(reader.next_record().unwrap().unwrap().message, "partial");
+    }
+
+    #[test]
+    /// Malformed lines are reported without stopping the reader
+    fn malformed_line() {
+        let mut file = b"garbage\n".to_vec();
+        encode_record(&record("valid"), LogEncoding::Text, &mut file);
+
+        let mut reader = LogReader::new(file.as_slice(), Some(LogEncoding::Text));
+        assert_eq!(
+            reader.next_record().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        assert_eq!(reader.next_record().unwrap().unwrap().message, "valid");
+    }
+
+    #[test]
+    /// Reading a file without the binary header as binary fails once and stops, any number
+    /// of blank lines is skipped
+    fn missing_binary_header() {
+        let mut file = Vec::new();
+        encode_record(&record("text"), LogEncoding::Text, &mut file);
+        let mut reader = LogReader::new(file.as_slice(), Some(LogEncoding::Binary));
+        assert_eq!(
+            reader.next_record().unwrap_err().kind(),
+            io::ErrorKind::Unsupported
+        );
+        assert!(reader.next_record().unwrap().is_none());
+
+        let mut file = vec![b'\n'; 1_000_000];
+        encode_record(&record("after blanks"), LogEncoding::Text, &mut file);
+        let mut reader = LogReader::new(file.as_slice(), Some(LogEncoding::Text));
+        assert_eq!(
+            reader.next_record().unwrap().unwrap().message,
+            "after blanks"
+        );
+    }
+
+    #[test]
+    /// Binary files of other format versions are recognised but not decoded
+    fn unsupported_binary_version() {
+        let mut file = b"NMDLOG\x00\x01".to_vec();
+        encode_record(&record("old"), LogEncoding::Binary, &mut file);
+
+        let mut reader = LogReader::new(file.as_slice(), None);
+        assert_eq!(
+            reader.next_record().unwrap_err().kind(),
+            io::ErrorKind::Unsupported
+        );
+        assert!(reader.next_record().unwrap().is_none());
+        assert_eq!(reader.encoding(), Some(LogEncoding::Binary));
+        assert_eq!(BINARY_FILE_HEADER[6..], BINARY_FILE_VERSION.to_be_bytes());
+    }
+}
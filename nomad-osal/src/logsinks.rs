@@ -9,6 +9,7 @@ use std::io::{self, Write};
 /// Implement a LogSink for stdout on POSIX platforms
 mod stdout_sink {
     use super::*;
+    use crate::logformat::format_met;
 
     pub struct StdoutLogSink;
 
@@ -45,8 +46,11 @@ mod stdout_sink {
         fn write(&mut self, record: &LogRecord) {
             let level_str = self.format_level(record.level);
             let output = format!(
-                "[{}] comp={}: {}",
-                level_str, record.component.0, record.message
+                "[{}] {} comp={}: {}",
+                level_str,
+                format_met(record.timestamp),
+                record.component.0,
+                record.message
             );
 
             match record.level {
@@ -68,6 +72,414 @@ mod stdout_sink {
     }
 }
 
+#[cfg(feature = "udp-log")]
+/// Implement a network LogSink that sends records as UDP datagrams on POSIX platforms
+mod udp_sink {
+    use super::*;
+    use crate::logformat::format_met;
+    use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    /// Private Enterprise Number reserved for documentation (RFC 5612), used as the
+    /// SD-ID suffix of the structured data Nomad attaches to syslog messages
+    const NOMAD_SD_ID: &str = "nomad@32473";
+
+    /// Version byte of the compact datagram format. Version 2 carries signed timestamps.
+    pub const COMPACT_VERSION: u8 = 2;
+
+    /// Wire format used by UdpLogSink
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub enum UdpLogFormat {
+        /// RFC 5424 syslog message, suitable for rsyslog/syslog-ng or any syslog collector
+        ///
+        /// * `facility`: Syslog facility code (0-23), e.g. 16 for local0
+        /// * `hostname`: HOSTNAME field, identifies the flight computer
+        /// * `app_name`: APP-NAME field, identifies the FSW
+        Syslog {
+            facility: u8,
+            hostname: String,
+            app_name: String,
+        },
+        /// Compact binary datagram, a version byte followed by the binary encoded
+        /// LogRecord (see `LogRecord::encode`)
+        Compact,
+    }
+
+    /// Maps a LogLevel onto its RFC 5424 severity
+    pub fn syslog_severity(level: LogLevel) -> u8 {
+        match level {
+            LogLevel::Error => 3, // Error: error conditions
+            LogLevel::Warn => 4,  // Warning: warning conditions
+            LogLevel::Info => 6,  // Informational: informational messages
+            LogLevel::Debug => 7, // Debug: debug-level messages
+        }
+    }
+
+    /// LogSink that sends every record to a remote host over UDP.
+    ///
+    /// The socket is non-blocking, a failed or would-block send drops the record
+    /// rather than stalling the caller. Dropped records are counted.
+    ///
+    /// * `socket`: Local socket records are sent from
+    /// * `dest`: Address of the remote listener
+    /// * `format`: Wire format of each datagram
+    /// * `dropped`: Number of records that failed to send
+    pub struct UdpLogSink {
+        socket: UdpSocket,
+        dest: SocketAddr,
+        format: UdpLogFormat,
+        dropped: u64,
+    }
+
+    impl UdpLogSink {
+        /// Creates a sink sending to `dest`, binding an ephemeral local port
+        pub fn new<A: ToSocketAddrs>(dest: A, format: UdpLogFormat) -> io::Result<Self> {
+            let dest = dest.to_socket_addrs()?.next().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "no destination address")
+            })?;
+
+            // Bind to the wildcard address of the same family as the destination
+            let local: SocketAddr = if dest.is_ipv4() {
+                "0.0.0.0:0".parse().unwrap()
+            } else {
+                "[::]:0".parse().unwrap()
+            };
+            let socket = UdpSocket::bind(local)?;
+            socket.set_nonblocking(true)?;
+
+            Ok(Self {
+                socket,
+                dest,
+                format,
+                dropped: 0,
+            })
+        }
+
+        /// Number of records that could not be sent
+        pub fn dropped(&self) -> u64 {
+            self.dropped
+        }
+
+        /// Encodes a record into a datagram according to the configured format
+        fn encode(&self, record: &LogRecord) -> Vec<u8> {
+            match &self.format {
+                UdpLogFormat::Syslog {
+                    facility,
+                    hostname,
+                    app_name,
+                } => {
+                    let pri = u16::from(*facility) * 8 + u16::from(syslog_severity(record.level));
+                    format!(
+                        "<{}>1 {} {} {} {} - [{} comp=\"{}\" met=\"{}\"] {}",
+                        pri,
+                        format_rfc3339(SystemTime::now()),
+                        nil_or(hostname),
+                        nil_or(app_name),
+                        std::process::id(),
+                        NOMAD_SD_ID,
+                        record.component.0,
+                        format_met(record.timestamp),
+                        record.message
+                    )
+                    .into_bytes()
+                }
+                UdpLogFormat::Compact => {
+                    let mut out = vec![0u8; 1 + record.encoded_len()];
+                    out[0] = COMPACT_VERSION;
+                    // Cannot fail, the buffer was sized for the record
+                    let _ = record.encode(&mut out[1..]);
+                    out
+                }
+            }
+        }
+    }
+
+    impl LogSink for UdpLogSink {
+        /// Sends the record as a single datagram, never blocks
+        fn write(&mut self, record: &LogRecord) {
+            let datagram = self.encode(record);
+            if self.socket.send_to(&datagram, self.dest).is_err() {
+                self.dropped += 1;
+            }
+        }
+    }
+
+    /// Syslog header fields use "-" (NILVALUE) when empty
+    fn nil_or(field: &str) -> &str {
+        if field.is_empty() { "-" } else { field }
+    }
+
+    /// Formats a system time as a RFC 3339 UTC timestamp with microsecond precision
+    fn format_rfc3339(t: SystemTime) -> String {
+        let since_epoch = t.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let secs = since_epoch.as_secs();
+        let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+        let sod = secs % 86_400;
+
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+            year,
+            month,
+            day,
+            sod / 3600,
+            (sod / 60) % 60,
+            sod % 60,
+            since_epoch.subsec_micros()
+        )
+    }
+
+    /// Converts days since 1970-01-01 into a (year, month, day) civil date
+    ///
+    /// Based on Howard Hinnant's `civil_from_days` algorithm
+    fn civil_from_days(days: i64) -> (i64, u32, u32) {
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        (year, month, day)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use nomad_core::{component::ComponentId, time::MissionTime};
+        use std::time::Duration;
+
+        /// Binds a local listener for the sink to send to
+        fn listener() -> UdpSocket {
+            let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+            sock.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+            sock
+        }
+
+        fn record(level: LogLevel, message: &'static str) -> LogRecord {
+            LogRecord {
+                level,
+                component: ComponentId(7),
+                timestamp: MissionTime::after_epoch(Duration::from_secs(5)),
+                message: message.into(),
+            }
+        }
+
+        #[test]
+        /// Checks the LogLevel to syslog severity mapping
+        fn severity_mapping() {
+            assert_eq!(syslog_severity(LogLevel::Error), 3);
+            assert_eq!(syslog_severity(LogLevel::Warn), 4);
+            assert_eq!(syslog_severity(LogLevel::Info), 6);
+            assert_eq!(syslog_severity(LogLevel::Debug), 7);
+        }
+
+        #[test]
+        /// Known dates convert correctly, including leap days
+        fn civil_dates() {
+            assert_eq!(civil_from_days(0), (1970, 1, 1));
+            assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+            assert_eq!(civil_from_days(19_723), (2024, 1, 1));
+        }
+
+        #[test]
+        /// Sends a RFC 5424 message to a local listener
+        fn sends_syslog() {
+            let rx = listener();
+            let mut sink = UdpLogSink::new(
+                rx.local_addr().unwrap(),
+                UdpLogFormat::Syslog {
+                    facility: 16,
+                    hostname: "laika".into(),
+                    app_name: "nomad-fsw".into(),
+                },
+            )
+            .unwrap();
+
+            sink.write(&record(LogLevel::Warn, "IMU using default calibration"));
+
+            let mut buf = [0u8; 512];
+            let n = rx.recv(&mut buf).unwrap();
+            let msg = std::str::from_utf8(&buf[..n]).unwrap();
+
+            // local0 (16) * 8 + warning (4)
+            assert!(msg.starts_with("<132>1 "));
+            assert!(msg.contains(" laika nomad-fsw "));
+            assert!(msg.contains("[nomad@32473 comp=\"7\" met=\"T+00:00:05.000000\"]"));
+            assert!(msg.ends_with(" IMU using default calibration"));
+            assert_eq!(sink.dropped(), 0);
+        }
+
+        #[test]
+        /// Sends a compact datagram to a local listener
+        fn sends_compact() {
+            let rx = listener();
+            let mut sink =
+                UdpLogSink::new(rx.local_addr().unwrap(), UdpLogFormat::Compact).unwrap();
+
+            sink.write(&record(LogLevel::Error, "nav error"));
+
+            let mut buf = [0u8; 64];
+            let n = rx.recv(&mut buf).unwrap();
+            assert_eq!(buf[0], COMPACT_VERSION);
+
+            let (decoded, used) = LogRecord::decode(&buf[1..n]).unwrap();
+            assert_eq!(used, n - 1);
+            let expected = record(LogLevel::Error, "nav error");
+            assert_eq!(
+                (decoded.level, decoded.component, decoded.timestamp),
+                (expected.level, expected.component, expected.timestamp)
+            );
+            assert_eq!(decoded.message, expected.message.as_str());
+        }
+    }
+}
+
+#[cfg(feature = "udp-log")]
+pub use udp_sink::{UdpLogFormat, UdpLogSink, syslog_severity};
+
+#[cfg(feature = "file-log")]
+/// Implement a LogSink that writes records to a file on POSIX platforms
+mod file_sink {
+    use super::*;
+    use crate::logformat::{BINARY_FILE_HEADER, LogEncoding, encode_record};
+    use std::fs::{File, OpenOptions};
+    use std::io::Read;
+    use std::path::Path;
+
+    /// LogSink that appends records to a log file in text, JSON or binary encoding.
+    ///
+    /// Each record is written with a single write call so a crash leaves at most one
+    /// partial record at the end of the file. Files can be read back with `nomad-log`.
+    ///
+    /// * `file`: Log file, opened for appending
+    /// * `encoding`: Encoding records are written in
+    /// * `failed`: Number of records that could not be written
+    pub struct FileLogSink {
+        file: File,
+        encoding: LogEncoding,
+        failed: u64,
+    }
+
+    impl FileLogSink {
+        /// Opens `path` for appending, creating it if it does not exist. A new binary log
+        /// file starts with the binary file header, an existing one must have the header of
+        /// the current format version.
+        pub fn open<P: AsRef<Path>>(path: P, encoding: LogEncoding) -> io::Result<Self> {
+            let mut file = OpenOptions::new()
+                .create(true)
+                .read(true)
+                .append(true)
+                .open(path)?;
+            if encoding == LogEncoding::Binary {
+                if file.metadata()?.len() == 0 {
+                    file.write_all(BINARY_FILE_HEADER)?;
+                } else {
+                    let mut header = [0u8; BINARY_FILE_HEADER.len()];
+                    file.read_exact(&mut header)?;
+                    if &header != BINARY_FILE_HEADER {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            "not a binary log file of the current version",
+                        ));
+                    }
+                }
+            }
+
+            Ok(Self {
+                file,
+                encoding,
+                failed: 0,
+            })
+        }
+
+        /// Number of records that could not be written
+        pub fn failed(&self) -> u64 {
+            self.failed
+        }
+    }
+
+    impl LogSink for FileLogSink {
+        /// Appends the record to the log file
+        fn write(&mut self, record: &LogRecord) {
+            let mut encoded = Vec::with_capacity(64 + record.message.len());
+            encode_record(record, self.encoding, &mut encoded);
+            if self.file.write_all(&encoded).is_err() {
+                self.failed += 1;
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::logformat::LogReader;
+        use nomad_core::{component::ComponentId, time::MissionTime};
+        use std::time::Duration;
+
+        #[test]
+        /// Writes records in every encoding, reopens the file and reads them back
+        fn write_and_read_back() {
+            for (encoding, ext) in [
+                (LogEncoding::Text, "log"),
+                (LogEncoding::Json, "jsonl"),
+                (LogEncoding::Binary, "bin"),
+            ] {
+                let path = std::env::temp_dir().join(format!(
+                    "nomad-file-sink-{}.{}",
+                    std::process::id(),
+                    ext
+                ));
+                let _ = std::fs::remove_file(&path);
+
+                // Writing twice exercises appending to an existing file
+                for message in ["first", "second"] {
+                    let mut sink = FileLogSink::open(&path, encoding).unwrap();
+                    sink.write(&LogRecord {
+                        level: LogLevel::Info,
+                        component: ComponentId(3),
+                        timestamp: MissionTime::after_epoch(Duration::from_millis(1500)),
+                        message: message.into(),
+                    });
+                    assert_eq!(sink.failed(), 0);
+                }
+
+                let mut reader = LogReader::new(File::open(&path).unwrap(), None);
+                for message in ["first", "second"] {
+                    let rec = reader.next_record().unwrap().unwrap();
+                    assert_eq!(rec.message, message);
+                    assert_eq!(
+                        rec.timestamp,
+                        MissionTime::after_epoch(Duration::from_millis(1500))
+                    );
+                }
+                assert!(reader.next_record().unwrap().is_none());
+                assert_eq!(reader.encoding(), Some(encoding));
+
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+
+        #[test]
+        /// Binary records are not appended to a file of another format version
+        fn rejects_other_binary_versions() {
+            let path =
+                std::env::temp_dir().join(format!("nomad-file-sink-{}.v1", std::process::id()));
+            std::fs::write(&path, b"NMDLOG\x00\x01").unwrap();
+
+            let err = FileLogSink::open(&path, LogEncoding::Binary).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert_eq!(std::fs::read(&path).unwrap(), b"NMDLOG\x00\x01");
+
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+}
+
+#[cfg(feature = "file-log")]
+pub use file_sink::FileLogSink;
+
 // TODO: Have a better way of handling default sinks
 // It might be better to remove this and instead let the Logger
 // such as LogService handle default LogSinks. OSAL shouldnt dicate
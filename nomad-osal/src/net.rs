@@ -0,0 +1,311 @@
+//! Nomad OSAL Network Sockets
+//!
+//! Provides the OS implementations of the socket API
+
+#[cfg(feature = "posix")]
+mod posix_net {
+    use crate::error::io_error;
+    use crate::poll::poll_readable;
+    use crate::sync::lock;
+    use nomad_core::osal::{IoMode, NetworkApi, OsalError, TcpListener, TcpStream, UdpSocket};
+    use std::io::{self, Read, Write};
+    use std::net::{self, SocketAddr};
+    use std::os::fd::AsRawFd;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    /// Timeout handed to the OS. A zero timeout is rejected by the OS, it is treated as the
+    /// shortest possible wait.
+    fn os_timeout(timeout: Duration) -> Duration {
+        timeout.max(Duration::from_micros(1))
+    }
+
+    /// Maps an error of a call made in `mode`. The OS reports an expired timeout as
+    /// WouldBlock, which is a timeout unless the socket is non-blocking.
+    fn mode_error(e: io::Error, mode: IoMode) -> OsalError {
+        match (io_error(e), mode) {
+            (OsalError::WouldBlock, IoMode::Timeout(_)) => OsalError::Timeout,
+            (e, _) => e,
+        }
+    }
+
+    /// Applies `mode` to a std socket through its setters
+    fn apply_mode(
+        mode: IoMode,
+        set_nonblocking: impl FnOnce(bool) -> io::Result<()>,
+        set_timeouts: impl Fn(Option<Duration>) -> io::Result<()>,
+    ) -> Result<(), OsalError> {
+        let (nonblocking, timeout) = match mode {
+            IoMode::Blocking => (false, None),
+            IoMode::NonBlocking => (true, None),
+            IoMode::Timeout(timeout) => (false, Some(os_timeout(timeout))),
+        };
+        set_nonblocking(nonblocking).map_err(io_error)?;
+        set_timeouts(timeout).map_err(io_error)
+    }
+
+    /// POSIX network stack, sockets are std sockets
+    #[derive(Copy, Clone, Debug, Default)]
+    pub struct PosixNetwork;
+
+    impl PosixNetwork {
+        pub fn new() -> Self {
+            Self
+        }
+    }
+
+    /// A UDP socket on the POSIX network stack
+    ///
+    /// * `socket`: The std socket
+    /// * `mode`: How calls wait
+    #[derive(Debug)]
+    pub struct PosixUdpSocket {
+        socket: net::UdpSocket,
+        mode: Mutex<IoMode>,
+    }
+
+    impl UdpSocket for PosixUdpSocket {
+        fn local_addr(&self) -> Result<SocketAddr, OsalError> {
+            self.socket.local_addr().map_err(io_error)
+        }
+
+        fn set_mode(&self, mode: IoMode) -> Result<(), OsalError> {
+            let mut current = lock(&self.mode);
+            apply_mode(
+                mode,
+                |nonblocking| self.socket.set_nonblocking(nonblocking),
+                |timeout| {
+                    self.socket.set_read_timeout(timeout)?;
+                    self.socket.set_write_timeout(timeout)
+                },
+            )?;
+            *current = mode;
+            Ok(())
+        }
+
+        fn send_to(&self, buf: &[u8], addr: SocketAddr) -> Result<usize, OsalError> {
+            self.socket
+                .send_to(buf, addr)
+                .map_err(|e| mode_error(e, *lock(&self.mode)))
+        }
+
+        fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr), OsalError> {
+            self.socket
+                .recv_from(buf)
+                .map_err(|e| mode_error(e, *lock(&self.mode)))
+        }
+    }
+
+    /// A TCP stream on the POSIX network stack
+    ///
+    /// * `stream`: The std stream
+    /// * `mode`: How calls wait
+    #[derive(Debug)]
+    pub struct PosixTcpStream {
+        stream: net::TcpStream,
+        mode: Mutex<IoMode>,
+    }
+
+    impl PosixTcpStream {
+        fn new(stream: net::TcpStream) -> Result<Self, OsalError> {
+            let stream = Self {
+                stream,
+                mode: Mutex::new(IoMode::Blocking),
+            };
+            stream.set_mode(IoMode::Blocking)?;
+            Ok(stream)
+        }
+    }
+
+    impl TcpStream for PosixTcpStream {
+        fn local_addr(&self) -> Result<SocketAddr, OsalError> {
+            self.stream.local_addr().map_err(io_error)
+        }
+
+        fn peer_addr(&self) -> Result<SocketAddr, OsalError> {
+            self.stream.peer_addr().map_err(io_error)
+        }
+
+        fn set_mode(&self, mode: IoMode) -> Result<(), OsalError> {
+            let mut current = lock(&self.mode);
+            apply_mode(
+                mode,
+                |nonblocking| self.stream.set_nonblocking(nonblocking),
+                |timeout| {
+                    self.stream.set_read_timeout(timeout)?;
+                    self.stream.set_write_timeout(timeout)
+                },
+            )?;
+            *current = mode;
+            Ok(())
+        }
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, OsalError> {
+            self.stream
+                .read(buf)
+                .map_err(|e| mode_error(e, *lock(&self.mode)))
+        }
+
+        fn write(&mut self, buf: &[u8]) -> Result<usize, OsalError> {
+            self.stream
+                .write(buf)
+                .map_err(|e| mode_error(e, *lock(&self.mode)))
+        }
+    }
+
+    /// A TCP listener on the POSIX network stack. Accepting with a timeout polls the
+    /// socket, std listeners have no accept timeout.
+    ///
+    /// * `listener`: The std listener
+    /// * `mode`: How accepting waits
+    #[derive(Debug)]
+    pub struct PosixTcpListener {
+        listener: net::TcpListener,
+        mode: Mutex<IoMode>,
+    }
+
+    impl TcpListener for PosixTcpListener {
+        type Stream = PosixTcpStream;
+
+        fn local_addr(&self) -> Result<SocketAddr, OsalError> {
+            self.listener.local_addr().map_err(io_error)
+        }
+
+        fn set_mode(&self, mode: IoMode) -> Result<(), OsalError> {
+            let mut current = lock(&self.mode);
+            apply_mode(
+                mode,
+                |nonblocking| self.listener.set_nonblocking(nonblocking),
+                |_| Ok(()),
+            )?;
+            *current = mode;
+            Ok(())
+        }
+
+        fn accept(&self) -> Result<(PosixTcpStream, SocketAddr), OsalError> {
+            let mode = *lock(&self.mode);
+            if let IoMode::Timeout(timeout) = mode {
+                poll_readable(self.listener.as_raw_fd(), Some(timeout))?;
+            }
+            let (stream, addr) = self.listener.accept().map_err(|e| mode_error(e, mode))?;
+            Ok((PosixTcpStream::new(stream)?, addr))
+        }
+    }
+
+    impl NetworkApi for PosixNetwork {
+        type UdpSocket = PosixUdpSocket;
+        type TcpListener = PosixTcpListener;
+        type TcpStream = PosixTcpStream;
+
+        fn udp_bind(&self, addr: SocketAddr) -> Result<PosixUdpSocket, OsalError> {
+            Ok(PosixUdpSocket {
+                socket: net::UdpSocket::bind(addr).map_err(io_error)?,
+                mode: Mutex::new(IoMode::Blocking),
+            })
+        }
+
+        fn tcp_listen(&self, addr: SocketAddr) -> Result<PosixTcpListener, OsalError> {
+            Ok(PosixTcpListener {
+                listener: net::TcpListener::bind(addr).map_err(io_error)?,
+                mode: Mutex::new(IoMode::Blocking),
+            })
+        }
+
+        fn tcp_connect(
+            &self,
+            addr: SocketAddr,
+            timeout: Option<Duration>,
+        ) -> Result<PosixTcpStream, OsalError> {
+            let stream = match timeout {
+                Some(timeout) => net::TcpStream::connect_timeout(&addr, os_timeout(timeout)),
+                None => net::TcpStream::connect(addr),
+            };
+            PosixTcpStream::new(stream.map_err(io_error)?)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::time::Instant;
+
+        const LOCALHOST: SocketAddr =
+            SocketAddr::new(core::net::IpAddr::V4(core::net::Ipv4Addr::LOCALHOST), 0);
+
+        #[test]
+        /// Datagrams travel between UDP sockets, receives honour the socket mode
+        fn udp() {
+            let net = PosixNetwork::new();
+            let a = net.udp_bind(LOCALHOST).unwrap();
+            let b = net.udp_bind(LOCALHOST).unwrap();
+            a.send_to(b"ping", b.local_addr().unwrap()).unwrap();
+            let mut buf = [0; 16];
+            let (n, from) = b.recv_from(&mut buf).unwrap();
+            assert_eq!(&buf[..n], b"ping");
+            assert_eq!(from, a.local_addr().unwrap());
+
+            b.set_mode(IoMode::NonBlocking).unwrap();
+            assert_eq!(b.recv_from(&mut buf), Err(OsalError::WouldBlock));
+            b.set_mode(IoMode::Timeout(Duration::from_millis(20)))
+                .unwrap();
+            let start = Instant::now();
+            assert_eq!(b.recv_from(&mut buf), Err(OsalError::Timeout));
+            assert!(start.elapsed() >= Duration::from_millis(15));
+
+            let taken = b.local_addr().unwrap();
+            assert_eq!(net.udp_bind(taken).err(), Some(OsalError::AlreadyExists));
+        }
+
+        #[test]
+        /// TCP streams connect to a listener and carry data both ways, accepting and
+        /// reading honour the socket modes
+        fn tcp() {
+            let net = PosixNetwork::new();
+            let listener = net.tcp_listen(LOCALHOST).unwrap();
+            let addr = listener.local_addr().unwrap();
+
+            listener.set_mode(IoMode::NonBlocking).unwrap();
+            assert_eq!(listener.accept().err(), Some(OsalError::WouldBlock));
+            listener
+                .set_mode(IoMode::Timeout(Duration::from_millis(20)))
+                .unwrap();
+            assert_eq!(listener.accept().err(), Some(OsalError::Timeout));
+
+            let mut client = net.tcp_connect(addr, Some(Duration::from_secs(1))).unwrap();
+            let (mut server, peer) = listener.accept().unwrap();
+            assert_eq!(peer, client.local_addr().unwrap());
+            assert_eq!(client.peer_addr().unwrap(), addr);
+
+            client.write_all(b"hello").unwrap();
+            let mut buf = [0; 16];
+            let mut received = 0;
+            while received < 5 {
+                received += server.read(&mut buf[received..]).unwrap();
+            }
+            assert_eq!(&buf[..5], b"hello");
+            server.write_all(b"!").unwrap();
+            assert_eq!(client.read(&mut buf).unwrap(), 1);
+
+            client
+                .set_mode(IoMode::Timeout(Duration::from_millis(20)))
+                .unwrap();
+            assert_eq!(client.read(&mut buf), Err(OsalError::Timeout));
+            client.set_mode(IoMode::NonBlocking).unwrap();
+            assert_eq!(client.read(&mut buf), Err(OsalError::WouldBlock));
+
+            // Reads end once the peer closed the stream
+            drop(server);
+            client.set_mode(IoMode::Blocking).unwrap();
+            assert_eq!(client.read(&mut buf), Ok(0));
+
+            drop(listener);
+            assert_eq!(
+                net.tcp_connect(addr, None).err(),
+                Some(OsalError::ConnectionRefused)
+            );
+        }
+    }
+}
+
+#[cfg(feature = "posix")]
+pub use posix_net::{PosixNetwork, PosixTcpListener, PosixTcpStream, PosixUdpSocket};
@@ -0,0 +1,45 @@
+//! Nomad OSAL Polling
+//!
+//! Waits on descriptors for the POSIX implementations that have no timeout of their own
+
+use crate::error::io_error;
+use nomad_core::osal::OsalError;
+use std::io;
+use std::os::fd::RawFd;
+use std::time::{Duration, Instant};
+
+/// Waits up to `timeout` until `fd` is readable, e.g. a listener has a connection to accept.
+/// Fails with `OsalError::Timeout` otherwise, None waits forever. A wait interrupted by a
+/// signal is resumed with the time remaining.
+pub(crate) fn poll_readable(fd: RawFd, timeout: Option<Duration>) -> Result<(), OsalError> {
+    let deadline = timeout.map(|t| Instant::now().checked_add(t));
+    loop {
+        let mut pollfd = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // Rounded up, so short timeouts still wait. A deadline too far out waits forever.
+        let millis = match deadline {
+            None | Some(None) => -1,
+            Some(Some(deadline)) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                remaining
+                    .as_nanos()
+                    .div_ceil(1_000_000)
+                    .min(i32::MAX as u128) as i32
+            }
+        };
+        // SAFETY: polls a single descriptor, the caller keeps it open
+        match unsafe { libc::poll(&mut pollfd, 1, millis) } {
+            0 => return Err(OsalError::Timeout),
+            n if n < 0 => {
+                let e = io::Error::last_os_error();
+                if e.kind() != io::ErrorKind::Interrupted {
+                    return Err(io_error(e));
+                }
+            }
+            _ => return Ok(()),
+        }
+    }
+}
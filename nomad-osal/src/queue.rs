@@ -0,0 +1,196 @@
+//! Nomad OSAL Message Queues
+//!
+//! Provides the OS implementations of bounded message queues
+
+#[cfg(feature = "posix")]
+mod posix_queue {
+    use crate::sync::{lock, wait_until};
+    use nomad_core::osal::{OsalError, Queue, QueueStats, SendError};
+    use std::collections::VecDeque;
+    use std::sync::{Condvar, Mutex};
+    use std::time::Duration;
+
+    /// Messages and fill level, guarded by the queue mutex
+    ///
+    /// * `messages`: Queued messages, oldest first
+    /// * `high_water`: Highest depth reached
+    struct State<T> {
+        messages: VecDeque<T>,
+        high_water: usize,
+    }
+
+    /// Error of a call that gave up waiting. A zero timeout never waits, the call would
+    /// have blocked.
+    fn wait_error(timeout: Option<Duration>) -> OsalError {
+        if timeout == Some(Duration::ZERO) {
+            OsalError::WouldBlock
+        } else {
+            OsalError::Timeout
+        }
+    }
+
+    /// Bounded queue between threads of one process, on a std mutex and condition variables.
+    /// Storage for all messages is allocated up front.
+    ///
+    /// * `state`: Messages and high-water mark
+    /// * `capacity`: Most messages the queue holds
+    /// * `not_empty`: Signalled when a message is sent
+    /// * `not_full`: Signalled when a message is received
+    pub struct PosixQueue<T> {
+        state: Mutex<State<T>>,
+        capacity: usize,
+        not_empty: Condvar,
+        not_full: Condvar,
+    }
+
+    impl<T> Queue<T> for PosixQueue<T> {
+        fn new(capacity: usize) -> Result<Self, OsalError> {
+            if capacity == 0 {
+                return Err(OsalError::InvalidArgument);
+            }
+            Ok(Self {
+                state: Mutex::new(State {
+                    messages: VecDeque::with_capacity(capacity),
+                    high_water: 0,
+                }),
+                capacity,
+                not_empty: Condvar::new(),
+                not_full: Condvar::new(),
+            })
+        }
+
+        fn send(&self, msg: T, timeout: Option<Duration>) -> Result<(), SendError<T>> {
+            let mut msg = Some(msg);
+            let capacity = self.capacity;
+            let sent = wait_until(&self.not_full, lock(&self.state), timeout, |state| {
+                if state.messages.len() >= capacity {
+                    return false;
+                }
+                state.messages.extend(msg.take());
+                state.high_water = state.high_water.max(state.messages.len());
+                true
+            });
+            if sent {
+                self.not_empty.notify_one();
+                return Ok(());
+            }
+            Err(SendError {
+                msg: msg.expect("message is only taken when sent"),
+                error: wait_error(timeout),
+            })
+        }
+
+        fn try_send(&self, msg: T) -> Result<(), SendError<T>> {
+            self.send(msg, Some(Duration::ZERO))
+        }
+
+        fn recv(&self, timeout: Option<Duration>) -> Result<T, OsalError> {
+            let mut msg = None;
+            wait_until(&self.not_empty, lock(&self.state), timeout, |state| {
+                msg = state.messages.pop_front();
+                msg.is_some()
+            });
+            let msg = msg.ok_or(wait_error(timeout))?;
+            self.not_full.notify_one();
+            Ok(msg)
+        }
+
+        fn try_recv(&self) -> Option<T> {
+            self.recv(Some(Duration::ZERO)).ok()
+        }
+
+        fn stats(&self) -> QueueStats {
+            let state = lock(&self.state);
+            QueueStats {
+                depth: state.messages.len(),
+                capacity: self.capacity,
+                high_water: state.high_water,
+            }
+        }
+
+        fn reset_high_water(&self) {
+            let mut state = lock(&self.state);
+            state.high_water = state.messages.len();
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::sync::Arc;
+        use std::thread;
+
+        #[test]
+        /// Messages pass between threads in order, blocking while the queue is full
+        fn producer_consumer() {
+            let queue = Arc::new(PosixQueue::new(4).unwrap());
+            let producer = {
+                let queue = Arc::clone(&queue);
+                thread::spawn(move || {
+                    for i in 0..100u32 {
+                        queue.send(i, None).unwrap();
+                    }
+                })
+            };
+            let received: Vec<u32> = (0..100)
+                .map(|_| queue.recv(Some(Duration::from_secs(5))).unwrap())
+                .collect();
+            producer.join().unwrap();
+
+            assert_eq!(received, (0..100).collect::<Vec<_>>());
+            assert_eq!(queue.depth(), 0);
+            assert!(queue.high_water() <= 4);
+        }
+
+        #[test]
+        /// Full and empty queues fail non-blocking and timed calls, and report their fill
+        /// level
+        fn full_and_empty() {
+            assert_eq!(
+                PosixQueue::<u8>::new(0).err(),
+                Some(OsalError::InvalidArgument)
+            );
+
+            let queue = PosixQueue::new(2).unwrap();
+            assert_eq!(queue.try_recv(), None);
+            assert_eq!(
+                queue.recv(Some(Duration::from_millis(10))),
+                Err(OsalError::Timeout)
+            );
+            assert_eq!(queue.recv(Some(Duration::ZERO)), Err(OsalError::WouldBlock));
+
+            queue.try_send(1).unwrap();
+            queue.send(2, Some(Duration::from_millis(10))).unwrap();
+            assert_eq!(
+                queue.try_send(3),
+                Err(SendError {
+                    msg: 3,
+                    error: OsalError::WouldBlock
+                })
+            );
+            assert_eq!(
+                queue
+                    .send(3, Some(Duration::from_millis(10)))
+                    .map_err(|e| e.error),
+                Err(OsalError::Timeout)
+            );
+            assert_eq!(
+                queue.stats(),
+                QueueStats {
+                    depth: 2,
+                    capacity: 2,
+                    high_water: 2
+                }
+            );
+
+            assert_eq!(queue.try_recv(), Some(1));
+            queue.reset_high_water();
+            assert_eq!(queue.high_water(), 1);
+            assert_eq!(queue.try_recv(), Some(2));
+            assert_eq!(queue.high_water(), 1);
+        }
+    }
+}
+
+#[cfg(feature = "posix")]
+pub use posix_queue::PosixQueue;
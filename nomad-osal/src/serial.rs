@@ -0,0 +1,302 @@
+//! Nomad OSAL Serial Ports
+//!
+//! Provides the OS implementations of the serial port API
+
+#[cfg(feature = "posix")]
+mod posix_serial {
+    use crate::error::io_error;
+    use crate::poll::poll_readable;
+    use nomad_core::osal::{
+        DataBits, FlowControl, OsalError, Parity, SerialConfig, SerialPort, StopBits,
+    };
+    use std::fs::{File, OpenOptions};
+    use std::io::{self, Read, Write};
+    use std::os::fd::AsRawFd;
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::path::Path;
+
+    /// termios speed of a baud rate, None for non-standard rates
+    fn speed(baud_rate: u32) -> Option<libc::speed_t> {
+        Some(match baud_rate {
+            1200 => libc::B1200,
+            2400 => libc::B2400,
+            4800 => libc::B4800,
+            9600 => libc::B9600,
+            19200 => libc::B19200,
+            38400 => libc::B38400,
+            57600 => libc::B57600,
+            115200 => libc::B115200,
+            230400 => libc::B230400,
+            #[cfg(target_os = "linux")]
+            460800 => libc::B460800,
+            #[cfg(target_os = "linux")]
+            921600 => libc::B921600,
+            #[cfg(target_os = "linux")]
+            1000000 => libc::B1000000,
+            #[cfg(target_os = "linux")]
+            2000000 => libc::B2000000,
+            _ => return None,
+        })
+    }
+
+    /// Checks the result of a termios call
+    fn check(result: libc::c_int) -> Result<(), OsalError> {
+        if result == 0 {
+            Ok(())
+        } else {
+            Err(io_error(io::Error::last_os_error()))
+        }
+    }
+
+    /// Whether the framing, flow control and speed of `applied` match `requested`
+    fn same_settings(requested: &libc::termios, applied: &libc::termios) -> bool {
+        let cflags = libc::CSIZE | libc::PARENB | libc::PARODD | libc::CSTOPB | libc::CRTSCTS;
+        let iflags = libc::IXON | libc::IXOFF;
+        // SAFETY: reads the speeds out of the plain data structs
+        let speeds = unsafe {
+            libc::cfgetispeed(requested) == libc::cfgetispeed(applied)
+                && libc::cfgetospeed(requested) == libc::cfgetospeed(applied)
+        };
+        requested.c_cflag & cflags == applied.c_cflag & cflags
+            && requested.c_iflag & iflags == applied.c_iflag & iflags
+            && speeds
+    }
+
+    /// Serial port on a TTY, configured through termios in raw mode, e.g. `/dev/ttyS0` or
+    /// `/dev/ttyUSB0`
+    ///
+    /// * `file`: The open TTY
+    /// * `config`: Settings in effect
+    #[derive(Debug)]
+    pub struct PosixSerialPort {
+        file: File,
+        config: SerialConfig,
+    }
+
+    impl PosixSerialPort {
+        /// Opens the TTY at `path` and applies `config`
+        pub fn open(path: impl AsRef<Path>, config: &SerialConfig) -> Result<Self, OsalError> {
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .custom_flags(libc::O_NOCTTY)
+                .open(path)
+                .map_err(io_error)?;
+            Self::from_file(file, config)
+        }
+
+        /// Serial port on an already open TTY, applies `config`
+        pub fn from_file(file: File, config: &SerialConfig) -> Result<Self, OsalError> {
+            let mut port = Self {
+                file,
+                config: *config,
+            };
+            port.configure(config)?;
+            Ok(port)
+        }
+    }
+
+    impl SerialPort for PosixSerialPort {
+        fn config(&self) -> SerialConfig {
+            self.config
+        }
+
+        fn configure(&mut self, config: &SerialConfig) -> Result<(), OsalError> {
+            let speed = speed(config.baud_rate).ok_or(OsalError::InvalidArgument)?;
+            let fd = self.file.as_raw_fd();
+            // SAFETY: termios is plain data, filled in by tcgetattr before it is modified.
+            // The descriptor stays open for the lifetime of the port.
+            unsafe {
+                let mut previous: libc::termios = core::mem::zeroed();
+                check(libc::tcgetattr(fd, &mut previous))?;
+                let mut termios = previous;
+                libc::cfmakeraw(&mut termios);
+
+                termios.c_cflag &= !(libc::CSIZE | libc::PARENB | libc::PARODD | libc::CSTOPB);
+                termios.c_cflag |= libc::CLOCAL | libc::CREAD;
+                termios.c_cflag |= match config.data_bits {
+                    DataBits::Five => libc::CS5,
+                    DataBits::Six => libc::CS6,
+                    DataBits::Seven => libc::CS7,
+                    DataBits::Eight => libc::CS8,
+                };
+                termios.c_cflag |= match config.parity {
+                    Parity::None => 0,
+                    Parity::Odd => libc::PARENB | libc::PARODD,
+                    Parity::Even => libc::PARENB,
+                };
+                if config.stop_bits == StopBits::Two {
+                    termios.c_cflag |= libc::CSTOPB;
+                }
+
+                termios.c_cflag &= !libc::CRTSCTS;
+                termios.c_iflag &= !(libc::IXON | libc::IXOFF | libc::IXANY);
+                match config.flow_control {
+                    FlowControl::None => {}
+                    FlowControl::Hardware => termios.c_cflag |= libc::CRTSCTS,
+                    FlowControl::Software => termios.c_iflag |= libc::IXON | libc::IXOFF,
+                }
+
+                // Reads block until a byte arrives, the read timeout is applied by polling
+                termios.c_cc[libc::VMIN] = 1;
+                termios.c_cc[libc::VTIME] = 0;
+
+                check(libc::cfsetispeed(&mut termios, speed))?;
+                check(libc::cfsetospeed(&mut termios, speed))?;
+                check(libc::tcsetattr(fd, libc::TCSANOW, &termios))?;
+
+                // tcsetattr succeeds when any of the settings was applied, drivers silently
+                // ignore the ones they do not support. Read them back and roll back on a
+                // mismatch, so the port never runs with a framing other than requested.
+                let mut applied: libc::termios = core::mem::zeroed();
+                check(libc::tcgetattr(fd, &mut applied))?;
+                if !same_settings(&termios, &applied) {
+                    check(libc::tcsetattr(fd, libc::TCSANOW, &previous))?;
+                    return Err(OsalError::InvalidArgument);
+                }
+            }
+            self.config = *config;
+            Ok(())
+        }
+
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, OsalError> {
+            if let Some(timeout) = self.config.read_timeout {
+                poll_readable(self.file.as_raw_fd(), Some(timeout))?;
+            }
+            self.file.read(buf).map_err(io_error)
+        }
+
+        fn write(&mut self, buf: &[u8]) -> Result<usize, OsalError> {
+            self.file.write(buf).map_err(io_error)
+        }
+
+        fn flush(&mut self) -> Result<(), OsalError> {
+            // SAFETY: waits on the descriptor owned by the port
+            check(unsafe { libc::tcdrain(self.file.as_raw_fd()) })
+        }
+
+        fn discard_input(&mut self) -> Result<(), OsalError> {
+            // SAFETY: flushes the descriptor owned by the port
+            check(unsafe { libc::tcflush(self.file.as_raw_fd(), libc::TCIFLUSH) })
+        }
+    }
+
+    #[cfg(all(test, target_os = "linux"))]
+    mod tests {
+        use super::*;
+        use std::ffi::CStr;
+        use std::os::fd::FromRawFd;
+        use std::time::{Duration, Instant};
+
+        /// Opens a pseudo-terminal pair, returning the controlling side and the path of
+        /// the TTY side
+        fn open_pty() -> (File, String) {
+            // SAFETY: plain libc calls, the name buffer is large enough and NUL terminated
+            // by ptsname_r. The master descriptor is owned by the returned file.
+            unsafe {
+                let master = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY);
+                assert!(master >= 0);
+                let master_file = File::from_raw_fd(master);
+                assert_eq!(libc::grantpt(master), 0);
+                assert_eq!(libc::unlockpt(master), 0);
+                let mut name = [0 as libc::c_char; 128];
+                assert_eq!(libc::ptsname_r(master, name.as_mut_ptr(), name.len()), 0);
+                let path = CStr::from_ptr(name.as_ptr()).to_str().unwrap().to_string();
+                (master_file, path)
+            }
+        }
+
+        /// termios settings in effect on `port`
+        fn termios(port: &PosixSerialPort) -> libc::termios {
+            // SAFETY: tcgetattr fills in the plain data struct
+            unsafe {
+                let mut termios: libc::termios = core::mem::zeroed();
+                assert_eq!(libc::tcgetattr(port.file.as_raw_fd(), &mut termios), 0);
+                termios
+            }
+        }
+
+        #[test]
+        /// Bytes travel both ways over a pseudo-terminal, settings reach the TTY and reads
+        /// time out
+        fn pty_loopback() {
+            let (mut master, path) = open_pty();
+            let config = SerialConfig {
+                read_timeout: Some(Duration::from_millis(20)),
+                ..SerialConfig::new(115200)
+            };
+            let mut port = PosixSerialPort::open(&path, &config).unwrap();
+
+            master.write_all(b"$GPGGA\r\n").unwrap();
+            let mut buf = [0; 32];
+            let mut received = 0;
+            while received < 8 {
+                received += port.read(&mut buf[received..]).unwrap();
+            }
+            assert_eq!(&buf[..8], b"$GPGGA\r\n");
+
+            port.write_all(b"ack\n").unwrap();
+            port.flush().unwrap();
+            let mut received = 0;
+            while received < 4 {
+                received += master.read(&mut buf[received..]).unwrap();
+            }
+            assert_eq!(&buf[..4], b"ack\n");
+
+            let start = Instant::now();
+            assert_eq!(port.read(&mut buf), Err(OsalError::Timeout));
+            assert!(start.elapsed() >= Duration::from_millis(15));
+
+            let t = termios(&port);
+            assert_eq!(t.c_cflag & libc::CSIZE, libc::CS8);
+            assert_eq!(t.c_cflag & (libc::PARENB | libc::CSTOPB | libc::CRTSCTS), 0);
+            // SAFETY: reads the speed out of the plain data struct
+            assert_eq!(unsafe { libc::cfgetospeed(&t) }, libc::B115200);
+
+            // The pty driver forces 8N1 framing, other framings are rejected and the
+            // previous settings restored
+            let framing = SerialConfig {
+                baud_rate: 9600,
+                data_bits: DataBits::Seven,
+                parity: Parity::Even,
+                stop_bits: StopBits::Two,
+                flow_control: FlowControl::Software,
+                read_timeout: Some(Duration::ZERO),
+            };
+            assert_eq!(port.configure(&framing), Err(OsalError::InvalidArgument));
+            assert_eq!(port.config(), config);
+            let t = termios(&port);
+            assert_eq!(t.c_iflag & libc::IXON, 0);
+            // SAFETY: reads the speed out of the plain data struct
+            assert_eq!(unsafe { libc::cfgetispeed(&t) }, libc::B115200);
+
+            // Speed and flow control stick
+            let config = SerialConfig {
+                data_bits: DataBits::Eight,
+                parity: Parity::None,
+                stop_bits: StopBits::One,
+                ..framing
+            };
+            port.configure(&config).unwrap();
+            assert_eq!(port.config(), config);
+            let t = termios(&port);
+            assert_ne!(t.c_iflag & libc::IXON, 0);
+            // SAFETY: reads the speed out of the plain data struct
+            assert_eq!(unsafe { libc::cfgetispeed(&t) }, libc::B9600);
+
+            // Non-standard rates are rejected, the settings stay in effect
+            let odd = SerialConfig::new(12345);
+            assert_eq!(port.configure(&odd), Err(OsalError::InvalidArgument));
+            assert_eq!(port.config(), config);
+
+            // Pending input can be discarded
+            master.write_all(b"stale").unwrap();
+            std::thread::sleep(Duration::from_millis(20));
+            port.discard_input().unwrap();
+            assert_eq!(port.read(&mut buf), Err(OsalError::Timeout));
+        }
+    }
+}
+
+#[cfg(feature = "posix")]
+pub use posix_serial::PosixSerialPort;
@@ -0,0 +1,221 @@
+//! Nomad OSAL Signals
+//!
+//! Provides the OS implementations of the signal API
+
+#[cfg(feature = "posix")]
+mod posix_signal {
+    use crate::error::io_error;
+    use crate::poll::poll_readable;
+    use nomad_core::osal::{OsalError, Signal, Signals};
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::sync::atomic::{AtomicI32, Ordering};
+    use std::time::{Duration, Instant};
+
+    /// Signals handled, and what they are delivered as
+    const HANDLED: [(libc::c_int, Signal); 3] = [
+        (libc::SIGINT, Signal::Interrupt),
+        (libc::SIGTERM, Signal::Terminate),
+        (libc::SIGHUP, Signal::Hangup),
+    ];
+
+    /// Write end of the pipe the signal handler forwards signals to, -1 when no handler is
+    /// installed
+    static SIGNAL_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+    /// Signal handler, forwards the signal number to the pipe. Only async-signal-safe calls
+    /// are allowed in here.
+    extern "C" fn on_signal(signum: libc::c_int) {
+        let fd = SIGNAL_PIPE.load(Ordering::SeqCst);
+        if fd < 0 {
+            return;
+        }
+        // SAFETY: write is async-signal-safe, the byte outlives the call. errno is restored
+        // since the interrupted code may be about to read it. A full pipe drops the signal,
+        // the ones already queued are handled first anyway.
+        unsafe {
+            #[cfg(target_os = "linux")]
+            let errno = *libc::__errno_location();
+            let byte = signum as u8;
+            libc::write(fd, (&byte as *const u8).cast(), 1);
+            #[cfg(target_os = "linux")]
+            {
+                *libc::__errno_location() = errno;
+            }
+        }
+    }
+
+    /// Sets the flags of a pipe end, non-blocking and closed on exec
+    fn set_flags(fd: &OwnedFd) -> Result<(), OsalError> {
+        let fd = fd.as_raw_fd();
+        // SAFETY: fcntl on a descriptor owned by the caller
+        let ok = unsafe {
+            libc::fcntl(fd, libc::F_SETFL, libc::O_NONBLOCK) == 0
+                && libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) == 0
+        };
+        if ok {
+            Ok(())
+        } else {
+            Err(io_error(io::Error::last_os_error()))
+        }
+    }
+
+    /// POSIX signals SIGINT, SIGTERM and SIGHUP, forwarded by a signal handler through a
+    /// pipe (the self-pipe trick), so they are received outside of the handler. Only one
+    /// instance can be installed at a time, dropping it restores the previous handlers.
+    ///
+    /// * `read`: Read end of the pipe
+    /// * `_write`: Write end of the pipe, used by the handler
+    /// * `previous`: Actions in place before the handler was installed
+    pub struct PosixSignals {
+        read: OwnedFd,
+        _write: OwnedFd,
+        previous: [libc::sigaction; HANDLED.len()],
+    }
+
+    impl PosixSignals {
+        /// Installs the signal handler. Fails with `OsalError::AlreadyExists` while another
+        /// instance is installed.
+        pub fn install() -> Result<Self, OsalError> {
+            let mut fds = [0; 2];
+            // SAFETY: pipe fills in two new descriptors, owned by the OwnedFds from here on
+            let (read, write) = unsafe {
+                if libc::pipe(fds.as_mut_ptr()) != 0 {
+                    return Err(io_error(io::Error::last_os_error()));
+                }
+                (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1]))
+            };
+            set_flags(&read)?;
+            set_flags(&write)?;
+
+            SIGNAL_PIPE
+                .compare_exchange(-1, write.as_raw_fd(), Ordering::SeqCst, Ordering::SeqCst)
+                .map_err(|_| OsalError::AlreadyExists)?;
+
+            // SAFETY: sigaction structs are plain data, the handler only makes async-signal-
+            // safe calls. Handlers installed before a failure are restored.
+            unsafe {
+                let mut action: libc::sigaction = core::mem::zeroed();
+                action.sa_sigaction = on_signal as extern "C" fn(libc::c_int) as usize;
+                action.sa_flags = libc::SA_RESTART;
+                libc::sigemptyset(&mut action.sa_mask);
+
+                let mut previous: [libc::sigaction; HANDLED.len()] = core::mem::zeroed();
+                for (i, (signum, _)) in HANDLED.iter().enumerate() {
+                    if libc::sigaction(*signum, &action, &mut previous[i]) != 0 {
+                        let e = io_error(io::Error::last_os_error());
+                        for (j, (signum, _)) in HANDLED.iter().enumerate().take(i) {
+                            libc::sigaction(*signum, &previous[j], core::ptr::null_mut());
+                        }
+                        SIGNAL_PIPE.store(-1, Ordering::SeqCst);
+                        return Err(e);
+                    }
+                }
+
+                Ok(Self {
+                    read,
+                    _write: write,
+                    previous,
+                })
+            }
+        }
+    }
+
+    impl Signals for PosixSignals {
+        fn poll(&self) -> Option<Signal> {
+            loop {
+                let mut byte = 0u8;
+                // SAFETY: reads a single byte from the pipe owned by self
+                let n =
+                    unsafe { libc::read(self.read.as_raw_fd(), (&mut byte as *mut u8).cast(), 1) };
+                if n != 1 {
+                    return None;
+                }
+                if let Some((_, signal)) = HANDLED
+                    .iter()
+                    .find(|(signum, _)| *signum == i32::from(byte))
+                {
+                    return Some(*signal);
+                }
+            }
+        }
+
+        fn wait(&self, timeout: Option<Duration>) -> Result<Signal, OsalError> {
+            let deadline = timeout.map(|t| Instant::now() + t);
+            loop {
+                if let Some(signal) = self.poll() {
+                    return Ok(signal);
+                }
+                let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+                poll_readable(self.read.as_raw_fd(), remaining)?;
+            }
+        }
+    }
+
+    impl Drop for PosixSignals {
+        fn drop(&mut self) {
+            // SAFETY: restores the actions saved by install
+            unsafe {
+                for ((signum, _), previous) in HANDLED.iter().zip(&self.previous) {
+                    libc::sigaction(*signum, previous, core::ptr::null_mut());
+                }
+            }
+            SIGNAL_PIPE.store(-1, Ordering::SeqCst);
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// Handler currently installed for `signum`
+        fn handler(signum: libc::c_int) -> libc::sighandler_t {
+            // SAFETY: queries the action into a plain data struct
+            unsafe {
+                let mut action: libc::sigaction = core::mem::zeroed();
+                libc::sigaction(signum, core::ptr::null(), &mut action);
+                action.sa_sigaction
+            }
+        }
+
+        #[test]
+        /// Signals raised while installed are received in order, dropping restores the
+        /// previous handlers
+        fn receive_signals() {
+            let before = handler(libc::SIGHUP);
+            let signals = PosixSignals::install().unwrap();
+            assert_eq!(
+                PosixSignals::install().err(),
+                Some(OsalError::AlreadyExists)
+            );
+            assert_eq!(signals.poll(), None);
+
+            // SAFETY: raises signals at the calling thread, handled by the installed handler
+            unsafe {
+                libc::raise(libc::SIGHUP);
+                libc::raise(libc::SIGTERM);
+                libc::raise(libc::SIGINT);
+            }
+            assert_eq!(signals.poll(), Some(Signal::Hangup));
+            assert_eq!(
+                signals.wait(Some(Duration::from_millis(10))),
+                Ok(Signal::Terminate)
+            );
+            assert_eq!(signals.wait(None), Ok(Signal::Interrupt));
+
+            let start = Instant::now();
+            assert_eq!(
+                signals.wait(Some(Duration::from_millis(20))),
+                Err(OsalError::Timeout)
+            );
+            assert!(start.elapsed() >= Duration::from_millis(20));
+
+            drop(signals);
+            assert_eq!(handler(libc::SIGHUP), before);
+            drop(PosixSignals::install().unwrap());
+        }
+    }
+}
+
+#[cfg(feature = "posix")]
+pub use posix_signal::PosixSignals;
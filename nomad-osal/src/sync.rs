@@ -0,0 +1,516 @@
+//! Nomad OSAL Synchronisation
+//!
+//! Provides the OS implementations of mutexes, semaphores and event flags
+
+#[cfg(feature = "posix")]
+mod posix_sync {
+    use core::cell::UnsafeCell;
+    use core::marker::PhantomData;
+    use core::mem::ManuallyDrop;
+    use core::ops::{Deref, DerefMut};
+    use nomad_core::osal::{EventFlags, FlagWait, Mutex, OsalError, Semaphore};
+    use std::sync::{Condvar, MutexGuard, PoisonError};
+    use std::time::{Duration, Instant};
+
+    /// Mutex on a pthread mutex. Uses the priority inheritance protocol where the host
+    /// supports it, so a low priority task holding the lock is boosted while a high
+    /// priority task waits for it.
+    ///
+    /// * `raw`: The pthread mutex, boxed as it must not move once initialised. Leaked when
+    ///   the mutex is dropped while locked.
+    /// * `value`: Value protected by the mutex
+    /// * `priority_inheritance`: Whether the mutex uses priority inheritance
+    pub struct PosixMutex<T> {
+        raw: ManuallyDrop<Box<UnsafeCell<libc::pthread_mutex_t>>>,
+        value: UnsafeCell<T>,
+        priority_inheritance: bool,
+    }
+
+    // SAFETY: the value is only accessed through a guard, while the pthread mutex is held
+    unsafe impl<T: Send> Send for PosixMutex<T> {}
+    unsafe impl<T: Send> Sync for PosixMutex<T> {}
+
+    impl<T> PosixMutex<T> {
+        /// Whether the mutex uses priority inheritance
+        pub fn priority_inheritance(&self) -> bool {
+            self.priority_inheritance
+        }
+
+        /// Initialises `raw`, with priority inheritance if requested. Returns whether it
+        /// succeeded.
+        fn init(raw: *mut libc::pthread_mutex_t, inherit: bool) -> bool {
+            // SAFETY: attr is initialised before use and destroyed afterwards, raw points to
+            // memory owned by the mutex that is not yet initialised
+            unsafe {
+                let mut attr: libc::pthread_mutexattr_t = core::mem::zeroed();
+                if libc::pthread_mutexattr_init(&mut attr) != 0 {
+                    return false;
+                }
+                let mut ok = true;
+                if inherit {
+                    ok = set_inherit(&mut attr);
+                }
+                ok = ok && libc::pthread_mutex_init(raw, &attr) == 0;
+                libc::pthread_mutexattr_destroy(&mut attr);
+                ok
+            }
+        }
+
+        fn guard(&self) -> PosixMutexGuard<'_, T> {
+            PosixMutexGuard {
+                mutex: self,
+                _not_send: PhantomData,
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    unsafe fn set_inherit(attr: *mut libc::pthread_mutexattr_t) -> bool {
+        // SAFETY: attr was initialised by the caller
+        unsafe { libc::pthread_mutexattr_setprotocol(attr, libc::PTHREAD_PRIO_INHERIT) == 0 }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    unsafe fn set_inherit(_attr: *mut libc::pthread_mutexattr_t) -> bool {
+        false
+    }
+
+    impl<T> Mutex<T> for PosixMutex<T> {
+        type Guard<'a>
+            = PosixMutexGuard<'a, T>
+        where
+            T: 'a;
+
+        /// Panics if the OS cannot create a mutex at all
+        fn new(value: T) -> Self {
+            // SAFETY: an all-zero pthread_mutex_t is only a placeholder, init overwrites it
+            let raw = Box::new(UnsafeCell::new(unsafe { core::mem::zeroed() }));
+            let priority_inheritance = Self::init(raw.get(), true);
+            if !priority_inheritance {
+                assert!(Self::init(raw.get(), false), "pthread_mutex_init failed");
+            }
+            Self {
+                raw: ManuallyDrop::new(raw),
+                value: UnsafeCell::new(value),
+                priority_inheritance,
+            }
+        }
+
+        fn lock(&self, timeout: Option<Duration>) -> Result<PosixMutexGuard<'_, T>, OsalError> {
+            let rc = match timeout {
+                // SAFETY: raw was initialised in new
+                None => unsafe { libc::pthread_mutex_lock(self.raw.get()) },
+                Some(timeout) => timed_lock(self.raw.get(), timeout),
+            };
+            match rc {
+                0 => Ok(self.guard()),
+                libc::ETIMEDOUT => Err(OsalError::Timeout),
+                e => Err(OsalError::Os(e)),
+            }
+        }
+
+        fn try_lock(&self) -> Option<PosixMutexGuard<'_, T>> {
+            // SAFETY: raw was initialised in new
+            let rc = unsafe { libc::pthread_mutex_trylock(self.raw.get()) };
+            (rc == 0).then(|| self.guard())
+        }
+    }
+
+    impl<T> Drop for PosixMutex<T> {
+        fn drop(&mut self) {
+            // A guard leaked with mem::forget leaves the mutex locked, and destroying a
+            // locked mutex is undefined behaviour. Like std, such a mutex is leaked instead.
+            // SAFETY: raw was initialised in new. Once the lock is taken here no other
+            // guard exists, as dropping requires exclusive access.
+            unsafe {
+                if libc::pthread_mutex_trylock(self.raw.get()) == 0 {
+                    libc::pthread_mutex_unlock(self.raw.get());
+                    libc::pthread_mutex_destroy(self.raw.get());
+                    ManuallyDrop::drop(&mut self.raw);
+                }
+            }
+        }
+    }
+
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    unsafe extern "C" {
+        /// pthread_mutex_timedlock against a chosen clock, glibc 2.30 and later
+        fn pthread_mutex_clocklock(
+            mutex: *mut libc::pthread_mutex_t,
+            clock: libc::clockid_t,
+            abstime: *const libc::timespec,
+        ) -> libc::c_int;
+    }
+
+    /// Locks with a deadline on CLOCK_MONOTONIC, so steps of the wall clock, e.g. by NTP
+    /// or node time sync, do not shorten or stretch the timeout
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    fn timed_lock(raw: *mut libc::pthread_mutex_t, timeout: Duration) -> i32 {
+        poll_if_unsupported(clock_lock(raw, timeout), raw, timeout)
+    }
+
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    fn clock_lock(raw: *mut libc::pthread_mutex_t, timeout: Duration) -> i32 {
+        // SAFETY: raw is an initialised mutex, timespec is plain data
+        unsafe {
+            let mut deadline: libc::timespec = core::mem::zeroed();
+            libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut deadline);
+            let nanos = deadline.tv_nsec as u64 + u64::from(timeout.subsec_nanos());
+            deadline.tv_sec = deadline
+                .tv_sec
+                .saturating_add((timeout.as_secs() + nanos / 1_000_000_000) as libc::time_t);
+            deadline.tv_nsec = (nanos % 1_000_000_000) as _;
+            pthread_mutex_clocklock(raw, libc::CLOCK_MONOTONIC, &deadline)
+        }
+    }
+
+    /// pthread_mutex_clocklock reports EINVAL or ENOTSUP where the kernel or libc does not
+    /// support the clock, e.g. under some emulators. The lock then falls back to polling.
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    fn poll_if_unsupported(rc: i32, raw: *mut libc::pthread_mutex_t, timeout: Duration) -> i32 {
+        match rc {
+            libc::EINVAL | libc::ENOTSUP => poll_lock(raw, timeout),
+            rc => rc,
+        }
+    }
+
+    /// Hosts without pthread_mutex_clocklock poll the mutex against the monotonic clock,
+    /// pthread_mutex_timedlock would measure the timeout on the wall clock
+    #[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+    fn timed_lock(raw: *mut libc::pthread_mutex_t, timeout: Duration) -> i32 {
+        poll_lock(raw, timeout)
+    }
+
+    /// Polls the mutex with trylock until it is taken or the timeout passes on the
+    /// monotonic clock
+    fn poll_lock(raw: *mut libc::pthread_mutex_t, timeout: Duration) -> i32 {
+        let deadline = Instant::now() + timeout;
+        loop {
+            // SAFETY: raw is an initialised mutex
+            if unsafe { libc::pthread_mutex_trylock(raw) } == 0 {
+                return 0;
+            }
+            if Instant::now() >= deadline {
+                return libc::ETIMEDOUT;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    /// Lock on a PosixMutex, released when dropped. Must be dropped on the task that took
+    /// the lock.
+    pub struct PosixMutexGuard<'a, T> {
+        mutex: &'a PosixMutex<T>,
+        _not_send: PhantomData<*const ()>,
+    }
+
+    impl<T> Deref for PosixMutexGuard<'_, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            // SAFETY: the lock is held
+            unsafe { &*self.mutex.value.get() }
+        }
+    }
+
+    impl<T> DerefMut for PosixMutexGuard<'_, T> {
+        fn deref_mut(&mut self) -> &mut T {
+            // SAFETY: the lock is held, and the guard is borrowed mutably
+            unsafe { &mut *self.mutex.value.get() }
+        }
+    }
+
+    impl<T> Drop for PosixMutexGuard<'_, T> {
+        fn drop(&mut self) {
+            // SAFETY: the lock is held by this task
+            unsafe {
+                libc::pthread_mutex_unlock(self.mutex.raw.get());
+            }
+        }
+    }
+
+    /// Locks a std mutex, ignoring poisoning. State guarded here stays consistent even if a
+    /// holder panicked.
+    pub(crate) fn lock<T>(mutex: &std::sync::Mutex<T>) -> MutexGuard<'_, T> {
+        mutex.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Waits on `condvar` until `done` returns true, or the timeout passes. Returns whether
+    /// `done` was satisfied.
+    pub(crate) fn wait_until<'a, T>(
+        condvar: &Condvar,
+        mut guard: MutexGuard<'a, T>,
+        timeout: Option<Duration>,
+        mut done: impl FnMut(&mut T) -> bool,
+    ) -> bool {
+        let deadline = timeout.map(|t| Instant::now() + t);
+        loop {
+            if done(&mut guard) {
+                return true;
+            }
+            guard = match deadline {
+                None => condvar.wait(guard).unwrap_or_else(PoisonError::into_inner),
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return false;
+                    }
+                    condvar
+                        .wait_timeout(guard, remaining)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                }
+            };
+        }
+    }
+
+    /// Counting semaphore on a std mutex and condition variable
+    ///
+    /// * `count`: Tokens held
+    /// * `max`: Most tokens the semaphore holds
+    /// * `given`: Signalled when a token is given
+    pub struct PosixSemaphore {
+        count: std::sync::Mutex<u32>,
+        max: u32,
+        given: Condvar,
+    }
+
+    impl Semaphore for PosixSemaphore {
+        fn new(initial: u32, max: u32) -> Self {
+            Self {
+                count: std::sync::Mutex::new(initial.min(max)),
+                max,
+                given: Condvar::new(),
+            }
+        }
+
+        fn take(&self, timeout: Option<Duration>) -> Result<(), OsalError> {
+            let taken = wait_until(&self.given, lock(&self.count), timeout, |count| {
+                let available = *count > 0;
+                if available {
+                    *count -= 1;
+                }
+                available
+            });
+            if taken {
+                Ok(())
+            } else {
+                Err(OsalError::Timeout)
+            }
+        }
+
+        fn try_take(&self) -> bool {
+            self.take(Some(Duration::ZERO)).is_ok()
+        }
+
+        fn give(&self) -> Result<(), OsalError> {
+            let mut count = lock(&self.count);
+            if *count >= self.max {
+                return Err(OsalError::Overflow);
+            }
+            *count += 1;
+            self.given.notify_one();
+            Ok(())
+        }
+
+        fn count(&self) -> u32 {
+            *lock(&self.count)
+        }
+    }
+
+    /// Event flags on a std mutex and condition variable
+    ///
+    /// * `flags`: Flags currently set
+    /// * `changed`: Signalled when flags are set
+    pub struct PosixEventFlags {
+        flags: std::sync::Mutex<u32>,
+        changed: Condvar,
+    }
+
+    impl Default for PosixEventFlags {
+        fn default() -> Self {
+            <Self as EventFlags>::new()
+        }
+    }
+
+    impl EventFlags for PosixEventFlags {
+        fn new() -> Self {
+            Self {
+                flags: std::sync::Mutex::new(0),
+                changed: Condvar::new(),
+            }
+        }
+
+        fn set(&self, mask: u32) {
+            *lock(&self.flags) |= mask;
+            self.changed.notify_all();
+        }
+
+        fn clear(&self, mask: u32) {
+            *lock(&self.flags) &= !mask;
+        }
+
+        fn get(&self) -> u32 {
+            *lock(&self.flags)
+        }
+
+        fn wait(
+            &self,
+            mask: u32,
+            wait: FlagWait,
+            clear: bool,
+            timeout: Option<Duration>,
+        ) -> Result<u32, OsalError> {
+            let mut seen = 0;
+            let satisfied = wait_until(&self.changed, lock(&self.flags), timeout, |flags| {
+                seen = *flags;
+                let satisfied = wait.satisfied(*flags, mask);
+                if satisfied && clear {
+                    *flags &= !mask;
+                }
+                satisfied
+            });
+            if satisfied {
+                Ok(seen)
+            } else {
+                Err(OsalError::Timeout)
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::sync::Arc;
+        use std::thread;
+
+        #[test]
+        /// The mutex serialises access between threads, and times out while held
+        fn mutex() {
+            let counter = Arc::new(PosixMutex::new(0u32));
+            #[cfg(target_os = "linux")]
+            assert!(counter.priority_inheritance());
+
+            let workers: Vec<_> = (0..4)
+                .map(|_| {
+                    let counter = Arc::clone(&counter);
+                    thread::spawn(move || {
+                        for _ in 0..1000 {
+                            *counter.lock(None).unwrap() += 1;
+                        }
+                    })
+                })
+                .collect();
+            for worker in workers {
+                worker.join().unwrap();
+            }
+            assert_eq!(*counter.lock(None).unwrap(), 4000);
+
+            let guard = counter.lock(Some(Duration::from_millis(10))).unwrap();
+            let contender = Arc::clone(&counter);
+            let blocked = thread::spawn(move || {
+                assert!(contender.try_lock().is_none());
+                contender.lock(Some(Duration::from_millis(20))).err()
+            });
+            assert_eq!(blocked.join().unwrap(), Some(OsalError::Timeout));
+            drop(guard);
+            assert!(counter.try_lock().is_some());
+
+            // A mutex dropped while locked through a leaked guard is leaked as well
+            let leaked = PosixMutex::new(1u8);
+            core::mem::forget(leaked.lock(None).unwrap());
+            drop(leaked);
+        }
+
+        #[test]
+        #[cfg(all(target_os = "linux", target_env = "gnu"))]
+        /// An unsupported clock falls back to polling, which still times out while held
+        fn mutex_poll_fallback() {
+            let mutex = Arc::new(PosixMutex::new(0u32));
+            let guard = mutex.lock(None).unwrap();
+            let contender = Arc::clone(&mutex);
+            let blocked = thread::spawn(move || {
+                let start = Instant::now();
+                let rc = poll_if_unsupported(
+                    libc::ENOTSUP,
+                    contender.raw.get(),
+                    Duration::from_millis(20),
+                );
+                (rc, start.elapsed())
+            });
+            let (rc, elapsed) = blocked.join().unwrap();
+            assert_eq!(rc, libc::ETIMEDOUT);
+            assert!(elapsed >= Duration::from_millis(20));
+            drop(guard);
+
+            let rc = poll_if_unsupported(libc::EINVAL, mutex.raw.get(), Duration::from_millis(20));
+            assert_eq!(rc, 0);
+            // SAFETY: the poll took the lock above
+            unsafe { libc::pthread_mutex_unlock(mutex.raw.get()) };
+            assert!(mutex.try_lock().is_some());
+
+            // Other results pass through unchanged
+            assert_eq!(
+                poll_if_unsupported(libc::ETIMEDOUT, mutex.raw.get(), Duration::ZERO),
+                libc::ETIMEDOUT
+            );
+        }
+
+        #[test]
+        /// Tokens are handed between threads, up to the maximum
+        fn semaphore() {
+            let sem = Arc::new(PosixSemaphore::binary(false));
+            assert!(!sem.try_take());
+            assert_eq!(
+                sem.take(Some(Duration::from_millis(10))),
+                Err(OsalError::Timeout)
+            );
+
+            let giver = Arc::clone(&sem);
+            let handle = thread::spawn(move || giver.give().unwrap());
+            sem.take(Some(Duration::from_secs(5))).unwrap();
+            handle.join().unwrap();
+
+            sem.give().unwrap();
+            assert_eq!(sem.give(), Err(OsalError::Overflow));
+            assert_eq!(sem.count(), 1);
+
+            let counting = PosixSemaphore::new(5, 3);
+            assert_eq!(counting.count(), 3);
+            assert!(counting.try_take());
+            assert_eq!(counting.count(), 2);
+        }
+
+        #[test]
+        /// Waits return once any or all flags of the mask are set
+        fn event_flags() {
+            let flags = Arc::new(PosixEventFlags::new());
+            let setter = Arc::clone(&flags);
+            let handle = thread::spawn(move || {
+                setter.set(0b001);
+                thread::sleep(Duration::from_millis(5));
+                setter.set(0b100);
+            });
+            let seen = flags
+                .wait(0b101, FlagWait::All, true, Some(Duration::from_secs(5)))
+                .unwrap();
+            handle.join().unwrap();
+            assert_eq!(seen, 0b101);
+            assert_eq!(flags.get(), 0);
+
+            flags.set(0b010);
+            assert_eq!(flags.wait(0b011, FlagWait::Any, false, None), Ok(0b010));
+            assert_eq!(flags.get(), 0b010);
+            flags.clear(0b010);
+            assert_eq!(
+                flags.wait(0b010, FlagWait::Any, false, Some(Duration::from_millis(10))),
+                Err(OsalError::Timeout)
+            );
+        }
+    }
+}
+
+#[cfg(feature = "posix")]
+pub use posix_sync::{PosixEventFlags, PosixMutex, PosixMutexGuard, PosixSemaphore};
+#[cfg(feature = "posix")]
+pub(crate) use posix_sync::{lock, wait_until};
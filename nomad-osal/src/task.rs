@@ -0,0 +1,271 @@
+//! Nomad OSAL Tasks
+//!
+//! Provides the OS implementations of the task API
+
+#[cfg(feature = "posix")]
+mod posix_task {
+    use nomad_core::osal::{OsalError, Task, TaskApi, TaskConfig, TaskPriority};
+    use std::sync::mpsc;
+    use std::thread::{self, JoinHandle, Thread};
+    use std::time::Duration;
+
+    /// What happens to a task whose realtime priority cannot be applied, typically because
+    /// the process lacks the privileges (CAP_SYS_NICE) for realtime scheduling
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub enum PriorityPolicy {
+        /// The task runs with the default scheduling, see `PosixTask::priority_applied`
+        BestEffort,
+        /// Spawning fails
+        Strict,
+    }
+
+    /// POSIX tasks, std threads whose priority is set through pthread scheduling
+    /// parameters. Realtime priorities use SCHED_FIFO, mapped linearly onto its priority
+    /// range.
+    ///
+    /// * `policy`: What to do when a realtime priority cannot be applied
+    #[derive(Copy, Clone, Debug)]
+    pub struct PosixTaskApi {
+        policy: PriorityPolicy,
+    }
+
+    impl PosixTaskApi {
+        /// Task API that runs tasks even when their priority cannot be applied, e.g. on a
+        /// development host
+        pub fn new() -> Self {
+            Self::with_policy(PriorityPolicy::BestEffort)
+        }
+
+        /// Task API that fails to spawn tasks whose priority cannot be applied, for flight
+        pub fn strict() -> Self {
+            Self::with_policy(PriorityPolicy::Strict)
+        }
+
+        pub fn with_policy(policy: PriorityPolicy) -> Self {
+            Self { policy }
+        }
+    }
+
+    impl Default for PosixTaskApi {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    /// A task running on a std thread
+    ///
+    /// * `name`: Name the task was spawned with
+    /// * `handle`: Handle of the thread
+    /// * `priority_applied`: Whether the requested priority is in effect
+    pub struct PosixTask {
+        name: String,
+        handle: JoinHandle<()>,
+        priority_applied: bool,
+    }
+
+    impl PosixTask {
+        /// Whether the requested priority is in effect. Only false for realtime priorities
+        /// under `PriorityPolicy::BestEffort`.
+        pub fn priority_applied(&self) -> bool {
+            self.priority_applied
+        }
+
+        /// The underlying thread
+        pub fn thread(&self) -> &Thread {
+            self.handle.thread()
+        }
+    }
+
+    impl Task for PosixTask {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn is_finished(&self) -> bool {
+            self.handle.is_finished()
+        }
+
+        fn join(self) -> Result<(), OsalError> {
+            self.handle.join().map_err(|_| OsalError::TaskPanicked)
+        }
+    }
+
+    impl TaskApi for PosixTaskApi {
+        type Task = PosixTask;
+
+        fn spawn<F>(&self, config: &TaskConfig, entry: F) -> Result<PosixTask, OsalError>
+        where
+            F: FnOnce() + Send + 'static,
+        {
+            let mut builder = thread::Builder::new().name(config.name.to_string());
+            if let Some(stack_size) = config.stack_size {
+                if stack_size < libc::PTHREAD_STACK_MIN {
+                    return Err(OsalError::InvalidArgument);
+                }
+                builder = builder.stack_size(stack_size);
+            }
+
+            // The priority is applied by the new thread to itself, before running the entry
+            // function, and reported back
+            let (applied_tx, applied_rx) = mpsc::channel();
+            let priority = config.priority;
+            let policy = self.policy;
+            let handle = builder
+                .spawn(move || {
+                    let applied = set_current_priority(priority);
+                    let run = applied.is_ok() || policy == PriorityPolicy::BestEffort;
+                    let _ = applied_tx.send(applied);
+                    if run {
+                        entry();
+                    }
+                })
+                .map_err(|e| {
+                    e.raw_os_error()
+                        .map_or(OsalError::ResourceExhausted, OsalError::Os)
+                })?;
+
+            let applied = applied_rx.recv().unwrap_or(Err(OsalError::TaskPanicked));
+            if let (Err(e), PriorityPolicy::Strict) = (applied, self.policy) {
+                let _ = handle.join();
+                return Err(e);
+            }
+            Ok(PosixTask {
+                name: config.name.to_string(),
+                handle,
+                priority_applied: applied.is_ok(),
+            })
+        }
+
+        fn sleep(&self, duration: Duration) {
+            thread::sleep(duration);
+        }
+    }
+
+    /// Sets the scheduling of the calling thread. Normal priority leaves the default
+    /// scheduling in place.
+    fn set_current_priority(priority: TaskPriority) -> Result<(), OsalError> {
+        if !priority.is_realtime() {
+            return Ok(());
+        }
+        // SAFETY: plain libc calls on the calling thread, sched_param is plain data
+        unsafe {
+            let min = libc::sched_get_priority_min(libc::SCHED_FIFO);
+            let max = libc::sched_get_priority_max(libc::SCHED_FIFO);
+            if min < 0 || max < min {
+                return Err(OsalError::Unsupported);
+            }
+            let step = i32::from(priority.0 - TaskPriority::MIN_REALTIME.0);
+            let range = i32::from(TaskPriority::MAX_REALTIME.0 - TaskPriority::MIN_REALTIME.0);
+            let mut param: libc::sched_param = core::mem::zeroed();
+            param.sched_priority = min + step * (max - min) / range;
+
+            match libc::pthread_setschedparam(libc::pthread_self(), libc::SCHED_FIFO, &param) {
+                0 => Ok(()),
+                libc::EPERM => Err(OsalError::PermissionDenied),
+                libc::EINVAL => Err(OsalError::InvalidArgument),
+                e => Err(OsalError::Os(e)),
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use nomad_core::time::{MonotonicInstant, TimeConfig, TimeMode, TimeSource};
+
+        /// Scheduling policy of the calling thread
+        fn current_policy() -> i32 {
+            let mut policy = 0;
+            // SAFETY: reads the scheduling parameters of the calling thread
+            unsafe {
+                let mut param: libc::sched_param = core::mem::zeroed();
+                libc::pthread_getschedparam(libc::pthread_self(), &mut policy, &mut param);
+            }
+            policy
+        }
+
+        #[test]
+        /// Tasks run with their name and stack size, and can be joined
+        fn spawn_and_join() {
+            let tasks = PosixTaskApi::new();
+            let (tx, rx) = mpsc::channel();
+            let config = TaskConfig {
+                stack_size: Some(256 * 1024),
+                ..TaskConfig::new("nomad-worker")
+            };
+            let task = tasks
+                .spawn(&config, move || {
+                    tx.send(thread::current().name().map(str::to_string))
+                        .unwrap();
+                })
+                .unwrap();
+            assert_eq!(task.name(), "nomad-worker");
+            assert!(task.priority_applied());
+            task.join().unwrap();
+            assert_eq!(rx.recv().unwrap().as_deref(), Some("nomad-worker"));
+
+            let panicking = tasks
+                .spawn(&TaskConfig::new("nomad-panic"), || panic!("task failed"))
+                .unwrap();
+            assert_eq!(panicking.join(), Err(OsalError::TaskPanicked));
+
+            let tiny = TaskConfig {
+                stack_size: Some(1),
+                ..TaskConfig::new("nomad-tiny")
+            };
+            assert_eq!(
+                tasks.spawn(&tiny, || {}).err(),
+                Some(OsalError::InvalidArgument)
+            );
+        }
+
+        #[test]
+        /// Realtime priorities are applied when permitted. Without privileges best effort
+        /// tasks still run and strict ones fail.
+        fn realtime_priority() {
+            let config = TaskConfig {
+                priority: TaskPriority::MAX_REALTIME,
+                ..TaskConfig::new("nomad-rt")
+            };
+            let (tx, rx) = mpsc::channel();
+            let task = PosixTaskApi::new()
+                .spawn(&config, move || tx.send(current_policy()).unwrap())
+                .unwrap();
+            let applied = task.priority_applied();
+            task.join().unwrap();
+            let policy = rx.recv().unwrap();
+            assert_eq!(policy == libc::SCHED_FIFO, applied);
+
+            match PosixTaskApi::strict().spawn(&config, || {}) {
+                Ok(task) => {
+                    assert!(applied);
+                    task.join().unwrap();
+                }
+                Err(e) => {
+                    assert!(!applied);
+                    assert_eq!(e, OsalError::PermissionDenied);
+                }
+            }
+        }
+
+        #[test]
+        /// Sleeping until a deadline wakes up at the deadline
+        fn sleep_until_deadline() {
+            let clock = crate::timesource::PosixTimeSource::new(&TimeConfig {
+                mode: TimeMode::Real,
+                mission_epoch: None,
+            });
+            let tasks = PosixTaskApi::new();
+            let deadline = clock.monotonic() + Duration::from_millis(20);
+            tasks.sleep_until(&clock, deadline);
+            assert!(clock.monotonic() >= deadline);
+
+            let start = clock.monotonic();
+            tasks.sleep_until(&clock, MonotonicInstant::ZERO);
+            assert!(clock.monotonic() - start < Duration::from_millis(10));
+        }
+    }
+}
+
+#[cfg(feature = "posix")]
+pub use posix_task::{PosixTask, PosixTaskApi, PriorityPolicy};
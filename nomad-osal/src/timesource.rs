@@ -3,81 +3,371 @@
 //! Provides Wrappers for Time Sources provided by OS
 
 use core::time::Duration;
-use nomad_core::time::{TimeConfig, TimeSource};
+use nomad_core::time::{
+    MissionTime, MonotonicInstant, SimulatedTimeSource, TimeConfig, TimeMode, TimeSource,
+    TimeSourceHealth, TimeSourceKind, UtcTime,
+};
+
+#[cfg(feature = "posix")]
+mod epoch_store;
+#[cfg(feature = "posix")]
+pub use epoch_store::FileEpochStore;
+
+#[cfg(feature = "sim-lockstep")]
+mod lockstep;
+#[cfg(feature = "sim-lockstep")]
+pub use lockstep::{LOCKSTEP_MSG_LEN, LockstepDriver, LockstepEvent, LockstepTimeSource};
+
+#[cfg(feature = "replay")]
+mod replay;
+#[cfg(feature = "replay")]
+pub use replay::ReplayTimeSource;
 
 #[cfg(feature = "posix")]
 mod posix_time {
 
     use super::*;
+    use crate::sync::lock;
+    use nomad_core::time::{Epoch, LeapSecondTable};
+    use std::sync::Mutex;
     use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
     /// POSIX Time Source using Instant + System Time
     ///
     /// * `start_instant`: Time reference for monotonic time, recorded and instantiation
-    /// * `mission_instant`: Time reference relative to mission epoch.
+    /// * `mission_epoch`: Mission epoch of the config, if any
+    /// * `mission_instant`: Time reference relative to mission epoch. Realigned when the
+    ///   leap-second table changes, as the epoch may be given in TAI or GPS time.
     pub struct PosixTimeSource {
         start_instant: Instant,
-        mission_instant: Instant,
+        mission_epoch: Option<Epoch>,
+        mission_instant: Mutex<Instant>,
     }
 
     impl PosixTimeSource {
+        /// Converts the mission epoch with the builtin leap-second table until a
+        /// TimeService hands over its own
         pub fn new(cfg: &TimeConfig) -> Self {
             let start_instant = Instant::now();
-
-            // If mission epoch is provided in config, align mission_instant so that:
-            // mission_time() = now_unix - mission_epoch.
-            // Can now provide time relative since mission started using this
-            let mission_instant = if let Some(mission_epoch_secs) = cfg.mission_epoch_unix {
-                // Current time
-                let now_sys = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap_or(Duration::from_secs(0));
-
-                // Convert mission epoch to duration
-                let mission_epoch = Duration::from_secs(mission_epoch_secs);
-
-                // Calculate difference from current time to epoch
-                let offset = now_sys
-                    .checked_sub(mission_epoch)
-                    .unwrap_or(Duration::from_secs(0));
-
-                // Align that difference to time source start.
-                start_instant.checked_sub(offset).unwrap_or(start_instant)
-            } else {
-                // If no epoch provided, just use start as mission base.
-                start_instant
-            };
-
+            let mission_instant = align(
+                start_instant,
+                cfg.mission_epoch,
+                &LeapSecondTable::builtin(),
+            );
             Self {
                 start_instant,
-                mission_instant,
+                mission_epoch: cfg.mission_epoch,
+                mission_instant: Mutex::new(mission_instant),
             }
         }
     }
 
+    /// Instant mission time is measured from, so that mission_time() = now_unix - epoch.
+    /// Without an epoch, mission time starts with the time source.
+    fn align(
+        start_instant: Instant,
+        epoch: Option<Epoch>,
+        leap_seconds: &LeapSecondTable,
+    ) -> Instant {
+        let Some(epoch) = epoch else {
+            return start_instant;
+        };
+        // Current time, measured at the same moment as now
+        let now = Instant::now();
+        let now_sys = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::from_secs(0));
+
+        // Convert mission epoch to UTC, the scale of the system clock
+        let mission_epoch = epoch.to_utc(leap_seconds).0;
+
+        // Align the difference from current time to epoch to now. An epoch in the future
+        // puts the mission instant ahead of now, mission time counts down to it
+        if let Some(elapsed) = now_sys.checked_sub(mission_epoch) {
+            now.checked_sub(elapsed).unwrap_or(start_instant)
+        } else {
+            let countdown = mission_epoch - now_sys;
+            now.checked_add(countdown).unwrap_or(start_instant)
+        }
+    }
+
     impl TimeSource for PosixTimeSource {
-        fn monotonic(&self) -> Duration {
-            Instant::now()
-                .checked_duration_since(self.start_instant)
-                .unwrap_or(Duration::from_secs(0))
+        fn monotonic(&self) -> MonotonicInstant {
+            MonotonicInstant::from_start(
+                Instant::now()
+                    .checked_duration_since(self.start_instant)
+                    .unwrap_or(Duration::from_secs(0)),
+            )
+        }
+
+        fn mission_time(&self) -> MissionTime {
+            let mission_instant = *lock(&self.mission_instant);
+            let now = Instant::now();
+            match now.checked_duration_since(mission_instant) {
+                Some(elapsed) => MissionTime::after_epoch(elapsed),
+                None => MissionTime::before_epoch(mission_instant - now),
+            }
+        }
+
+        fn utc(&self) -> Option<UtcTime> {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(UtcTime)
         }
 
-        fn mission_time(&self) -> Duration {
-            Instant::now()
-                .checked_duration_since(self.mission_instant)
-                .unwrap_or(Duration::from_secs(0))
+        /// Synchronisation state and maximum error as tracked by the kernel, e.g. while NTP
+        /// or chrony discipline the clock
+        fn health(&self) -> TimeSourceHealth {
+            kernel_clock_health()
         }
+
+        fn leap_seconds_changed(&self, leap_seconds: &LeapSecondTable) {
+            *lock(&self.mission_instant) =
+                align(self.start_instant, self.mission_epoch, leap_seconds);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn kernel_clock_health() -> TimeSourceHealth {
+        // SAFETY: timex is plain data, modes 0 only reads the kernel clock state
+        let mut timex: libc::timex = unsafe { core::mem::zeroed() };
+        let state = unsafe { libc::adjtimex(&mut timex) };
+        if state < 0 || state == libc::TIME_ERROR || timex.status & libc::STA_UNSYNC != 0 {
+            return TimeSourceHealth::free_running(TimeSourceKind::OsClock);
+        }
+        TimeSourceHealth {
+            kind: TimeSourceKind::OsClock,
+            valid: true,
+            synchronized: true,
+            error_bound: Some(Duration::from_micros(timex.maxerror as u64)),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn kernel_clock_health() -> TimeSourceHealth {
+        TimeSourceHealth::free_running(TimeSourceKind::OsClock)
+    }
+
+    /// TimeSource selected by the TimeConfig mode
+    pub enum DefaultTimeSource {
+        /// `TimeMode::Real`, time from the OS clock
+        Posix(PosixTimeSource),
+        /// `TimeMode::Simulated`, time only advances when told to
+        Simulated(SimulatedTimeSource),
+        /// `TimeMode::Replay`, time played back from a recording
+        #[cfg(feature = "replay")]
+        Replay(ReplayTimeSource),
     }
 
-    /// Factory for the default POSIX TimeSource.
+    impl DefaultTimeSource {
+        /// The simulated clock, if the source is simulated, to advance it
+        pub fn as_simulated(&self) -> Option<&SimulatedTimeSource> {
+            match self {
+                DefaultTimeSource::Simulated(sim) => Some(sim),
+                _ => None,
+            }
+        }
+
+        /// The replay, if the source plays back a recording, to pause, seek or change speed
+        #[cfg(feature = "replay")]
+        pub fn as_replay(&self) -> Option<&ReplayTimeSource> {
+            match self {
+                DefaultTimeSource::Replay(replay) => Some(replay),
+                _ => None,
+            }
+        }
+    }
+
+    impl TimeSource for DefaultTimeSource {
+        fn monotonic(&self) -> MonotonicInstant {
+            match self {
+                DefaultTimeSource::Posix(source) => source.monotonic(),
+                DefaultTimeSource::Simulated(source) => source.monotonic(),
+                #[cfg(feature = "replay")]
+                DefaultTimeSource::Replay(source) => source.monotonic(),
+            }
+        }
+
+        fn mission_time(&self) -> MissionTime {
+            match self {
+                DefaultTimeSource::Posix(source) => source.mission_time(),
+                DefaultTimeSource::Simulated(source) => source.mission_time(),
+                #[cfg(feature = "replay")]
+                DefaultTimeSource::Replay(source) => source.mission_time(),
+            }
+        }
+
+        fn utc(&self) -> Option<UtcTime> {
+            match self {
+                DefaultTimeSource::Posix(source) => source.utc(),
+                DefaultTimeSource::Simulated(source) => source.utc(),
+                #[cfg(feature = "replay")]
+                DefaultTimeSource::Replay(source) => source.utc(),
+            }
+        }
+
+        fn health(&self) -> TimeSourceHealth {
+            match self {
+                DefaultTimeSource::Posix(source) => source.health(),
+                DefaultTimeSource::Simulated(source) => source.health(),
+                #[cfg(feature = "replay")]
+                DefaultTimeSource::Replay(source) => source.health(),
+            }
+        }
+
+        fn leap_seconds_changed(&self, leap_seconds: &LeapSecondTable) {
+            match self {
+                DefaultTimeSource::Posix(source) => source.leap_seconds_changed(leap_seconds),
+                DefaultTimeSource::Simulated(source) => source.leap_seconds_changed(leap_seconds),
+                #[cfg(feature = "replay")]
+                DefaultTimeSource::Replay(source) => source.leap_seconds_changed(leap_seconds),
+            }
+        }
+    }
+
+    /// Factory for the default POSIX TimeSource. Selects a SimulatedTimeSource when the
+    /// config mode is Simulated, a ReplayTimeSource when it is Replay, and the OS clock
+    /// otherwise.
+    /// Panics when the recording to replay cannot be read, see
+    /// `try_make_default_time_source`.
     /// TODO: Have a better way of handling default time sources
     /// Have the TimeService handle defaults
-    pub fn make_default_time_source(cfg: &TimeConfig) -> PosixTimeSource {
-        PosixTimeSource::new(cfg)
+    pub fn make_default_time_source(cfg: &TimeConfig) -> DefaultTimeSource {
+        match try_make_default_time_source(cfg) {
+            Ok(source) => source,
+            Err(e) => panic!("failed to create time source for {:?}: {e}", cfg.mode),
+        }
+    }
+
+    /// Factory for the default POSIX TimeSource, reporting recordings that cannot be
+    /// replayed instead of panicking
+    pub fn try_make_default_time_source(cfg: &TimeConfig) -> std::io::Result<DefaultTimeSource> {
+        Ok(match cfg.mode {
+            TimeMode::Real => DefaultTimeSource::Posix(PosixTimeSource::new(cfg)),
+            TimeMode::Simulated => DefaultTimeSource::Simulated(SimulatedTimeSource::new()),
+            #[cfg(feature = "replay")]
+            TimeMode::Replay(path) => DefaultTimeSource::Replay(ReplayTimeSource::open(path)?),
+            #[cfg(not(feature = "replay"))]
+            TimeMode::Replay(_) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    "replay support not enabled",
+                ));
+            }
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use nomad_core::components::time::TimeService;
+        use nomad_core::time::scale::LeapSecond;
+
+        #[test]
+        /// The factory honours the configured TimeMode
+        fn factory_selects_mode() {
+            let mut cfg = TimeConfig {
+                mode: TimeMode::Simulated,
+                mission_epoch: None,
+            };
+
+            let source = make_default_time_source(&cfg);
+            let sim = source.as_simulated().expect("simulated source");
+            sim.advance(Duration::from_secs(3));
+            assert_eq!(source.monotonic().since_start(), Duration::from_secs(3));
+            assert_eq!(
+                source.mission_time().to_duration(),
+                Some(Duration::from_secs(3))
+            );
+
+            assert_eq!(source.health().kind, TimeSourceKind::Simulated);
+
+            cfg.mode = TimeMode::Real;
+            let source = make_default_time_source(&cfg);
+            assert!(source.as_simulated().is_none());
+            let health = source.health();
+            assert_eq!(health.kind, TimeSourceKind::OsClock);
+            assert!(health.valid);
+        }
+
+        #[cfg(feature = "replay")]
+        #[test]
+        /// Replay mode plays back the configured recording
+        fn factory_selects_replay() {
+            let path =
+                std::env::temp_dir().join(format!("nomad-replay-{}.log", std::process::id()));
+            std::fs::write(&path, "T+00:00:05.000000 INFO comp=1: recorded\n").unwrap();
+
+            let cfg = TimeConfig {
+                mode: TimeMode::Replay(path.to_str().unwrap()),
+                mission_epoch: None,
+            };
+            let source = make_default_time_source(&cfg);
+            let replay = source.as_replay().expect("replay source");
+            assert_eq!(
+                replay.start(),
+                MissionTime::after_epoch(Duration::from_secs(5))
+            );
+            assert_eq!(source.health().kind, TimeSourceKind::Simulated);
+            std::fs::remove_file(&path).unwrap();
+
+            let cfg = TimeConfig {
+                mode: TimeMode::Replay("/nonexistent/nomad-recording.log"),
+                mission_epoch: None,
+            };
+            assert!(try_make_default_time_source(&cfg).is_err());
+        }
+
+        #[test]
+        /// A mission epoch in the future counts down in mission time
+        fn countdown_before_epoch() {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+            let cfg = TimeConfig {
+                mode: TimeMode::Real,
+                mission_epoch: Some(Epoch::Utc(UtcTime(now + Duration::from_secs(10)))),
+            };
+            let source = PosixTimeSource::new(&cfg);
+
+            let met = source.mission_time();
+            assert!(met.is_before_epoch());
+            assert!(met.magnitude() > Duration::from_secs(9));
+            assert!(met.magnitude() <= Duration::from_secs(10));
+        }
+
+        #[test]
+        /// The mission epoch is converted with the leap-second table of the TimeService
+        fn epoch_follows_service_leap_seconds() {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+            let epoch = UtcTime(now + Duration::from_secs(10));
+            let cfg = TimeConfig {
+                mode: TimeMode::Real,
+                mission_epoch: Some(Epoch::Gps(LeapSecondTable::builtin().utc_to_gps(epoch))),
+            };
+            let mut service = TimeService::new(PosixTimeSource::new(&cfg));
+            let met = service.inner().mission_time();
+            assert!(met.magnitude() > Duration::from_secs(9));
+
+            // One more leap second puts the GPS epoch one second earlier in UTC
+            service
+                .update_leap_seconds(|table| {
+                    table.push(LeapSecond {
+                        utc: UtcTime::from_unix_secs(1_577_836_800),
+                        tai_minus_utc: 38,
+                    })
+                })
+                .unwrap();
+            let met = service.inner().mission_time();
+            assert!(met.is_before_epoch());
+            assert!(met.magnitude() > Duration::from_secs(8));
+            assert!(met.magnitude() <= Duration::from_secs(9));
+        }
     }
 }
 
 #[cfg(feature = "posix")]
 /// TODO: Have a better way of handling default time sources
 /// Have the TimeService handle defaults
-pub use posix_time::make_default_time_source;
+pub use posix_time::{
+    DefaultTimeSource, PosixTimeSource, make_default_time_source, try_make_default_time_source,
+};
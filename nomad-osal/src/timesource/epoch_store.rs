@@ -0,0 +1,103 @@
+//! File backed mission epoch storage
+//!
+//! Keeps the mission epoch set at runtime in a small text file, so the TimeService finds
+//! it again after a restart. The file holds a single line with the time scale and the
+//! time since the epoch of that scale, e.g. `UTC 1735689600.000000000`.
+
+use nomad_core::components::time::epoch::EpochStore;
+use nomad_core::time::{Epoch, GpsTime, TaiTime, UtcTime};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// EpochStore writing the epoch to a file. Writes go to a temporary file that is synced
+/// and renamed over the old one, then the directory is synced, so a reset or power loss
+/// during the write leaves either the previous or the new epoch.
+///
+/// * `path`: File holding the epoch
+pub struct FileEpochStore {
+    path: PathBuf,
+}
+
+impl FileEpochStore {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Atomically replaces the file contents with `contents`
+    fn replace(&self, contents: &[u8]) -> io::Result<()> {
+        let tmp = self.path.with_extension("tmp");
+        let mut file = File::create(&tmp)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        drop(file);
+
+        fs::rename(&tmp, &self.path)?;
+        // The rename only survives a power loss once the directory entry is on disk
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        File::open(dir)?.sync_all()
+    }
+}
+
+/// Formats an epoch as stored in the file
+fn format_epoch(epoch: &Epoch) -> String {
+    let (scale, time) = match epoch {
+        Epoch::Utc(t) => ("UTC", t.0),
+        Epoch::Tai(t) => ("TAI", t.0),
+        Epoch::Gps(t) => ("GPS", t.0),
+    };
+    format!("{scale} {}.{:09}\n", time.as_secs(), time.subsec_nanos())
+}
+
+/// Parses an epoch written by `format_epoch`
+fn parse_epoch(s: &str) -> Option<Epoch> {
+    let (scale, time) = s.trim().split_once(' ')?;
+    let (secs, nanos) = time.split_once('.')?;
+    if nanos.len() != 9 {
+        return None;
+    }
+    let time = Duration::new(secs.parse().ok()?, nanos.parse().ok()?);
+    match scale {
+        "UTC" => Some(Epoch::Utc(UtcTime(time))),
+        "TAI" => Some(Epoch::Tai(TaiTime(time))),
+        "GPS" => Some(Epoch::Gps(GpsTime(time))),
+        _ => None,
+    }
+}
+
+impl EpochStore for FileEpochStore {
+    fn load(&mut self) -> Option<Epoch> {
+        parse_epoch(&fs::read_to_string(&self.path).ok()?)
+    }
+
+    fn store(&mut self, epoch: Epoch) -> bool {
+        self.replace(format_epoch(&epoch).as_bytes()).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    /// Stored epochs are read back, missing or corrupt files read as no epoch
+    fn store_and_load() {
+        let path = std::env::temp_dir().join(format!("nomad-epoch-{}", std::process::id()));
+        let mut store = FileEpochStore::new(&path);
+        assert_eq!(store.load(), None);
+
+        let epoch = Epoch::Gps(GpsTime(Duration::new(1_420_000_000, 5)));
+        assert!(store.store(epoch));
+        assert_eq!(FileEpochStore::new(&path).load(), Some(epoch));
+
+        fs::write(&path, "UTC yesterday").unwrap();
+        assert_eq!(store.load(), None);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,293 @@
+//! Lockstep TimeSource
+//!
+//! Advances time only when an external simulator says so, for software-in-the-loop runs.
+//!
+//! The simulator and the FSW exchange small UDP datagrams, little endian:
+//!
+//! * Step (simulator → FSW): `['S'][seq: u32][sim time ns: u64]`
+//! * Ack (FSW → simulator): `['A'][seq: u32][sim time ns: u64]`, sent once the FSW has
+//!   finished the step
+//! * Stop (simulator → FSW): `['Q']`, ends the run
+
+use super::*;
+use std::cell::Cell;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+const STEP: u8 = b'S';
+const ACK: u8 = b'A';
+const STOP: u8 = b'Q';
+
+/// Length of step and ack messages
+pub const LOCKSTEP_MSG_LEN: usize = 13;
+
+/// Events received from the simulator
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LockstepEvent {
+    /// Time advanced to `time`, the FSW should run one step and acknowledge it
+    Step { seq: u32, time: Duration },
+    /// The simulator ended the run
+    Stop,
+}
+
+fn encode(kind: u8, seq: u32, time: Duration) -> [u8; LOCKSTEP_MSG_LEN] {
+    let mut msg = [0u8; LOCKSTEP_MSG_LEN];
+    msg[0] = kind;
+    msg[1..5].copy_from_slice(&seq.to_le_bytes());
+    let nanos = u64::try_from(time.as_nanos()).unwrap_or(u64::MAX);
+    msg[5..13].copy_from_slice(&nanos.to_le_bytes());
+    msg
+}
+
+fn decode(msg: &[u8]) -> Option<(u8, u32, Duration)> {
+    if msg.len() != LOCKSTEP_MSG_LEN {
+        return None;
+    }
+    let seq = u32::from_le_bytes(msg[1..5].try_into().ok()?);
+    let nanos = u64::from_le_bytes(msg[5..13].try_into().ok()?);
+    Some((msg[0], seq, Duration::from_nanos(nanos)))
+}
+
+fn set_timeout(socket: &UdpSocket, timeout: Option<Duration>) -> io::Result<()> {
+    // A zero read timeout is rejected by the OS, treat it as the shortest possible wait
+    socket.set_read_timeout(timeout.map(|t| t.max(Duration::from_micros(1))))
+}
+
+/// TimeSource driven in lockstep by an external simulator over UDP.
+///
+/// Time only changes in `wait_for_step`, which blocks until the simulator sends the next
+/// step. Once the FSW has run the step it calls `ack` so the simulator can advance the
+/// physics. Monotonic and mission time both read the simulator time.
+///
+/// * `socket`: Socket steps are received on
+/// * `clock`: Simulated clock set to the time of every step
+/// * `simulator`: Address of the simulator, learned from the first step
+/// * `pending`: Sequence number of the step waiting for an ack
+pub struct LockstepTimeSource {
+    // NOTE: NOT THREADSAFE
+    socket: UdpSocket,
+    clock: SimulatedTimeSource,
+    simulator: Cell<Option<SocketAddr>>,
+    pending: Cell<Option<u32>>,
+}
+
+impl LockstepTimeSource {
+    /// Binds the socket the simulator sends steps to
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        Ok(Self {
+            socket: UdpSocket::bind(addr)?,
+            clock: SimulatedTimeSource::new(),
+            simulator: Cell::new(None),
+            pending: Cell::new(None),
+        })
+    }
+
+    /// Address the simulator should send steps to
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Blocks until the simulator sends the next step or stops the run, and advances
+    /// time to the step. Waits forever when `timeout` is None, a timeout is reported as
+    /// a `WouldBlock` or `TimedOut` error. Unknown datagrams are ignored. A step before the
+    /// current time, e.g. a reordered or duplicated datagram, would move monotonic time
+    /// backwards: it is not applied nor acknowledged, and reported as an `InvalidData`
+    /// error.
+    pub fn wait_for_step(&self, timeout: Option<Duration>) -> io::Result<LockstepEvent> {
+        set_timeout(&self.socket, timeout)?;
+        let mut buf = [0u8; 64];
+
+        loop {
+            let (n, from) = self.socket.recv_from(&mut buf)?;
+            if buf[..n] == [STOP] {
+                return Ok(LockstepEvent::Stop);
+            }
+            let Some((STEP, seq, time)) = decode(&buf[..n]) else {
+                continue;
+            };
+
+            let now = self.clock.monotonic().since_start();
+            if time < now {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("step {seq} to {time:?} is before the current time {now:?}"),
+                ));
+            }
+
+            self.simulator.set(Some(from));
+            self.pending.set(Some(seq));
+            self.clock.set(time);
+            return Ok(LockstepEvent::Step { seq, time });
+        }
+    }
+
+    /// Acknowledges the current step, telling the simulator the FSW is done with it.
+    /// Does nothing if there is no step waiting for an ack.
+    pub fn ack(&self) -> io::Result<()> {
+        if let (Some(seq), Some(simulator)) = (self.pending.take(), self.simulator.get()) {
+            self.socket.send_to(
+                &encode(ACK, seq, self.clock.monotonic().since_start()),
+                simulator,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl TimeSource for LockstepTimeSource {
+    fn monotonic(&self) -> MonotonicInstant {
+        self.clock.monotonic()
+    }
+
+    fn mission_time(&self) -> MissionTime {
+        self.clock.mission_time()
+    }
+
+    fn health(&self) -> TimeSourceHealth {
+        self.clock.health()
+    }
+}
+
+/// Simulator side of the lockstep protocol. Stand-in for the physics simulator in tests,
+/// and a starting point for Rust simulation harnesses.
+///
+/// * `socket`: Socket steps are sent from
+/// * `fsw`: Address of the LockstepTimeSource
+/// * `seq`: Sequence number of the last step
+pub struct LockstepDriver {
+    socket: UdpSocket,
+    fsw: SocketAddr,
+    seq: u32,
+}
+
+impl LockstepDriver {
+    /// Creates a driver for the FSW listening at `fsw`
+    pub fn connect<A: ToSocketAddrs>(fsw: A) -> io::Result<Self> {
+        let fsw = fsw
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no FSW address"))?;
+        let local = if fsw.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+
+        Ok(Self {
+            socket: UdpSocket::bind(local)?,
+            fsw,
+            seq: 0,
+        })
+    }
+
+    /// Advances the FSW to `time` and waits up to `timeout` for it to acknowledge the step
+    pub fn step(&mut self, time: Duration, timeout: Duration) -> io::Result<()> {
+        self.seq = self.seq.wrapping_add(1);
+        self.socket
+            .send_to(&encode(STEP, self.seq, time), self.fsw)?;
+        set_timeout(&self.socket, Some(timeout))?;
+
+        let mut buf = [0u8; 64];
+        loop {
+            let n = self.socket.recv(&mut buf)?;
+            // Acks of earlier steps that arrived late are skipped
+            if let Some((ACK, seq, _)) = decode(&buf[..n])
+                && seq == self.seq
+            {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Ends the run
+    pub fn stop(&mut self) -> io::Result<()> {
+        self.socket.send_to(&[STOP], self.fsw)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    /// A driver steps a FSW loop running on another thread in lockstep
+    fn drives_fsw_in_lockstep() {
+        let source = LockstepTimeSource::bind("127.0.0.1:0").unwrap();
+        let addr = source.local_addr().unwrap();
+
+        // FSW loop: records the time it observes in every step
+        let fsw = thread::spawn(move || {
+            let mut observed = Vec::new();
+            loop {
+                match source.wait_for_step(Some(Duration::from_secs(5))).unwrap() {
+                    LockstepEvent::Step { time, .. } => {
+                        assert_eq!(source.monotonic().since_start(), time);
+                        observed.push(source.mission_time().to_duration().unwrap());
+                        source.ack().unwrap();
+                    }
+                    LockstepEvent::Stop => return observed,
+                }
+            }
+        });
+
+        let mut driver = LockstepDriver::connect(addr).unwrap();
+        let steps = [10, 20, 30].map(Duration::from_millis);
+        for t in steps {
+            driver.step(t, Duration::from_secs(5)).unwrap();
+        }
+        driver.stop().unwrap();
+
+        assert_eq!(fsw.join().unwrap(), steps);
+    }
+
+    #[test]
+    /// Steps back in time are reported and leave the time untouched
+    fn rejects_steps_back_in_time() {
+        let source = LockstepTimeSource::bind("127.0.0.1:0").unwrap();
+        let simulator = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = source.local_addr().unwrap();
+        let timeout = Some(Duration::from_secs(5));
+
+        simulator
+            .send_to(&encode(STEP, 1, Duration::from_millis(20)), addr)
+            .unwrap();
+        source.wait_for_step(timeout).unwrap();
+        source.ack().unwrap();
+
+        simulator
+            .send_to(&encode(STEP, 1, Duration::from_millis(10)), addr)
+            .unwrap();
+        let err = source.wait_for_step(timeout).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            source.monotonic(),
+            MonotonicInstant::from_start(Duration::from_millis(20))
+        );
+
+        // A step to the current time is fine
+        simulator
+            .send_to(&encode(STEP, 2, Duration::from_millis(20)), addr)
+            .unwrap();
+        assert_eq!(
+            source.wait_for_step(timeout).unwrap(),
+            LockstepEvent::Step {
+                seq: 2,
+                time: Duration::from_millis(20)
+            }
+        );
+    }
+
+    #[test]
+    /// Waiting for a step gives up after the timeout
+    fn wait_times_out() {
+        let source = LockstepTimeSource::bind("127.0.0.1:0").unwrap();
+        let err = source
+            .wait_for_step(Some(Duration::from_millis(10)))
+            .unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+        ));
+        // Nothing to acknowledge yet
+        source.ack().unwrap();
+        assert_eq!(source.monotonic(), MonotonicInstant::ZERO);
+    }
+}
@@ -0,0 +1,281 @@
+//! Replay TimeSource
+//!
+//! Plays back the time of a recorded flight, so it can be re-run from recorded logs or
+//! telemetry with time following the recording.
+
+use super::*;
+use crate::logformat::LogReader;
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+use std::time::Instant;
+
+/// TimeSource playing back recorded timestamps.
+///
+/// Playback starts at the first recorded timestamp and runs with the host clock, multiplied
+/// by the playback speed, until the last one. Mission time is the recorded time at the
+/// current playback position. Monotonic time counts the time played, it also advances when
+/// seeking forward but never goes back when seeking backwards, so timers keep working.
+///
+/// * `timestamps`: Recorded timestamps, sorted
+/// * `speed`: Playback speed, 1.0 is real time
+/// * `paused`: Whether playback is paused
+/// * `anchor`: Host time `position` and `played` were last updated at
+/// * `position`: Playback position, time since the first timestamp
+/// * `played`: Monotonic time, total time played
+pub struct ReplayTimeSource {
+    // NOTE: NOT THREADSAFE
+    timestamps: Vec<MissionTime>,
+    speed: Cell<f64>,
+    paused: Cell<bool>,
+    anchor: Cell<Instant>,
+    position: Cell<Duration>,
+    played: Cell<Duration>,
+}
+
+impl ReplayTimeSource {
+    /// Plays back the record timestamps of a Nomad log file, in any log encoding
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_log(BufReader::new(File::open(path)?))
+    }
+
+    /// Plays back the record timestamps of a log read from `reader`. Malformed records are
+    /// skipped, a log whose header cannot be read, e.g. a binary log of another version, is
+    /// an error.
+    pub fn from_log<R: Read>(reader: R) -> io::Result<Self> {
+        let mut reader = LogReader::new(reader, None);
+        let mut timestamps = Vec::new();
+        loop {
+            match reader.next_record() {
+                Ok(Some(record)) => timestamps.push(record.timestamp),
+                Ok(None) => break,
+                // Only malformed records are skipped, the reader moved past them
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        if timestamps.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "recording contains no timestamps",
+            ));
+        }
+        Ok(Self::from_timestamps(timestamps))
+    }
+
+    /// Plays back timestamps extracted by the caller, e.g. from decoded telemetry packets
+    pub fn from_timestamps(mut timestamps: Vec<MissionTime>) -> Self {
+        timestamps.sort_unstable();
+        Self {
+            timestamps,
+            speed: Cell::new(1.0),
+            paused: Cell::new(false),
+            anchor: Cell::new(Instant::now()),
+            position: Cell::new(Duration::ZERO),
+            played: Cell::new(Duration::ZERO),
+        }
+    }
+
+    /// Recorded timestamps, sorted
+    pub fn timestamps(&self) -> &[MissionTime] {
+        &self.timestamps
+    }
+
+    /// First recorded timestamp
+    pub fn start(&self) -> MissionTime {
+        self.timestamps.first().copied().unwrap_or_default()
+    }
+
+    /// Last recorded timestamp
+    pub fn end(&self) -> MissionTime {
+        self.timestamps.last().copied().unwrap_or_default()
+    }
+
+    /// Stops playback, time stands still until `resume`
+    pub fn pause(&self) {
+        self.update();
+        self.paused.set(true);
+    }
+
+    /// Continues a paused playback
+    pub fn resume(&self) {
+        self.update();
+        self.paused.set(false);
+    }
+
+    /// Whether playback is paused
+    pub fn is_paused(&self) -> bool {
+        self.paused.get()
+    }
+
+    /// Whether playback reached the last recorded timestamp
+    pub fn is_finished(&self) -> bool {
+        self.update() >= self.length()
+    }
+
+    /// Current playback speed
+    pub fn speed(&self) -> f64 {
+        self.speed.get()
+    }
+
+    /// Sets the playback speed, e.g. 10 for ten times faster than recorded. Negative or
+    /// non-finite speeds are treated as zero.
+    pub fn set_speed(&self, speed: f64) {
+        self.update();
+        let speed = if speed.is_finite() && speed > 0.0 {
+            speed
+        } else {
+            0.0
+        };
+        self.speed.set(speed);
+    }
+
+    /// Moves playback to `to`, clamped to the recording
+    pub fn seek(&self, to: MissionTime) {
+        let current = self.update();
+        let target = to
+            .clamp(self.start(), self.end())
+            .saturating_duration_since(self.start());
+        if let Some(forward) = target.checked_sub(current) {
+            self.played.set(self.played.get() + forward);
+        }
+        self.position.set(target);
+    }
+
+    /// Number of recorded timestamps played so far, i.e. index of the next one
+    pub fn played_records(&self) -> usize {
+        let now = self.mission_time();
+        self.timestamps.partition_point(|&t| t <= now)
+    }
+
+    /// Length of the recording
+    fn length(&self) -> Duration {
+        self.end().saturating_duration_since(self.start())
+    }
+
+    /// Moves playback forward by the host time since the last update, returning the new
+    /// position
+    fn update(&self) -> Duration {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.anchor.get());
+        self.anchor.set(now);
+
+        let position = self.position.get();
+        if self.paused.get() {
+            return position;
+        }
+        // Huge speeds overflow a Duration, playback then simply runs to the end
+        let advanced = Duration::try_from_secs_f64(elapsed.as_secs_f64() * self.speed.get())
+            .unwrap_or(Duration::MAX)
+            .min(self.length().saturating_sub(position));
+        self.position.set(position + advanced);
+        self.played.set(self.played.get() + advanced);
+        position + advanced
+    }
+}
+
+impl TimeSource for ReplayTimeSource {
+    fn monotonic(&self) -> MonotonicInstant {
+        self.update();
+        MonotonicInstant::from_start(self.played.get())
+    }
+
+    fn mission_time(&self) -> MissionTime {
+        self.start() + self.update()
+    }
+
+    fn health(&self) -> TimeSourceHealth {
+        TimeSourceHealth {
+            valid: !self.timestamps.is_empty(),
+            ..TimeSourceHealth::free_running(TimeSourceKind::Simulated)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logformat::{LogEncoding, encode_record};
+    use nomad_core::{LogLevel, LogRecord, component::ComponentId};
+
+    fn met(secs: i64) -> MissionTime {
+        if secs < 0 {
+            MissionTime::before_epoch(Duration::from_secs(secs.unsigned_abs()))
+        } else {
+            MissionTime::after_epoch(Duration::from_secs(secs as u64))
+        }
+    }
+
+    #[test]
+    /// Timestamps are read from a log, and paused playback can seek around the recording
+    fn replay_from_log() {
+        let mut log = Vec::new();
+        for secs in [-5, 10, 0, 20] {
+            let record = LogRecord {
+                level: LogLevel::Info,
+                component: ComponentId(1),
+                timestamp: met(secs),
+                message: "recorded",
+            };
+            encode_record(&record, LogEncoding::Text, &mut log);
+        }
+        log.extend_from_slice(b"not a record\n");
+
+        let replay = ReplayTimeSource::from_log(log.as_slice()).unwrap();
+        replay.pause();
+        replay.seek(met(-5));
+        assert_eq!(replay.timestamps(), &[met(-5), met(0), met(10), met(20)]);
+        assert_eq!(replay.mission_time(), met(-5));
+        assert_eq!(replay.played_records(), 1);
+
+        // Seeking forward advances monotonic time, seeking back does not rewind it
+        let start = replay.monotonic();
+        replay.seek(met(10));
+        assert_eq!(replay.mission_time(), met(10));
+        assert_eq!(replay.monotonic() - start, Duration::from_secs(15));
+        assert_eq!(replay.played_records(), 3);
+        replay.seek(met(0));
+        assert_eq!(replay.mission_time(), met(0));
+        assert_eq!(replay.monotonic() - start, Duration::from_secs(15));
+
+        // Seeks are clamped to the recording
+        replay.seek(met(100));
+        assert_eq!(replay.mission_time(), met(20));
+        assert!(replay.is_finished());
+
+        assert!(ReplayTimeSource::from_log(&b"garbage\n"[..]).is_err());
+        let old_version = ReplayTimeSource::from_log(&b"NMDLOG\x00\x01 old records"[..]);
+        assert_eq!(
+            old_version.err().map(|e| e.kind()),
+            Some(io::ErrorKind::Unsupported)
+        );
+    }
+
+    #[test]
+    /// Playback follows the host clock times the playback speed, and stops at the end
+    fn replay_speed() {
+        let replay = ReplayTimeSource::from_timestamps(vec![met(0), met(1)]);
+        replay.pause();
+        replay.set_speed(10.0);
+        replay.resume();
+        std::thread::sleep(Duration::from_millis(20));
+        replay.pause();
+        let position = replay.mission_time().to_duration().unwrap();
+        assert!(position >= Duration::from_millis(200), "{position:?}");
+        assert!(!replay.is_finished());
+
+        replay.set_speed(1000.0);
+        replay.resume();
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(replay.is_finished());
+        assert_eq!(replay.mission_time(), met(1));
+        assert_eq!(replay.monotonic().since_start(), Duration::from_secs(1));
+        assert!(replay.health().valid);
+        // Speeds too large for a Duration jump to the end instead of overflowing
+        replay.seek(met(0));
+        replay.set_speed(f64::MAX);
+        std::thread::sleep(Duration::from_millis(1));
+        assert_eq!(replay.mission_time(), met(1));
+    }
+}
@@ -0,0 +1,427 @@
+//! Nomad OSAL Time Sync
+//!
+//! Synchronises the time of two Nomad nodes, e.g. a flight computer and a payload
+//! computer, with a lightweight NTP-style exchange over UDP. The client stamps a request
+//! with its monotonic time, the server answers with its absolute time at receiving the
+//! request and at sending the response, and the client stamps the response on arrival.
+//! From the four timestamps the client computes the round trip delay and the server time,
+//! which is fed into the TimeService correlation.
+//!
+//! Messages are UDP datagrams, little endian:
+//!
+//! * Request (client → server): `['R'][seq: u32][t1 ns: u64]`
+//! * Response (server → client): `['P'][seq: u32][valid: u8][t1 ns: u64][t2 ns: u64][t3 ns: u64]`
+//!
+//! `t1` is client monotonic time at sending the request, echoed by the server. `t2` and
+//! `t3` are server UTC at receiving the request and sending the response, `valid` is zero
+//! when the server has no absolute time.
+
+use core::time::Duration;
+use nomad_core::components::time::TimeService;
+use nomad_core::components::time::correlation::{CorrelationOutcome, CorrelationSample, SyncState};
+use nomad_core::components::time::epoch::EpochStore;
+use nomad_core::time::TimeSource;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::Instant;
+
+const REQUEST: u8 = b'R';
+const RESPONSE: u8 = b'P';
+
+/// Length of a request
+pub const TIME_SYNC_REQUEST_LEN: usize = 13;
+/// Length of a response
+pub const TIME_SYNC_RESPONSE_LEN: usize = 30;
+
+fn nanos(d: Duration) -> u64 {
+    u64::try_from(d.as_nanos()).unwrap_or(u64::MAX)
+}
+
+fn read_u64(buf: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(buf[at..at + 8].try_into().unwrap_or_default())
+}
+
+fn set_timeout(socket: &UdpSocket, timeout: Option<Duration>) -> io::Result<()> {
+    // A zero read timeout is rejected by the OS, treat it as the shortest possible wait
+    socket.set_read_timeout(timeout.map(|t| t.max(Duration::from_micros(1))))
+}
+
+/// Sets the read timeout of `socket` to the time left until `deadline`, None waits
+/// forever. Fails with `TimedOut` once the deadline passed, so datagrams that are skipped
+/// do not extend the wait.
+fn set_deadline(socket: &UdpSocket, deadline: Option<Instant>) -> io::Result<()> {
+    let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+    if remaining == Some(Duration::ZERO) {
+        return Err(io::Error::from(io::ErrorKind::TimedOut));
+    }
+    set_timeout(socket, remaining)
+}
+
+/// Server side of the exchange, answering requests with the time of a TimeSource
+///
+/// * `socket`: Socket requests are received on
+pub struct TimeSyncServer {
+    socket: UdpSocket,
+}
+
+impl TimeSyncServer {
+    /// Binds the socket clients send requests to
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        Ok(Self {
+            socket: UdpSocket::bind(addr)?,
+        })
+    }
+
+    /// Address clients should send requests to
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Waits for one request and answers it with the UTC of `clock`. Waits forever when
+    /// `timeout` is None, a timeout is reported as a `WouldBlock` or `TimedOut` error.
+    /// Unknown datagrams are ignored.
+    pub fn serve<T: TimeSource>(&self, clock: &T, timeout: Option<Duration>) -> io::Result<()> {
+        let deadline = timeout.and_then(|t| Instant::now().checked_add(t));
+        let mut buf = [0u8; 64];
+
+        loop {
+            set_deadline(&self.socket, deadline)?;
+            let (n, from) = self.socket.recv_from(&mut buf)?;
+            let received = clock.utc();
+            if n != TIME_SYNC_REQUEST_LEN || buf[0] != REQUEST {
+                continue;
+            }
+
+            let mut msg = [0u8; TIME_SYNC_RESPONSE_LEN];
+            msg[0] = RESPONSE;
+            msg[1..5].copy_from_slice(&buf[1..5]);
+            msg[6..14].copy_from_slice(&buf[5..13]);
+            let transmit = clock.utc();
+            if let (Some(t2), Some(t3)) = (received, transmit) {
+                msg[5] = 1;
+                msg[14..22].copy_from_slice(&nanos(t2.0).to_le_bytes());
+                msg[22..30].copy_from_slice(&nanos(t3.0).to_le_bytes());
+            }
+            self.socket.send_to(&msg, from)?;
+            return Ok(());
+        }
+    }
+}
+
+/// Result of one exchange with the server
+///
+/// * `sample`: Server UTC at the local monotonic time the response arrived, to feed the
+///   correlation with
+/// * `rtt`: Round trip delay, without the time the server took to answer
+/// * `offset_ns`: Server UTC minus local UTC, None if the local clock has no UTC
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TimeSyncMeasurement {
+    pub sample: CorrelationSample,
+    pub rtt: Duration,
+    pub offset_ns: Option<i64>,
+}
+
+/// State of the synchronisation with the server
+///
+/// * `state`: Synchronisation state of the TimeService correlation
+/// * `offset_ns`: Offset measured by the latest exchange, server minus local UTC
+/// * `rtt`: Round trip delay of the latest exchange
+/// * `exchanges`: Exchanges completed
+/// * `rejected`: Exchanges not fed into the correlation, due to a round trip above the
+///   limit or a time jump
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TimeSyncStatus {
+    pub state: SyncState,
+    pub offset_ns: Option<i64>,
+    pub rtt: Duration,
+    pub exchanges: u32,
+    pub rejected: u32,
+}
+
+/// Client side of the exchange
+///
+/// * `socket`: Socket requests are sent from, connected to the TimeSyncServer
+/// * `seq`: Sequence number of the last request
+/// * `max_rtt`: Exchanges with a longer round trip are too imprecise and not used
+/// * `status`: State of the synchronisation
+pub struct TimeSyncClient {
+    socket: UdpSocket,
+    seq: u32,
+    max_rtt: Duration,
+    status: TimeSyncStatus,
+}
+
+impl TimeSyncClient {
+    /// Creates a client for the server listening at `server`. The socket is connected to
+    /// it, so datagrams from any other sender never reach the client and cannot inject a
+    /// time offset.
+    pub fn connect<A: ToSocketAddrs>(server: A) -> io::Result<Self> {
+        let server = server
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no server address"))?;
+        let local = if server.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        };
+
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(server)?;
+
+        Ok(Self {
+            socket,
+            seq: 0,
+            max_rtt: Duration::from_millis(100),
+            status: TimeSyncStatus {
+                state: SyncState::Unsynchronized,
+                offset_ns: None,
+                rtt: Duration::ZERO,
+                exchanges: 0,
+                rejected: 0,
+            },
+        })
+    }
+
+    /// Sets the longest round trip an exchange may take to be used, 100ms by default
+    pub fn set_max_rtt(&mut self, max_rtt: Duration) {
+        self.max_rtt = max_rtt;
+    }
+
+    /// State of the synchronisation
+    pub fn status(&self) -> TimeSyncStatus {
+        self.status
+    }
+
+    /// Runs one exchange, stamped with `clock`, and waits up to `timeout` for the response.
+    /// Responses to earlier requests that arrive late are skipped. A server without
+    /// absolute time is reported as `InvalidData`.
+    pub fn exchange<T: TimeSource>(
+        &mut self,
+        clock: &T,
+        timeout: Duration,
+    ) -> io::Result<TimeSyncMeasurement> {
+        self.seq = self.seq.wrapping_add(1);
+        let t1 = clock.monotonic();
+        let mut msg = [0u8; TIME_SYNC_REQUEST_LEN];
+        msg[0] = REQUEST;
+        msg[1..5].copy_from_slice(&self.seq.to_le_bytes());
+        msg[5..13].copy_from_slice(&nanos(t1.since_start()).to_le_bytes());
+        self.socket.send(&msg)?;
+        let deadline = Instant::now().checked_add(timeout);
+
+        let mut buf = [0u8; 64];
+        let (n, t4, local_utc) = loop {
+            set_deadline(&self.socket, deadline)?;
+            let n = self.socket.recv(&mut buf)?;
+            let (t4, local_utc) = (clock.monotonic(), clock.utc());
+            if n == TIME_SYNC_RESPONSE_LEN
+                && buf[0] == RESPONSE
+                && buf[1..5] == self.seq.to_le_bytes()
+            {
+                break (n, t4, local_utc);
+            }
+        };
+        let response = &buf[..n];
+        if response[5] == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "server has no absolute time",
+            ));
+        }
+
+        let t2 = i128::from(read_u64(response, 14));
+        let t3 = i128::from(read_u64(response, 22));
+        let local = (t4 - t1).as_nanos() as i128;
+        let rtt = (local - (t3 - t2)).max(0);
+        let external = Duration::from_nanos((t3 + rtt / 2).clamp(0, u64::MAX as i128) as u64);
+        let offset_ns = local_utc.map(|utc| {
+            (external.as_nanos() as i128 - utc.0.as_nanos() as i128)
+                .clamp(i64::MIN.into(), i64::MAX.into()) as i64
+        });
+
+        Ok(TimeSyncMeasurement {
+            sample: CorrelationSample {
+                external,
+                local: t4,
+            },
+            rtt: Duration::from_nanos(rtt as u64),
+            offset_ns,
+        })
+    }
+
+    /// Runs one exchange and feeds the result into the correlation of `service`. Exchanges
+    /// with a round trip above the limit are counted as rejected and not used.
+    pub fn sync<T: TimeSource, S: EpochStore>(
+        &mut self,
+        service: &mut TimeService<T, S>,
+        timeout: Duration,
+    ) -> io::Result<TimeSyncStatus> {
+        let measurement = self.exchange(service, timeout)?;
+        self.status.exchanges = self.status.exchanges.saturating_add(1);
+        self.status.offset_ns = measurement.offset_ns;
+        self.status.rtt = measurement.rtt;
+
+        let applied = measurement.rtt <= self.max_rtt
+            && !matches!(
+                service.add_correlation_sample(measurement.sample),
+                CorrelationOutcome::JumpDetected { .. }
+            );
+        if !applied {
+            self.status.rejected = self.status.rejected.saturating_add(1);
+        }
+        self.status.state = service.correlation_status().state;
+        Ok(self.status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timesource::PosixTimeSource;
+    use nomad_core::time::{MissionTime, MonotonicInstant, TimeConfig, TimeMode, UtcTime};
+    use std::thread;
+
+    /// OS clock running 5s ahead
+    struct Ahead(PosixTimeSource);
+
+    impl TimeSource for Ahead {
+        fn monotonic(&self) -> MonotonicInstant {
+            self.0.monotonic()
+        }
+
+        fn mission_time(&self) -> MissionTime {
+            self.0.mission_time()
+        }
+
+        fn utc(&self) -> Option<UtcTime> {
+            self.0
+                .utc()
+                .map(|utc| UtcTime(utc.0 + Duration::from_secs(5)))
+        }
+    }
+
+    fn os_clock() -> PosixTimeSource {
+        PosixTimeSource::new(&TimeConfig {
+            mode: TimeMode::Real,
+            mission_epoch: None,
+        })
+    }
+
+    #[test]
+    /// A client synchronises its TimeService to a server on another thread
+    fn client_follows_server() {
+        let server = TimeSyncServer::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let handle = thread::spawn(move || {
+            let clock = Ahead(os_clock());
+            for _ in 0..5 {
+                server.serve(&clock, Some(Duration::from_secs(5))).unwrap();
+            }
+        });
+
+        let mut service = TimeService::new(os_clock());
+        let mut client = TimeSyncClient::connect(addr).unwrap();
+        client.set_max_rtt(Duration::from_secs(1));
+        let first = client.sync(&mut service, Duration::from_secs(5)).unwrap();
+        let offset = first.offset_ns.unwrap();
+        assert!((offset - 5_000_000_000).abs() < 50_000_000, "{offset}");
+        assert_eq!(first.state, SyncState::Converging);
+
+        for _ in 0..4 {
+            client.sync(&mut service, Duration::from_secs(5)).unwrap();
+        }
+        handle.join().unwrap();
+
+        let status = client.status();
+        assert_eq!(status.state, SyncState::Synchronized);
+        assert_eq!(status.exchanges, 5);
+        assert_eq!(status.rejected, 0);
+        assert!(status.rtt < Duration::from_secs(1));
+        // Corrected time now follows the server
+        assert!(status.offset_ns.unwrap().abs() < 50_000_000);
+    }
+
+    #[test]
+    /// Servers without absolute time and missing servers are reported as errors
+    fn exchange_errors() {
+        let server = TimeSyncServer::bind("127.0.0.1:0").unwrap();
+        let mut client = TimeSyncClient::connect(server.local_addr().unwrap()).unwrap();
+        let clock = nomad_core::time::SimulatedTimeSource::new();
+
+        let handle = thread::spawn(move || {
+            let clock = nomad_core::time::SimulatedTimeSource::new();
+            server.serve(&clock, Some(Duration::from_secs(5))).unwrap();
+        });
+        let err = client.exchange(&clock, Duration::from_secs(5)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        handle.join().unwrap();
+
+        let err = client
+            .exchange(&clock, Duration::from_millis(10))
+            .unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::ConnectionRefused
+        ));
+    }
+
+    #[test]
+    /// Responses from anyone but the server are ignored, even with the right sequence
+    /// number
+    fn ignores_other_senders() {
+        let fake_server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut client = TimeSyncClient::connect(fake_server.local_addr().unwrap()).unwrap();
+
+        let handle = thread::spawn(move || {
+            let mut buf = [0u8; 64];
+            let (_, from) = fake_server.recv_from(&mut buf).unwrap();
+            let mut forged = [0u8; TIME_SYNC_RESPONSE_LEN];
+            forged[0] = RESPONSE;
+            forged[1..5].copy_from_slice(&1u32.to_le_bytes());
+            forged[5] = 1;
+            let attacker = UdpSocket::bind("127.0.0.1:0").unwrap();
+            attacker.send_to(&forged, from).unwrap();
+        });
+
+        let clock = nomad_core::time::SimulatedTimeSource::new();
+        let err = client
+            .exchange(&clock, Duration::from_millis(100))
+            .unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+        ));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    /// Stale datagrams arriving during an exchange do not extend its timeout
+    fn timeout_not_extended_by_stale_datagrams() {
+        let fake_server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut client = TimeSyncClient::connect(fake_server.local_addr().unwrap()).unwrap();
+
+        let handle = thread::spawn(move || {
+            let mut buf = [0u8; 64];
+            let (_, from) = fake_server.recv_from(&mut buf).unwrap();
+            // Responses to an older request, skipped by the client
+            let stale = [RESPONSE; TIME_SYNC_RESPONSE_LEN];
+            for _ in 0..40 {
+                fake_server.send_to(&stale, from).unwrap();
+                thread::sleep(Duration::from_millis(5));
+            }
+        });
+
+        let clock = nomad_core::time::SimulatedTimeSource::new();
+        let start = Instant::now();
+        let err = client
+            .exchange(&clock, Duration::from_millis(50))
+            .unwrap_err();
+        assert!(start.elapsed() < Duration::from_millis(150));
+        assert!(matches!(
+            err.kind(),
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+        ));
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,89 @@
+//! Time sync between two processes on localhost
+//!
+//! Runs the `time_sync` example as the server process and synchronises a TimeService in
+//! the test process to it.
+
+#![cfg(feature = "time-sync")]
+
+use core::time::Duration;
+use nomad_core::components::time::TimeService;
+use nomad_core::components::time::correlation::SyncState;
+use nomad_core::time::{TimeConfig, TimeMode};
+use nomad_osal::timesource::PosixTimeSource;
+use nomad_osal::timesync::TimeSyncClient;
+use std::io::{BufRead, BufReader};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+
+/// Builds the `time_sync` example, `cargo test` only builds examples when running every
+/// test target. Returns the path of the binary, next to the test binary.
+fn example_path() -> PathBuf {
+    let status = Command::new(env!("CARGO"))
+        .args(["build", "-p", "nomad-osal", "--example", "time_sync"])
+        .args(["--features", "time-sync"])
+        .args((!cfg!(debug_assertions)).then_some("--release"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .status()
+        .unwrap();
+    assert!(status.success(), "building the time_sync example failed");
+
+    let deps = std::env::current_exe().unwrap();
+    let profile = deps.parent().and_then(|d| d.parent()).unwrap();
+    profile
+        .join("examples")
+        .join(format!("time_sync{}", std::env::consts::EXE_SUFFIX))
+}
+
+/// Server process, killed when dropped so a failing test does not leave it running
+struct Server(Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+/// Starts the example as a server on an ephemeral port, returning the address it serves on
+fn start_server() -> (Server, SocketAddr) {
+    let mut child = Command::new(example_path())
+        .args(["server", "127.0.0.1:0"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stdout = child.stdout.take().unwrap();
+    let server = Server(child);
+
+    let mut line = String::new();
+    BufReader::new(stdout).read_line(&mut line).unwrap();
+    let addr = line
+        .trim()
+        .strip_prefix("serving time on ")
+        .and_then(|addr| addr.parse().ok())
+        .unwrap_or_else(|| panic!("unexpected server output: {line:?}"));
+    (server, addr)
+}
+
+#[test]
+/// A client synchronises to a server in another process
+fn client_follows_server_process() {
+    let (_server, addr) = start_server();
+
+    let mut service = TimeService::new(PosixTimeSource::new(&TimeConfig {
+        mode: TimeMode::Real,
+        mission_epoch: None,
+    }));
+    let mut client = TimeSyncClient::connect(addr).unwrap();
+    client.set_max_rtt(Duration::from_secs(1));
+    for _ in 0..5 {
+        client.sync(&mut service, Duration::from_secs(5)).unwrap();
+    }
+
+    let status = client.status();
+    assert_eq!(status.state, SyncState::Synchronized);
+    assert_eq!(status.exchanges, 5);
+    assert_eq!(status.rejected, 0);
+    // Both processes read the same OS clock
+    assert!(status.offset_ns.unwrap().abs() < 50_000_000);
+}